@@ -0,0 +1,54 @@
+#ifndef LUNAR_MAGIC_WRAPPER_FILE_LOCK_H
+#define LUNAR_MAGIC_WRAPPER_FILE_LOCK_H
+
+#include <filesystem>
+#include <optional>
+#include <chrono>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    // Exclusive advisory lock on a ROM file, held until the lock is destroyed, see LunarMagicWrapper::setFileLock.
+    //
+    // Only other handles that ask for the lock are kept out, reading and writing the ROM still works for everyone,
+    // including Lunar Magic. Elsewhere it's a flock on the whole file. On Windows, where locks are mandatory, it's a
+    // LockFileEx of a single byte far past the end of the file, which nothing reads, so the ROM itself stays
+    // accessible. Two handles in the same process exclude each other just like two processes do.
+    class ROMFileLock {
+    private:
+#ifdef _WIN32
+        void* file_handle{};
+
+        explicit ROMFileLock(void* file_handle) : file_handle(file_handle) {}
+#else
+        int fd{ -1 };
+
+        explicit ROMFileLock(int fd) : fd(fd) {}
+#endif
+
+    public:
+        // holds nothing
+        ROMFileLock() = default;
+
+        // Waits up to timeout for the lock, nullopt if someone else still holds it by then. Throws a
+        // std::filesystem::filesystem_error if the ROM can't be opened.
+        static std::optional<ROMFileLock> tryAcquire(const fs::path& rom_path, std::chrono::milliseconds timeout);
+
+        ROMFileLock(const ROMFileLock&) = delete;
+        ROMFileLock& operator=(const ROMFileLock&) = delete;
+
+        ROMFileLock(ROMFileLock&& other) noexcept;
+        ROMFileLock& operator=(ROMFileLock&& other) noexcept;
+
+        ~ROMFileLock();
+
+        [[nodiscard]] bool ownsLock() const;
+
+        // lets go of the lock early, does nothing if it holds none
+        void release();
+    };
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_FILE_LOCK_H
@@ -5,13 +5,33 @@
 #include <utility>
 #include <optional>
 #include <fstream>
+#include <functional>
+#include <mutex>
+#include <exception>
+#include <array>
+#include <string_view>
+#include <chrono>
+#include <set>
+#include <stop_token>
+#include <future>
+#include <atomic>
+#include <memory>
 
 #include "fmt/format.h"
 
+#include "rom.h"
+#include "mwl.h"
+#include "map16.h"
+#include "output_parse.h"
+#include "session.h"
+#include "file_lock.h"
+
 namespace fs = std::filesystem;
 
 namespace lunar_magic_wrapper {
 
+    // Both option types are flags combined with |. Value initialization (LevelExportOption{}) gives NONE, so
+    // they can be default members of config structs.
     enum class LevelExportOption {
         NONE = 0,
         MODIFIED_ONLY = 1
@@ -21,6 +41,10 @@ namespace lunar_magic_wrapper {
         return static_cast<LevelExportOption>(static_cast<int>(lhs) | static_cast<int>(rhs));
     }
 
+    constexpr LevelExportOption operator&(LevelExportOption lhs, LevelExportOption rhs) {
+        return static_cast<LevelExportOption>(static_cast<int>(lhs) & static_cast<int>(rhs));
+    }
+
     enum class LevelImportOption {
         NONE = 0,
         CLEAR_SECONDARY_EXITS = 1
@@ -30,6 +54,41 @@ namespace lunar_magic_wrapper {
         return static_cast<LevelImportOption>(static_cast<int>(lhs) | static_cast<int>(rhs));
     }
 
+    constexpr LevelImportOption operator&(LevelImportOption lhs, LevelImportOption rhs) {
+        return static_cast<LevelImportOption>(static_cast<int>(lhs) & static_cast<int>(rhs));
+    }
+
+    // What happens to the secondary exits of a level that's being overwritten. Lunar Magic 3.40 treats a missing
+    // flags argument the same as 0, so leaving the options out keeps them.
+    enum class SecondaryExitPolicy {
+        KEEP,
+        CLEAR
+    };
+
+    // options with the CLEAR_SECONDARY_EXITS bit set or cleared according to the policy, other bits untouched
+    constexpr LevelImportOption withSecondaryExitPolicy(LevelImportOption options, SecondaryExitPolicy policy) {
+        const auto clear_bit{ static_cast<int>(LevelImportOption::CLEAR_SECONDARY_EXITS) };
+        return static_cast<LevelImportOption>(policy == SecondaryExitPolicy::CLEAR
+            ? static_cast<int>(options) | clear_bit
+            : static_cast<int>(options) & ~clear_bit);
+    }
+
+    // Priority Lunar Magic runs at, see LunarMagicWrapper::setProcessPriority. On Windows these are the
+    // IDLE_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS and NORMAL_PRIORITY_CLASS creation flags, elsewhere a nice
+    // value of 19, 10 and whatever the calling process has.
+    enum class ProcessPriority {
+        IDLE,
+        BELOW_NORMAL,
+        NORMAL
+    };
+
+    // what to do with bytes past the end of an exported file's contents
+    enum class TrailingDataPolicy {
+        IGNORE,
+        ERROR,
+        TRUNCATE
+    };
+
     enum class ROMSize {
         _2MB,
         _3MB,
@@ -44,6 +103,60 @@ namespace lunar_magic_wrapper {
         LC_LZ3
     };
 
+    // one per Lunar Magic command line function
+    enum class Operation {
+        EXPORT_GFX,
+        EXPORT_EXGFX,
+        IMPORT_GFX,
+        IMPORT_EXGFX,
+        IMPORT_ALL_GRAPHICS,
+        EXPORT_LEVEL,
+        IMPORT_LEVEL,
+        IMPORT_MAP16,
+        IMPORT_CUSTOM_PALETTE,
+        EXPORT_SHARED_PALETTE,
+        IMPORT_SHARED_PALETTE,
+        EXPORT_ALL_MAP16,
+        IMPORT_ALL_MAP16,
+        EXPORT_MULTIPLE_LEVELS,
+        IMPORT_MULTIPLE_LEVELS,
+        EXPAND_ROM,
+        CHANGE_COMPRESSION,
+        TRANSFER_GLOBAL_EXANIMATION,
+        TRANSFER_OVERWORLD,
+        TRANSFER_TITLE_SCREEN,
+        TRANSFER_CREDITS,
+        EXPORT_TITLE_MOVES,
+        IMPORT_TITLE_MOVES
+    };
+
+    constexpr std::array<Operation, 23> ALL_OPERATIONS{
+        Operation::EXPORT_GFX, Operation::EXPORT_EXGFX, Operation::IMPORT_GFX, Operation::IMPORT_EXGFX,
+        Operation::IMPORT_ALL_GRAPHICS, Operation::EXPORT_LEVEL, Operation::IMPORT_LEVEL, Operation::IMPORT_MAP16,
+        Operation::IMPORT_CUSTOM_PALETTE, Operation::EXPORT_SHARED_PALETTE, Operation::IMPORT_SHARED_PALETTE,
+        Operation::EXPORT_ALL_MAP16, Operation::IMPORT_ALL_MAP16, Operation::EXPORT_MULTIPLE_LEVELS,
+        Operation::IMPORT_MULTIPLE_LEVELS, Operation::EXPAND_ROM, Operation::CHANGE_COMPRESSION,
+        Operation::TRANSFER_GLOBAL_EXANIMATION, Operation::TRANSFER_OVERWORLD, Operation::TRANSFER_TITLE_SCREEN,
+        Operation::TRANSFER_CREDITS, Operation::EXPORT_TITLE_MOVES, Operation::IMPORT_TITLE_MOVES
+    };
+
+    // version as Lunar Magic prints it, e.g. 3.40 is major_version 3 and minor_version 40
+    struct LunarMagicVersion {
+    public:
+        const int major_version{};
+        const int minor_version{};
+
+        auto operator<=>(const LunarMagicVersion&) const = default;
+
+        [[nodiscard]] std::string toString() const {
+            return fmt::format("{}.{:02}", major_version, minor_version);
+        }
+    };
+
+    // "Lunar Magic <major>.<minor>" banner within the first BANNER_LINES lines, nullopt if there's none
+    constexpr size_t BANNER_LINES{ 5 };
+    std::optional<LunarMagicVersion> parseBanner(const std::vector<std::string>& lines);
+
     struct Result{
     public:
         const std::vector<std::string> out{};
@@ -52,9 +165,247 @@ namespace lunar_magic_wrapper {
         const bool succeeded{};
         const std::optional<int> error_code{};
 
-        Result(int exit_code, std::vector<std::string> out, std::vector<std::string> err)
-        : out(std::move(out)), err(std::move(err)), succeeded(exit_code == 0),
-        error_code(exit_code == 0 ? std::nullopt : std::make_optional(exit_code)) {}
+        // the Lunar Magic function that produced this result
+        const std::optional<Operation> operation{};
+
+        // from the banner some operations print at the top of their output, see parseBanner
+        const std::optional<LunarMagicVersion> detected_version{};
+
+        // lines were dropped from the middle of out or err, see LunarMagicWrapper::setMaxOutputLines
+        const bool truncated{};
+
+        // what the "N bytes written" lines of out add up to, nullopt if there are none, see output_parse
+        const std::optional<uint64_t> bytes_written{};
+
+        Result(int exit_code, std::vector<std::string> out, std::vector<std::string> err,
+               std::optional<Operation> operation = std::nullopt)
+        : Result(exit_code, std::move(out), std::move(err), exit_code == 0, operation) {}
+
+        // for when success is decided by something other than the exit code, see LunarMagicWrapper::setSuccessPolicy
+        Result(int exit_code, std::vector<std::string> out, std::vector<std::string> err, bool succeeded,
+               std::optional<Operation> operation, bool truncated = false)
+        : out(std::move(out)), err(std::move(err)), succeeded(succeeded),
+        error_code(exit_code == 0 ? std::nullopt : std::make_optional(exit_code)), operation(operation),
+        detected_version(parseBanner(this->out)), truncated(truncated),
+        bytes_written(output_parse::totalBytesWritten(this->out)) {}
+
+        [[nodiscard]] std::string joinedOut() const {
+            return output_parse::joined(out);
+        }
+
+        [[nodiscard]] std::string joinedErr() const {
+            return output_parse::joined(err);
+        }
+
+        // first line of out, then of err, containing text, see output_parse::findLine
+        [[nodiscard]] std::optional<std::string> findLine(const std::string& text) const {
+            if (auto line{ output_parse::findLine(out, text) }) {
+                return line;
+            }
+            return output_parse::findLine(err, text);
+        }
+
+        [[nodiscard]] bool containsLine(const std::string& text) const {
+            return findLine(text).has_value();
+        }
+    };
+
+    struct CompressionReport {
+    public:
+        const Result result;
+        const CompressionOption format{};
+
+        const size_t before_bytes{};
+        const size_t after_bytes{};
+
+        // negative if the ROM uses less space after the change
+        [[nodiscard]] std::ptrdiff_t delta() const {
+            return static_cast<std::ptrdiff_t>(after_bytes) - static_cast<std::ptrdiff_t>(before_bytes);
+        }
+    };
+
+    // one format of LunarMagicWrapper::benchmarkCompression, report is of the ROM copy the format was applied to
+    struct CompressionBenchmark {
+    public:
+        const CompressionReport report;
+        const std::chrono::nanoseconds duration{};
+    };
+
+    enum class LevelImportStatus {
+        CREATED,
+        OVERWRITTEN,
+        UNKNOWN
+    };
+
+    struct ImportLevelOutcome {
+    public:
+        const Result result;
+        const LevelImportStatus status{};
+        const std::optional<uint16_t> level_number{};
+    };
+
+    // prediction of whether importing an MWL would fit into a ROM, see LunarMagicWrapper::checkLevelImport
+    struct ImportFeasibility {
+    public:
+        const bool will_fit{};
+        const size_t estimated_bytes{};
+        const size_t free_bytes{};
+    };
+
+    enum class ImportChange {
+        IMPORTED,
+        UNCHANGED
+    };
+
+    // result is the import's, or for UNCHANGED the one of the export the graphics were compared against
+    struct GFXImportOutcome {
+    public:
+        const Result result;
+        const ImportChange change{};
+    };
+
+    // outcome of one ROM in a batch, exactly one of result and error is set
+    struct BatchResult {
+    public:
+        const fs::path rom_path;
+        const std::optional<Result> result{};
+        const std::exception_ptr error{};
+
+        [[nodiscard]] bool succeeded() const {
+            return result.has_value();
+        }
+    };
+
+    // one palette of LunarMagicWrapper::importCustomPalettesFromDirectory, exactly one of result and error is set
+    struct PaletteImportOutcome {
+    public:
+        const uint16_t level_number{};
+        const fs::path palette_path;
+        const std::optional<Result> result{};
+        const std::exception_ptr error{};
+
+        [[nodiscard]] bool succeeded() const {
+            return result.has_value();
+        }
+    };
+
+    // the same operation run by two wrappers, see LunarMagicWrapper::compareWith
+    struct ComparisonResult {
+    public:
+        const Result result;
+        const Result other_result;
+        // one "line N: 'this' vs 'other'" per line of out/err that differs, "nothing" where one has no such line
+        const std::vector<std::string> out_differences{};
+        const std::vector<std::string> err_differences{};
+
+        // both succeeded or both failed, with the same exit code
+        [[nodiscard]] bool sameOutcome() const {
+            return result.succeeded == other_result.succeeded && result.error_code == other_result.error_code;
+        }
+
+        [[nodiscard]] bool identical() const {
+            return sameOutcome() && out_differences.empty() && err_differences.empty();
+        }
+    };
+
+    enum class SummaryFormat {
+        TEXT,
+        JSON
+    };
+
+    // see LunarMagicWrapper::exportLevelWithSummary
+    struct LevelExportWithSummary {
+    public:
+        const Result result;
+        const fs::path mwl_path;
+        const fs::path summary_path;
+        const LevelSummary summary;
+    };
+
+    // one import of LunarMagicWrapper::buildProjectPlan, source is relative to the project directory
+    struct BuildStep {
+    public:
+        const Operation operation{};
+        const fs::path source;
+        // for IMPORT_LEVEL, the level the MWL is imported as
+        const std::optional<uint16_t> level_number{};
+
+        // e.g. "ImportLevel Levels/level 105.mwl as level 105"
+        [[nodiscard]] std::string toString() const;
+    };
+
+    struct BuildPlan {
+    public:
+        const std::vector<BuildStep> steps{};
+
+        // one numbered line per step, in the order they'd run
+        [[nodiscard]] std::string toString() const;
+
+        friend std::ostream& operator<<(std::ostream& stream, const BuildPlan& plan) {
+            return stream << plan.toString();
+        }
+    };
+
+    // how one step of LunarMagicWrapper::buildProject went, err ends with the exception's message for failed steps
+    struct StepResult {
+    public:
+        // BuildStep::toString of the step
+        const std::string name;
+        const bool succeeded{};
+        const std::chrono::nanoseconds duration{};
+        const std::vector<std::string> out{};
+        const std::vector<std::string> err{};
+        const std::optional<Operation> operation{};
+    };
+
+    struct BuildReport {
+    public:
+        const std::vector<StepResult> steps{};
+
+        [[nodiscard]] bool succeeded() const;
+
+        [[nodiscard]] std::vector<StepResult> failures() const;
+
+        // The out and then err lines of every step in order, prefixed with the operation that printed them if
+        // prefixed, e.g. "[ImportLevel] Level 105 imported."
+        [[nodiscard]] std::vector<std::string> mergedLog(bool prefixed = true) const;
+
+        // a summary line, then one line per step with its status and duration, failed steps followed by their err
+        // lines indented
+        [[nodiscard]] std::string toString() const;
+
+        friend std::ostream& operator<<(std::ostream& stream, const BuildReport& report) {
+            return stream << report.toString();
+        }
+    };
+
+    // snapshot of the wrapper's setup for bug reports, see LunarMagicWrapper::diagnostics
+    struct Diagnostics {
+    public:
+        const fs::path lunar_magic_path;
+        const bool lunar_magic_exists{};
+        const bool lunar_magic_is_directory{};
+        // file version from the executable's version resource
+        const std::optional<std::string> lunar_magic_version{};
+        const std::string os;
+        const std::string architecture;
+        const bool shell_available{};
+        const std::vector<std::string> command_prefix{};
+        const std::vector<std::string> command_suffix{};
+        const bool canonicalize_paths{};
+        const std::optional<fs::path> tee_log_path{};
+        const std::optional<MappingMode> expected_mapping_mode{};
+        const bool auto_fix_checksum{};
+        // directory backups are written to, if enabled
+        const std::optional<fs::path> backups{};
+        const std::optional<size_t> minimum_free_space{};
+
+        // one "name: value" line per field
+        [[nodiscard]] std::string toString() const;
+
+        friend std::ostream& operator<<(std::ostream& stream, const Diagnostics& diagnostics) {
+            return stream << diagnostics.toString();
+        }
     };
 
     class LunarMagicWrapperException : public std::runtime_error {
@@ -68,101 +419,1397 @@ namespace lunar_magic_wrapper {
         [[nodiscard]] const Result& getResult() const {
             return result;
         }
+
+        [[nodiscard]] std::optional<Operation> getOperation() const {
+            return result.operation;
+        }
     };
 
-    class LunarMagicWrapper {
-    protected:
-        const fs::path lunar_magic_path;
-        // TODO get lunar magic version and store it
+    // Phrases Lunar Magic prints for failures that get reported as their own exception types, since the exact
+    // wording can differ between versions. Matching is case insensitive and looks at both stdout and stderr.
+    struct OutputMarkers {
+    public:
+        std::string missing_graphics_folder{ "Graphics folder not found" };
+        std::string rom_too_small{ "too small" };
+        // printed when the ROM was last saved by a newer Lunar Magic than the one running
+        std::string newer_rom_format{ "newer version of Lunar Magic" };
 
-        // TODO ensure_version(low, high) function to ensure function is actually available in that LM version (?)
-        // might not be strictly necessary, if it's not available it'll error out anyway
+        [[nodiscard]] static bool contains(const Result& result, const std::string& marker);
 
-        static std::string romSizeToString(ROMSize rom_size);
+        // size mentioned on a rom_too_small line, if Lunar Magic suggested one
+        [[nodiscard]] std::optional<ROMSize> suggestedROMSize(const Result& result) const;
 
-        static std::string compressionOptionToString(CompressionOption compression_option);
+        // the first line containing newer_rom_format, if any
+        [[nodiscard]] std::optional<std::string> newerROMFormatWarning(const Result& result) const;
+    };
 
-        Result inline call(const std::string& call_string) {
-            const auto temp_out{ fs::temp_directory_path() / "lm_wrapper_out.log" };
-            const auto temp_err{ fs::temp_directory_path() / "lm_wrapper_err.log" };
+    class InvalidPathException : public std::runtime_error {
+    private:
+        const fs::path path;
 
-            const auto str{ fmt::format(R"(""{}" {} > "{}" 2> "{}"")",
-                                        lunar_magic_path.string(), call_string,
-                                        temp_out.string(), temp_err.string()) };
+    public:
+        InvalidPathException(const std::string& message, fs::path path)
+        : std::runtime_error(message), path(std::move(path)) {}
 
-            const auto exit_code{ std::system(str.c_str()) };
+        [[nodiscard]] const fs::path& getPath() const {
+            return path;
+        }
+    };
 
-            std::ifstream out_file(temp_out);
-            std::string line;
-            std::vector<std::string> out{};
-            while (std::getline(out_file, line)) {
-                out.push_back(line);
-            }
-            out_file.close();
+    // thrown before running Lunar Magic for level numbers above LunarMagicWrapper::MAX_LEVEL, with every offending
+    // number when a call takes more than one
+    class InvalidLevelException : public std::invalid_argument {
+    private:
+        const std::vector<uint16_t> levels;
 
-            std::ifstream err_file(temp_err);
-            std::vector<std::string> err{};
-            while (std::getline(err_file, line)) {
-                err.push_back(line);
-            }
-            err_file.close();
+    public:
+        InvalidLevelException(const std::string& message, std::vector<uint16_t> levels)
+        : std::invalid_argument(message), levels(std::move(levels)) {}
 
-            return { exit_code, out, err };
+        [[nodiscard]] const std::vector<uint16_t>& getLevels() const {
+            return levels;
         }
+    };
+
+    // thrown when neither the system temp directory nor any fallback location can hold a temporary directory
+    class TempDirectoryException : public std::runtime_error {
+    private:
+        const std::error_code code;
 
     public:
-        explicit LunarMagicWrapper(fs::path lunar_magic_path) : lunar_magic_path(std::move(lunar_magic_path)) {}
+        explicit TempDirectoryException(const std::string& message, std::error_code code = {})
+        : std::runtime_error(message), code(code) {}
 
-        Result exportGFX(const fs::path& rom_path);
+        // for code that creates temporary files of its own next to Lunar Magic calls
+        explicit TempDirectoryException(const fs::filesystem_error& error)
+        : std::runtime_error(error.what()), code(error.code()) {}
 
-        Result exportExGFX(const fs::path& rom_path);
+        // of the last location that was tried, empty if the error didn't come from the operating system
+        [[nodiscard]] std::error_code getErrorCode() const {
+            return code;
+        }
+    };
 
-        Result importGFX(const fs::path& rom_path);
+    // thrown instead of running Lunar Magic when its configured path can't be it, e.g. a directory without the
+    // executable in it
+    class LunarMagicMissingException : public InvalidPathException {
+    public:
+        LunarMagicMissingException(const std::string& message, fs::path path)
+        : InvalidPathException(message, std::move(path)) {}
+    };
 
-        Result importExGFX(const fs::path& rom_path);
+    // thrown instead of running Lunar Magic when a file it's supposed to import doesn't exist, or isn't the kind of
+    // file it's passed as
+    class MissingInputFileException : public InvalidPathException {
+    public:
+        MissingInputFileException(const std::string& message, fs::path path)
+        : InvalidPathException(message, std::move(path)) {}
+    };
 
-        Result importAllGraphics(const fs::path& rom_path);
+    // thrown instead of running Lunar Magic when a map16 file to import fails Map16File::validate, importing it
+    // would corrupt tiles
+    class InvalidMap16Exception : public InvalidPathException {
+    public:
+        InvalidMap16Exception(const std::string& message, fs::path path)
+        : InvalidPathException(message, std::move(path)) {}
+    };
 
-        Result exportLevel(const fs::path& rom_path, const fs::path& mwl_path, uint16_t level_number);
+    // thrown instead of running Lunar Magic when a title moves file isn't a ZSNES savestate
+    class InvalidTitleMovesException : public InvalidPathException {
+    public:
+        InvalidTitleMovesException(const std::string& message, fs::path path)
+        : InvalidPathException(message, std::move(path)) {}
+    };
 
-        Result importLevel(const fs::path& rom_path, const fs::path& mwl_path,
-                         std::optional<uint16_t> level_number = std::nullopt);
+    // MWL whose file name doesn't say which level it is, see LunarMagicWrapper::checkLevelFileNames
+    struct LevelFileNameMismatch {
+    public:
+        const fs::path path;
+        // nullopt if the file name doesn't end in a level number at all
+        const std::optional<uint16_t> file_name_level{};
+        const uint16_t embedded_level{};
+    };
 
-        Result importMap16(const fs::path& rom_path, const fs::path& map16_path, uint16_t level_number,
-                        std::optional<std::pair<size_t, size_t>> coordinates = std::nullopt);
+    class LevelFileNameMismatchException : public std::runtime_error {
+    private:
+        const std::vector<LevelFileNameMismatch> mismatches;
 
-        Result importCustomPalette(const fs::path& rom_path, const fs::path& palette_path, uint16_t level_number);
+    public:
+        LevelFileNameMismatchException(const std::string& message, std::vector<LevelFileNameMismatch> mismatches)
+        : std::runtime_error(message), mismatches(std::move(mismatches)) {}
 
-        Result exportSharedPalette(const fs::path& rom_path, const fs::path& shared_palette_path);
+        [[nodiscard]] const std::vector<LevelFileNameMismatch>& getMismatches() const {
+            return mismatches;
+        }
+    };
 
-        Result importSharedPalette(const fs::path& rom_path, const fs::path& shared_palette_path);
+    // thrown before running Lunar Magic on a ROM whose mapping mode isn't the one set through
+    // LunarMagicWrapper::setExpectedMappingMode
+    class UnexpectedMappingException : public std::runtime_error {
+    private:
+        const fs::path rom_path;
+        const MappingMode actual;
 
-        Result exportAllMap16(const fs::path& rom_path, const fs::path& all_map16_path);
+    public:
+        UnexpectedMappingException(const std::string& message, fs::path rom_path, MappingMode actual)
+        : std::runtime_error(message), rom_path(std::move(rom_path)), actual(actual) {}
 
-        Result importAllMap16(const fs::path& rom_path, const fs::path& all_map16_path);
+        [[nodiscard]] const fs::path& getROMPath() const {
+            return rom_path;
+        }
 
-        Result exportMultipleLevels(const fs::path& rom_path, const fs::path& directory_path,
-                                 const std::string& file_name_start,
-                                 std::optional<LevelExportOption> options = std::nullopt);
+        [[nodiscard]] MappingMode getActual() const {
+            return actual;
+        }
+    };
 
-        Result importMultipleLevels(const fs::path& rom_path, const fs::path& directory_path,
-                                 std::optional<LevelImportOption> options = std::nullopt);
+    // thrown instead of running Lunar Magic when another process or handle kept the ROM locked for longer than the
+    // timeout, see LunarMagicWrapper::setFileLock
+    class ROMLockedException : public std::runtime_error {
+    private:
+        const fs::path rom_path;
 
-        Result expandROM(const fs::path& rom_path, ROMSize rom_size);
+    public:
+        ROMLockedException(const std::string& message, fs::path rom_path)
+        : std::runtime_error(message), rom_path(std::move(rom_path)) {}
 
-        Result changeCompression(const fs::path& rom_path, CompressionOption option);
+        [[nodiscard]] const fs::path& getROMPath() const {
+            return rom_path;
+        }
+    };
 
-        Result transferGlobalExanimation(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+    // thrown when an operation's stop token was triggered, the Result holds what Lunar Magic printed until then
+    class CancelledException : public LunarMagicWrapperException {
+    public:
+        CancelledException(const std::string& message, Result result)
+        : LunarMagicWrapperException(message, std::move(result)) {}
+    };
 
-        Result transferOverworld(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+    // thrown by LunarMagicWrapper::expandROM before running Lunar Magic when the ROM's mapping can't take the size,
+    // i.e. an SA-1 size for a ROM that hasn't been converted to SA-1
+    class IncompatibleExpansionException : public std::runtime_error {
+    private:
+        const MappingMode from;
+        const ROMSize to;
 
-        Result transferTitleScreen(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+    public:
+        IncompatibleExpansionException(const std::string& message, MappingMode from, ROMSize to)
+        : std::runtime_error(message), from(from), to(to) {}
 
-        Result transferCredits(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+        [[nodiscard]] MappingMode getFrom() const {
+            return from;
+        }
 
-        Result exportTitleMoves(const fs::path& rom_path, const fs::path& title_moves_path);
+        [[nodiscard]] ROMSize getTo() const {
+            return to;
+        }
+    };
 
-        Result importTitleMoves(const fs::path& rom_path, const fs::path& title_moves_path);
+    class MissingGraphicsFolderException : public LunarMagicWrapperException {
+    private:
+        const fs::path expected_path;
+
+    public:
+        MissingGraphicsFolderException(const std::string& message, Result result, fs::path expected_path)
+        : LunarMagicWrapperException(message, std::move(result)), expected_path(std::move(expected_path)) {}
+
+        [[nodiscard]] const fs::path& getExpectedPath() const {
+            return expected_path;
+        }
+    };
+
+    // thrown before importing GFX from a Graphics folder that's missing files of an export, see
+    // LunarMagicWrapper::setCheckExportedGraphics
+    class NoExportedGraphicsException : public InvalidPathException {
+    private:
+        const std::vector<std::string> missing_files;
+
+    public:
+        NoExportedGraphicsException(const std::string& message, fs::path path, std::vector<std::string> missing_files)
+        : InvalidPathException(message, std::move(path)), missing_files(std::move(missing_files)) {}
+
+        // file names as Lunar Magic exports them, e.g. GFX00.bin
+        [[nodiscard]] const std::vector<std::string>& getMissingFiles() const {
+            return missing_files;
+        }
+    };
+
+    // thrown once an operation has succeeded but left less free space in the ROM than required, see
+    // LunarMagicWrapper::setFreeSpaceGuard
+    class OutOfSpaceException : public LunarMagicWrapperException {
+    private:
+        const size_t used;
+        const size_t capacity;
+
+    public:
+        OutOfSpaceException(const std::string& message, Result result, size_t used, size_t capacity)
+        : LunarMagicWrapperException(message, std::move(result)), used(used), capacity(capacity) {}
+
+        [[nodiscard]] size_t getUsed() const {
+            return used;
+        }
+
+        [[nodiscard]] size_t getCapacity() const {
+            return capacity;
+        }
+    };
+
+    // Thrown when Lunar Magic stopped producing output for longer than the prompt timeout while still running, which
+    // almost always means it's waiting on a dialog nobody will answer. It has been killed by then, the Result holds
+    // whatever it printed before. See LunarMagicWrapper::setPromptTimeout.
+    class InteractivePromptException : public LunarMagicWrapperException {
+    private:
+        const std::chrono::milliseconds stalled_for;
+
+    public:
+        InteractivePromptException(const std::string& message, Result result, std::chrono::milliseconds stalled_for)
+        : LunarMagicWrapperException(message, std::move(result)), stalled_for(stalled_for) {}
+
+        [[nodiscard]] std::chrono::milliseconds getStalledFor() const {
+            return stalled_for;
+        }
+    };
+
+    // thrown instead of running Lunar Magic once the calls so far took longer than allowed, see
+    // LunarMagicWrapper::setTimeBudget
+    class BudgetExceededException : public std::runtime_error {
+    private:
+        const std::chrono::milliseconds budget;
+        const std::chrono::milliseconds spent;
+
+    public:
+        BudgetExceededException(const std::string& message, std::chrono::milliseconds budget,
+                                std::chrono::milliseconds spent)
+        : std::runtime_error(message), budget(budget), spent(spent) {}
+
+        [[nodiscard]] std::chrono::milliseconds getBudget() const {
+            return budget;
+        }
+
+        [[nodiscard]] std::chrono::milliseconds getSpent() const {
+            return spent;
+        }
+    };
+
+    // thrown after an export whose file doesn't have the size its contents say it should, see
+    // LunarMagicWrapper::setTrailingDataPolicy
+    class MalformedExportException : public LunarMagicWrapperException {
+    private:
+        const fs::path path;
+        const size_t expected_size;
+        const size_t actual_size;
+
+    public:
+        MalformedExportException(const std::string& message, Result result, fs::path path, size_t expected_size,
+                                 size_t actual_size)
+        : LunarMagicWrapperException(message, std::move(result)), path(std::move(path)),
+        expected_size(expected_size), actual_size(actual_size) {}
+
+        [[nodiscard]] const fs::path& getPath() const {
+            return path;
+        }
+
+        [[nodiscard]] size_t getExpectedSize() const {
+            return expected_size;
+        }
+
+        [[nodiscard]] size_t getActualSize() const {
+            return actual_size;
+        }
+    };
+
+    // Thrown when Lunar Magic warned that the ROM was saved by a newer version than the one running, since the older
+    // one may silently drop or corrupt data it doesn't know about. Lunar Magic only warns once it has written the ROM
+    // already, restoring it is up to the caller, see setBackupBeforeWrite. See OutputMarkers::newer_rom_format.
+    class NewerROMFormatException : public LunarMagicWrapperException {
+    private:
+        const fs::path rom_path;
+        const std::string warning;
+
+    public:
+        NewerROMFormatException(const std::string& message, Result result, fs::path rom_path, std::string warning)
+        : LunarMagicWrapperException(message, std::move(result)), rom_path(std::move(rom_path)),
+        warning(std::move(warning)) {}
+
+        [[nodiscard]] const fs::path& getROMPath() const {
+            return rom_path;
+        }
+
+        // the line Lunar Magic printed
+        [[nodiscard]] const std::string& getWarning() const {
+            return warning;
+        }
+    };
+
+    class NeedsExpansionException : public LunarMagicWrapperException {
+    private:
+        const size_t current_size;
+        const std::optional<ROMSize> suggested_size;
+
+    public:
+        NeedsExpansionException(const std::string& message, Result result, size_t current_size,
+                                std::optional<ROMSize> suggested_size)
+        : LunarMagicWrapperException(message, std::move(result)), current_size(current_size),
+        suggested_size(suggested_size) {}
+
+        // without copier header
+        [[nodiscard]] size_t getCurrentSize() const {
+            return current_size;
+        }
+
+        // Lunar Magic's suggestion if it made one, otherwise the next size up (none past 4MB, since going
+        // further requires SA-1)
+        [[nodiscard]] std::optional<ROMSize> getSuggestedSize() const {
+            return suggested_size;
+        }
+    };
+
+    class ROMOperations;
+
+    // Operations can run concurrently on one instance as long as nothing reconfigures it meanwhile, but not on the
+    // same ROM, see "Thread safety" in the README
+    class LunarMagicWrapper {
+    public:
+        using BeforeHook = std::function<void(const std::string& call_string)>;
+        using AfterHook = std::function<void(const std::string& call_string, const Result& result)>;
+        using LevelPathTemplate = std::function<fs::path(uint16_t level_number)>;
+        using BackupHook = std::function<void(const fs::path& rom_path, const fs::path& backup_path)>;
+        using SuccessPolicy = std::function<bool(Operation operation, int exit_code,
+                                                 const std::vector<std::string>& out,
+                                                 const std::vector<std::string>& err)>;
+        using ActivityHook = std::function<void()>;
+        // only the exit code, out and err of the returned Result are used
+        using Spawner = std::function<Result(const std::string& call_string)>;
+
+    protected:
+        const fs::path lunar_magic_path;
+
+        std::vector<BeforeHook> before_hooks{};
+        std::vector<AfterHook> after_hooks{};
+
+        OutputMarkers output_markers{};
+        ClassifierPatterns classifier_patterns{};
+
+        std::vector<std::string> command_prefix{};
+        std::vector<std::string> command_suffix{};
+
+        bool canonicalize_paths{};
+
+        std::optional<fs::path> tee_log_path{};
+        std::optional<MappingMode> expected_mapping_mode{};
+        bool auto_fix_checksum{};
+        bool backup_before_write{};
+        std::optional<fs::path> backup_directory{};
+        std::vector<BackupHook> backup_hooks{};
+        SuccessPolicy success_policy{};
+        Spawner spawner{};
+        TrailingDataPolicy trailing_data_policy{ TrailingDataPolicy::IGNORE };
+        std::vector<ActivityHook> activity_hooks{};
+        std::chrono::milliseconds activity_interval{ 100 };
+        std::optional<std::chrono::milliseconds> prompt_timeout{};
+        std::optional<std::chrono::milliseconds> time_budget{};
+        // shared with copies, see setTimeBudget
+        std::shared_ptr<std::atomic<std::chrono::nanoseconds::rep>> time_spent{
+            std::make_shared<std::atomic<std::chrono::nanoseconds::rep>>()
+        };
+        std::string coordinate_separator{ "," };
+        std::optional<uint16_t> code_page{};
+        bool hide_window{ true };
+        ProcessPriority process_priority{ ProcessPriority::NORMAL };
+        bool collapse_repeated_lines{};
+        std::optional<size_t> max_output_lines{};
+        bool echo_commands{};
+        bool timestamp_output{};
+        std::optional<LevelExportOption> default_level_export_options{};
+        std::optional<LevelImportOption> default_level_import_options{};
+        bool check_level_file_names{};
+        bool check_exported_graphics{};
+        bool create_output_directories{ true };
+        std::optional<std::chrono::milliseconds> file_lock_timeout{};
+        std::optional<size_t> minimum_free_space{};
+        // TODO get lunar magic version and store it
+
+        // TODO ensure_version(low, high) function to ensure function is actually available in that LM version (?)
+        // might not be strictly necessary, if it's not available it'll error out anyway
+
+        static std::string romSizeToString(ROMSize rom_size);
+
+        static std::string compressionOptionToString(CompressionOption compression_option);
+
+        // Fresh, uniquely named directory under the system temp directory, or if that's unavailable or not
+        // writable, under the first of the fallbacks where one can be created. Throws a TempDirectoryException if
+        // there's nowhere to create one. The caller is responsible for removing it, see TempDirectory.
+        static fs::path makeTempDirectory(const std::vector<fs::path>& fallbacks = {});
+
+        // owns a directory from makeTempDirectory and removes it however the scope holding it is left
+        class TempDirectory {
+        public:
+            explicit TempDirectory(fs::path directory);
+            ~TempDirectory();
+
+            TempDirectory(const TempDirectory&) = delete;
+            TempDirectory& operator=(const TempDirectory&) = delete;
+
+            [[nodiscard]] const fs::path& path() const;
+
+        private:
+            const fs::path directory;
+        };
+
+        // directory holding the GFX files somewhere under root: a "Graphics" folder (the shallowest one, in any
+        // case), or root itself if the GFX files are directly in it
+        static std::optional<fs::path> findGraphicsFolder(const fs::path& root);
+
+        // extracts with the system's tar, or unzip for .zip files outside of Windows, whose tar reads zips too
+        static void extractArchive(const fs::path& archive_path, const fs::path& destination);
+
+        // "a.b.c.d" from the VS_FIXEDFILEINFO in a Windows executable's version resource, found by its signature
+        static std::optional<std::string> readFileVersion(const fs::path& executable_path);
+
+        // packs the directory's contents, the format follows the archive's extension
+        static void createArchive(const fs::path& directory_path, const fs::path& archive_path);
+
+        // level number and path of every "<file_name_start><level>.mwl" in the directory, sorted by level, matching
+        // both the start and the extension ignoring case
+        static std::vector<std::pair<uint16_t, fs::path>> exportedLevels(const fs::path& directory_path,
+                                                                         const std::string& file_name_start);
+
+        // moves "<file_name_start><level>.mwl" files from the directory to their templated paths
+        static void placeExportedLevels(const fs::path& directory_path, const std::string& file_name_start,
+                                        const LevelPathTemplate& path_for_level);
+
+        // checks for failures Lunar Magic reports in its output that get their own exception type, should be
+        // called by every operation that writes to a ROM
+        void throwIfKnownFailure(const fs::path& rom_path, const Result& result) const;
+
+        void throwIfGraphicsFolderMissing(const fs::path& rom_path, const Result& result) const;
+
+        // does nothing unless enabled through setCheckExportedGraphics
+        void throwIfNoExportedGraphics(const fs::path& rom_path) const;
+
+        // reads the ROM's header before a mutating operation, does nothing unless an expected mode is set
+        void throwIfUnexpectedMapping(const fs::path& rom_path) const;
+
+        // throws an InvalidTitleMovesException unless the file starts with ZST_SIGNATURE and is large enough to
+        // hold the savestate's WRAM
+        static void throwIfInvalidTitleMoves(const fs::path& title_moves_path);
+
+        // applies the trailing data policy to an exported file, files of unknown size are left alone
+        void checkExportSize(const fs::path& path, const Result& result) const;
+
+        // throws an InvalidLevelException naming all levels above MAX_LEVEL, if there are any
+        static void throwIfInvalidLevels(const std::vector<uint16_t>& levels);
+
+        // throws a MissingInputFileException if the file doesn't exist or, if given, has a different extension
+        static void throwIfMissingInput(const fs::path& path, const std::string& description,
+                                        const std::optional<std::string>& extension = std::nullopt);
+
+        // throws an InvalidPathException unless the path has one of ROM::EXTENSIONS
+        static void throwIfNotROM(const fs::path& rom_path);
+
+        // throwIfMissingInput, then an InvalidMap16Exception if the file fails Map16File::validate
+        static void throwIfInvalidMap16(const fs::path& map16_path);
+
+        // Called by every operation that writes to a ROM, before running Lunar Magic and once it has succeeded. The
+        // returned lock, which holds nothing unless setFileLock is on, has to be kept until afterWrite is done.
+        [[nodiscard]] ROMFileLock beforeWrite(const fs::path& rom_path) const;
+
+        void afterWrite(const fs::path& rom_path, const Result& result) const;
+
+        // "<rom file name>.<UTC yyyymmdd-hhmmssmmm>.bak" in the backup directory
+        [[nodiscard]] fs::path makeBackupPath(const fs::path& rom_path) const;
+
+        // path as it's passed to Lunar Magic, canonicalized if enabled
+        [[nodiscard]] std::string resolvePath(const fs::path& path, bool must_exist) const;
+
+        [[nodiscard]] std::string inputPath(const fs::path& path) const {
+            return resolvePath(path, true);
+        }
+
+        // creates the path's parent directory first if enabled, Lunar Magic doesn't and fails with a vague error
+        [[nodiscard]] std::string outputPath(const fs::path& path) const;
+
+        // appends the call's output to tee_log_path, if set
+        void teeLog(const std::string& call_string, const Result& result) const;
+
+        // lunar_magic_path, or the executable inside it if it's Lunar Magic's directory, which is an easy mistake
+        // to make. Throws a LunarMagicMissingException for a directory without one.
+        fs::path executablePath() const;
+
+        // full line handed to the shell, including the configured prefix/suffix and output redirection
+        [[nodiscard]] std::string buildCommand(const std::string& call_string, const fs::path& out_path,
+                                               const fs::path& err_path) const;
+
+        // Argument list for starting Lunar Magic without going through the shell, including the configured
+        // prefix/suffix. nullopt if anything in the call contains a character the shell would interpret, in which
+        // case the call has to go through buildCommand to behave the same.
+        [[nodiscard]] std::optional<std::vector<std::string>> directArguments(const std::string& call_string) const;
+
+        // starts arguments[0] with stdout/stderr written to the given files and waits for it, returning the same
+        // exit code std::system would have, -1 if it couldn't be waited on, or nullopt if the process couldn't be
+        // started. hide_window only matters on Windows. Requesting a stop kills the process along with any it
+        // started, through a job object on Windows and a process group of its own elsewhere.
+        static std::optional<int> runDirectly(const std::vector<std::string>& arguments, const fs::path& out_path,
+                                              const fs::path& err_path, bool hide_window, ProcessPriority priority,
+                                              std::stop_token stop = {});
+
+#ifdef _WIN32
+        // priority class among the creation flags runDirectly passes to CreateProcessW
+        static uint32_t priorityCreationFlags(ProcessPriority priority);
+#else
+        // nice value runDirectly's child process sets before starting Lunar Magic, nullopt to leave it alone
+        static std::optional<int> niceValue(ProcessPriority priority);
+#endif
+
+        // the map16 location argument, X and Y in hex joined by the coordinate separator, e.g. "10,2"
+        [[nodiscard]] std::string coordinatesToString(std::pair<size_t, size_t> coordinates) const;
+
+        // where call() creates its temporary directory if the system temp directory can't be used: the directory
+        // of the ROM (the first argument), then the tee log's directory
+        [[nodiscard]] std::vector<fs::path> tempFallbacks(const std::string& arguments) const;
+
+        // throws a CancelledException if cancel is triggered, see exportGFX
+        Result call(Operation operation, const std::string& arguments, std::stop_token cancel = {});
+
+        // process wide, so operations on the same ROM from different threads (or wrappers) don't overlap
+        static std::mutex& romMutex(const fs::path& rom_path);
+
+        using Transfer = Result (LunarMagicWrapper::*)(const fs::path& source_rom_path,
+                                                       const fs::path& destination_rom_path);
+
+        // For data Lunar Magic can only transfer between ROMs: transfers it from the ROM into a copy of the clean ROM
+        // and writes the bytes that differ from transferredBaseline to the file, see ROM::writeChanges. Whatever
+        // Lunar Magic writes on every transfer (its hacks, expansion, checksum) is in both and stays out of the file.
+        Result exportThroughTransfer(Transfer transfer, const fs::path& rom_path, const fs::path& changes_path,
+                                     const fs::path& clean_rom_path);
+
+        // Applies the changes to transferredBaseline and transfers from there into the ROM, which only gets what
+        // the transfer copies, like transferring from the ROM the file was exported from
+        Result importThroughTransfer(Transfer transfer, const fs::path& rom_path, const fs::path& changes_path,
+                                     const fs::path& clean_rom_path, const std::string& description);
+
+        // copy of the clean ROM in the directory with the clean ROM's own data transferred into it
+        [[nodiscard]] fs::path transferredBaseline(Transfer transfer, const fs::path& clean_rom_path,
+                                                   const fs::path& directory_path) const;
+
+        // copy of this wrapper for writing to temporary ROM copies, without backup, checksum fix or space check
+        [[nodiscard]] LunarMagicWrapper stagedWrapper() const;
+
+    public:
+        // name of the command line function without the leading dash, e.g. "ExportGFX"
+        static std::string operationToString(Operation operation);
+
+        explicit LunarMagicWrapper(fs::path lunar_magic_path) : lunar_magic_path(std::move(lunar_magic_path)) {}
+
+        constexpr static std::array<std::string_view, 2> EXECUTABLE_NAMES{ "lunar_magic.exe", "Lunar Magic.exe" };
+
+        // Looks for Lunar Magic, in order:
+        //   1. every directory on PATH
+        //   2. "Lunar Magic" folders in %ProgramFiles%, %ProgramFiles(x86)% and %LOCALAPPDATA%
+        //   3. C:/Lunar Magic
+        // checking each directory for any of EXECUTABLE_NAMES. Lunar Magic is a portable executable that doesn't
+        // record an install location in the registry or an INI file, so there's nothing else to consult.
+        static std::optional<fs::path> findLunarMagic();
+
+        static std::optional<LunarMagicWrapper> fromDefaultLocation();
+
+        // Hooks run in registration order around every Lunar Magic invocation, with the arguments passed to it.
+        // Before hooks run right before Lunar Magic is started, after hooks run once its output has been read
+        // but before the operation decides whether it failed, so they also see failing results. Hooks may be
+        // called from multiple threads if the wrapper is shared, so they must be thread-safe themselves.
+        LunarMagicWrapper& beforeEach(BeforeHook hook) {
+            before_hooks.push_back(std::move(hook));
+            return *this;
+        }
+
+        LunarMagicWrapper& afterEach(AfterHook hook) {
+            after_hooks.push_back(std::move(hook));
+            return *this;
+        }
+
+        // Tokens placed before Lunar Magic's path (e.g. a launcher or compatibility shim that runs it) and after
+        // its arguments, on every invocation. Each token is quoted, so a token can't contain a double quote.
+        LunarMagicWrapper& setCommandPrefix(std::vector<std::string> prefix) {
+            command_prefix = std::move(prefix);
+            return *this;
+        }
+
+        LunarMagicWrapper& setCommandSuffix(std::vector<std::string> suffix) {
+            command_suffix = std::move(suffix);
+            return *this;
+        }
+
+        // Resolves every path to an absolute one without '.' or '..' segments before passing it to Lunar Magic,
+        // so relative paths don't depend on the working directory. Paths Lunar Magic reads from have to exist,
+        // otherwise an InvalidPathException is thrown before Lunar Magic is run. Off by default.
+        LunarMagicWrapper& setCanonicalizePaths(bool canonicalize) {
+            canonicalize_paths = canonicalize;
+            return *this;
+        }
+
+        // Appends the output of every call to this file, successful or not, each preceded by a header line with
+        // the arguments and exit code. Output is still returned in the Result as usual. std::nullopt turns it off.
+        LunarMagicWrapper& setTeeLogPath(std::optional<fs::path> path) {
+            tee_log_path = std::move(path);
+            return *this;
+        }
+
+        // Checks the ROM's mapping mode before every operation that writes to it and throws an
+        // UnexpectedMappingException on a mismatch, without running Lunar Magic. SMW is MappingMode::LOROM,
+        // ROMs expanded to 6MB/8MB are MappingMode::SA1. std::nullopt turns the check off, which is the default.
+        LunarMagicWrapper& setExpectedMappingMode(std::optional<MappingMode> mode) {
+            expected_mapping_mode = mode;
+            return *this;
+        }
+
+        // Recomputes the ROM's checksum after every successful operation that writes to it, so emulators that
+        // validate it accept the ROM. Those are all imports (including importGFXFromArchive and insertExGFX),
+        // expandROM, changeCompression and the transfers, whose destination ROM is fixed. Off by default.
+        LunarMagicWrapper& setAutoFixChecksum(bool fix) {
+            auto_fix_checksum = fix;
+            return *this;
+        }
+
+        // Copies the ROM to a timestamped backup before every operation that writes to it (the same ones
+        // setAutoFixChecksum applies to), never for exports. Backups go next to the ROM unless a backup directory
+        // is set. Off by default.
+        LunarMagicWrapper& setBackupBeforeWrite(bool backup) {
+            backup_before_write = backup;
+            return *this;
+        }
+
+        LunarMagicWrapper& setBackupDirectory(std::optional<fs::path> directory) {
+            backup_directory = std::move(directory);
+            return *this;
+        }
+
+        // Runs every activity interval while Lunar Magic is running, e.g. to advance a spinner. With any of these
+        // registered, Lunar Magic is waited for on a separate thread so the hooks can run on the calling one.
+        LunarMagicWrapper& onActivity(ActivityHook hook) {
+            activity_hooks.push_back(std::move(hook));
+            return *this;
+        }
+
+        // 100ms by default
+        LunarMagicWrapper& setActivityInterval(std::chrono::milliseconds interval) {
+            activity_interval = interval;
+            return *this;
+        }
+
+        // What goes between X and Y of importMap16's coordinates, which are always written in hex without prefix.
+        // "," by default, which gives the X,Y form Lunar Magic's usage text shows. A separator containing whitespace
+        // splits the location into two arguments.
+        LunarMagicWrapper& setCoordinateSeparator(std::string separator) {
+            coordinate_separator = std::move(separator);
+            return *this;
+        }
+
+        // Windows only: runs Lunar Magic through cmd.exe with "chcp <code_page> >nul &&" in front, so it prints in
+        // a known code page instead of whatever the console was set to. Output is captured as raw bytes either
+        // way, so e.g. 65001 gives UTF-8 lines. Every call goes through the shell while this is set. Throws
+        // std::invalid_argument for 0. Ignored elsewhere, off by default.
+        LunarMagicWrapper& setCodePage(std::optional<uint16_t> page) {
+            if (page == 0) {
+                throw std::invalid_argument("0 is not a valid code page");
+            }
+
+            code_page = page;
+            return *this;
+        }
+
+        // Treats Lunar Magic as stuck on an interactive prompt when it's still running but neither its stdout nor
+        // its stderr grew for this long: it's killed and the call throws an InteractivePromptException instead of
+        // waiting forever. Only applies when Lunar Magic is started directly, not through the shell or a spawner.
+        // Off by default, since some operations legitimately take a while without printing anything.
+        LunarMagicWrapper& setPromptTimeout(std::optional<std::chrono::milliseconds> timeout) {
+            prompt_timeout = timeout;
+            return *this;
+        }
+
+        // Takes a ROMFileLock on the ROM for the whole of every operation that writes to it, backup and checksum
+        // fix included, so other tools that lock the ROM the same way wait for it or are told it's busy. Waits up
+        // to timeout for a lock someone else holds and throws a ROMLockedException after that. Tools that don't
+        // ask for the lock aren't stopped from writing. std::nullopt, the default, takes no lock.
+        LunarMagicWrapper& setFileLock(std::optional<std::chrono::milliseconds> timeout) {
+            file_lock_timeout = timeout;
+            return *this;
+        }
+
+        // Adds up how long Lunar Magic runs and, once that's past the budget, throws a BudgetExceededException from
+        // every further call without starting it, for CI jobs that would rather fail than hit a hard time limit. A
+        // call that's already running isn't interrupted. The total is kept per wrapper, copies of it share it since
+        // several operations run on internal copies. std::nullopt, the default, turns it off.
+        LunarMagicWrapper& setTimeBudget(std::optional<std::chrono::milliseconds> budget) {
+            time_budget = budget;
+            return *this;
+        }
+
+        // starts counting towards the budget from zero again
+        LunarMagicWrapper& resetTimeBudget() {
+            *time_spent = 0;
+            return *this;
+        }
+
+        [[nodiscard]] std::chrono::nanoseconds getTimeSpent() const {
+            return std::chrono::nanoseconds{ time_spent->load() };
+        }
+
+        // On Windows, starts Lunar Magic with CREATE_NO_WINDOW and STARTF_USESHOWWINDOW/SW_HIDE so no window flashes
+        // up during scripted runs. Calls that have to go through cmd.exe (see the README) can't be hidden this way.
+        // On by default, has no effect elsewhere. open() always shows the editor.
+        LunarMagicWrapper& setHideWindow(bool hide) {
+            hide_window = hide;
+            return *this;
+        }
+
+        // Runs Lunar Magic at a lower priority, e.g. so long batch runs in the background leave the machine
+        // responsive. See ProcessPriority for what each level maps to. Calls that go through the shell (see the
+        // README) run at normal priority. NORMAL by default.
+        LunarMagicWrapper& setProcessPriority(ProcessPriority priority) {
+            process_priority = priority;
+            return *this;
+        }
+
+        // runs after each backup with the ROM and the path of its backup
+        LunarMagicWrapper& afterBackup(BackupHook hook) {
+            backup_hooks.push_back(std::move(hook));
+            return *this;
+        }
+
+        // Decides whether a call succeeded, for Lunar Magic builds that exit with 0 on failure or with something
+        // else on success. Gets the exit code exactly as the shell returned it, which outside of Windows is the raw
+        // wait status. An empty policy, the default, treats exit code 0 as success.
+        LunarMagicWrapper& setSuccessPolicy(SuccessPolicy policy) {
+            success_policy = std::move(policy);
+            return *this;
+        }
+
+        // Replaces starting Lunar Magic: the spawner gets the arguments every call would pass it (e.g.
+        // "-ExportGFX "rom.smc"") and returns the exit code and output to use instead. Everything around it,
+        // hooks, tee log, success policy and error handling, runs as usual. Meant for testing code built on the
+        // wrapper without a Lunar Magic executable. An empty spawner, the default, runs Lunar Magic.
+        LunarMagicWrapper& setSpawner(Spawner new_spawner) {
+            spawner = std::move(new_spawner);
+            return *this;
+        }
+
+        // Checks the files exportLevel, exportMultipleLevels, exportAllMap16 and exportSharedPalette write against
+        // the size their contents call for: the end of the last section for MWL and map16 files, which both carry
+        // a section table, and SHARED_PALETTE_SIZE for shared palettes. ERROR throws a MalformedExportException for
+        // any difference, TRUNCATE cuts off trailing bytes and only throws if the file is too short. IGNORE, the
+        // default, checks nothing.
+        LunarMagicWrapper& setTrailingDataPolicy(TrailingDataPolicy policy) {
+            trailing_data_policy = policy;
+            return *this;
+        }
+
+        // Collapses runs of identical consecutive lines in Lunar Magic's output into one line with a " (xN)"
+        // suffix before anything else sees it. Off by default, so output is exactly what Lunar Magic printed.
+        LunarMagicWrapper& setCollapseRepeatedLines(bool collapse) {
+            collapse_repeated_lines = collapse;
+            return *this;
+        }
+
+        // Keeps only the first and the last max_lines lines of each of out and err, with a line saying how many were
+        // left out in between, and marks the Result as truncated. Lines are dropped while reading, so a runaway
+        // operation can't exhaust memory. Unbounded by default.
+        LunarMagicWrapper& setMaxOutputLines(std::optional<size_t> max_lines) {
+            max_output_lines = max_lines;
+            return *this;
+        }
+
+        // Prints every command to stderr with a "+ " prefix right before running it, like a shell's set -x. What's
+        // printed is exactly what runs: the full shell command with its redirections, the arguments Lunar Magic is
+        // started with directly (quoted where they contain whitespace) or the call string handed to the spawner.
+        // Off by default.
+        LunarMagicWrapper& setEchoCommands(bool echo) {
+            echo_commands = echo;
+            return *this;
+        }
+
+        // Prefixes every line of out and err with the UTC time it was read, e.g. "2026-01-31T12:00:00.250Z Done".
+        // Output is picked up while Lunar Magic runs, so gaps between lines show where an operation was slow. Lines
+        // of a spawner all get the time it returned. Off by default, see splitTimestamp for reading them back.
+        LunarMagicWrapper& setTimestampOutput(bool timestamp) {
+            timestamp_output = timestamp;
+            return *this;
+        }
+
+        // the time and the rest of a line prefixed by setTimestampOutput, nullopt if it has no timestamp
+        static std::optional<std::pair<std::chrono::system_clock::time_point, std::string>> splitTimestamp(
+            const std::string& line);
+
+        // Options exportMultipleLevels uses when a call passes none. Options passed to the call always win, without
+        // either Lunar Magic's own default applies. std::nullopt, the default, passes nothing.
+        LunarMagicWrapper& setDefaultLevelExportOptions(std::optional<LevelExportOption> options) {
+            default_level_export_options = options;
+            return *this;
+        }
+
+        // Options importMultipleLevels uses when a call passes none, e.g. to clear secondary exits project-wide.
+        // Precedence is the same as for exports.
+        LunarMagicWrapper& setDefaultLevelImportOptions(std::optional<LevelImportOption> options) {
+            default_level_import_options = options;
+            return *this;
+        }
+
+        // Makes importGFX, importGFXAndExGFX and importAllGraphics throw a NoExportedGraphicsException without running
+        // Lunar Magic unless hasExportedGraphics, off by default
+        LunarMagicWrapper& setCheckExportedGraphics(bool check) {
+            check_exported_graphics = check;
+            return *this;
+        }
+
+        // Makes the exports create any missing directories on the way to the file(s) they write, e.g. exportLevel
+        // to "out/levels/level 105.mwl" in a fresh project, on by default
+        LunarMagicWrapper& setCreateOutputDirectories(bool create) {
+            create_output_directories = create;
+            return *this;
+        }
+
+        // makes importMultipleLevels check the directory with checkLevelFileNames first, off by default
+        LunarMagicWrapper& setCheckLevelFileNames(bool check) {
+            check_level_file_names = check;
+            return *this;
+        }
+
+        // Throws an OutOfSpaceException after a writing operation if fewer than minimum_free_bytes are left in
+        // the ROM. This is best-effort: Lunar Magic doesn't report its free space, so it's estimated the same way
+        // as ROM::freeBytes does, from zeroed runs in the expanded area. std::nullopt, the default, turns it off.
+        LunarMagicWrapper& setFreeSpaceGuard(std::optional<size_t> minimum_free_bytes) {
+            minimum_free_space = minimum_free_bytes;
+            return *this;
+        }
+
+        LunarMagicWrapper& setOutputMarkers(OutputMarkers markers) {
+            output_markers = std::move(markers);
+            return *this;
+        }
+
+        // Everything about the setup that's useful in a bug report. Doesn't run Lunar Magic or anything else,
+        // the version is read from the executable file.
+        [[nodiscard]] Diagnostics diagnostics() const;
+
+        // Operations whose flag ("-ExportGFX", case insensitive) appears in a usage text, unknown flags are ignored
+        static std::set<Operation> parseSupportedOperations(const std::vector<std::string>& usage_lines);
+
+        // Lunar Magic shows its usage in a message box rather than printing it, so it can't be probed without
+        // blocking on the dialog. Instead this parses a usage text saved from it (or from its documentation) if
+        // one is given and mentions any flag, and otherwise falls back to every operation of Lunar Magic 3.40,
+        // the oldest version this wrapper supports.
+        static std::set<Operation> supportedOperations(const std::optional<fs::path>& usage_path = std::nullopt);
+
+        [[nodiscard]] const fs::path& getLunarMagicPath() const {
+            return lunar_magic_path;
+        }
+
+        [[nodiscard]] const std::vector<std::string>& getCommandPrefix() const {
+            return command_prefix;
+        }
+
+        [[nodiscard]] const std::vector<std::string>& getCommandSuffix() const {
+            return command_suffix;
+        }
+
+        [[nodiscard]] bool getCanonicalizePaths() const {
+            return canonicalize_paths;
+        }
+
+        [[nodiscard]] const std::optional<fs::path>& getTeeLogPath() const {
+            return tee_log_path;
+        }
+
+        [[nodiscard]] bool getHideWindow() const {
+            return hide_window;
+        }
+
+        [[nodiscard]] ProcessPriority getProcessPriority() const {
+            return process_priority;
+        }
+
+        [[nodiscard]] std::optional<MappingMode> getExpectedMappingMode() const {
+            return expected_mapping_mode;
+        }
+
+        [[nodiscard]] bool getAutoFixChecksum() const {
+            return auto_fix_checksum;
+        }
+
+        [[nodiscard]] bool getBackupBeforeWrite() const {
+            return backup_before_write;
+        }
+
+        [[nodiscard]] const std::optional<fs::path>& getBackupDirectory() const {
+            return backup_directory;
+        }
+
+        [[nodiscard]] const OutputMarkers& getOutputMarkers() const {
+            return output_markers;
+        }
+
+        // patterns classifyOutput uses, e.g. for a localized Lunar Magic
+        LunarMagicWrapper& setClassifierPatterns(ClassifierPatterns patterns) {
+            classifier_patterns = std::move(patterns);
+            return *this;
+        }
+
+        [[nodiscard]] const ClassifierPatterns& getClassifierPatterns() const {
+            return classifier_patterns;
+        }
+
+        // output_parse::classify with the configured patterns
+        [[nodiscard]] std::vector<ClassifiedLine> classifyOutput(const std::vector<std::string>& lines) const {
+            return output_parse::classify(lines, classifier_patterns);
+        }
+
+        // Writes the ROM to a temporary directory, runs the operation on it and returns the resulting bytes.
+        // The directory (including anything Lunar Magic creates next to the ROM, like a Graphics folder) is
+        // removed afterwards whether or not the operation throws. Since the ROM is copied both ways, this costs
+        // two full writes and reads of the ROM on top of the operation itself.
+        static std::vector<uint8_t> withROMBytes(const std::vector<uint8_t>& rom_bytes,
+                                                 const std::function<void(const fs::path& rom_path)>& operation);
+
+        // Opens the ROM in the Lunar Magic editor without waiting for it to be closed
+        [[nodiscard]] LunarMagicSession open(const fs::path& rom_path) const {
+            return { executablePath(), rom_path };
+        }
+
+        // Runs the operation on every ROM using up to `parallelism` threads and collects one BatchResult per ROM,
+        // in the same order as the ROMs were passed. Failures don't stop the other ROMs from being processed.
+        // Every thread runs its own Lunar Magic process, so memory use and process count grow with parallelism,
+        // a handful of threads is usually plenty. The same ROM listed twice is processed one at a time.
+        static std::vector<BatchResult> forEachROM(const std::vector<fs::path>& rom_paths, size_t parallelism,
+                                                   const std::function<Result(const fs::path& rom_path)>& operation);
+
+        std::vector<BatchResult> importGFXMany(const std::vector<fs::path>& rom_paths, size_t parallelism);
+
+        // Imports the same shared palette into every ROM like importGFXMany does with graphics. The palette is
+        // checked once before any ROM is touched: a MissingInputFileException if it doesn't exist, an
+        // InvalidPathException if it isn't SHARED_PALETTE_SIZE bytes.
+        std::vector<BatchResult> importSharedPaletteMany(const std::vector<fs::path>& rom_paths,
+                                                         const fs::path& shared_palette_path, size_t parallelism);
+
+        Result exportGFX(const fs::path& rom_path);
+
+        // Requesting a stop on cancel kills Lunar Magic and throws a CancelledException once it's gone and its
+        // temporary directory is cleaned up. Lunar Magic can only be killed when it's started directly, calls that
+        // go through the shell (see the README) run to completion and throw afterwards.
+        Result exportGFX(const fs::path& rom_path, std::stop_token cancel);
+
+        // exportGFX on another thread, the wrapper has to outlive the future
+        std::future<Result> exportGFXAsync(const fs::path& rom_path, std::stop_token cancel = {});
+
+        // Like exportGFX, but writes Lunar Magic's output (stdout, then stderr) into output line by line and
+        // returns the exit code instead of throwing when it's not 0, for callers that forward output into a log
+        // they already manage
+        int exportGFXInto(const fs::path& rom_path, std::ostream& output);
+
+        Result exportExGFX(const fs::path& rom_path);
+
+        // Lunar Magic always reads and writes a ROM's GFX and ExGFX files in these folders next to it
+        static fs::path graphicsDirectory(const fs::path& rom_path);
+
+        static fs::path exGraphicsDirectory(const fs::path& rom_path);
+
+        static bool graphicsDirectoryExists(const fs::path& rom_path);
+
+        static bool exGraphicsDirectoryExists(const fs::path& rom_path);
+
+        // Whether the Graphics folder has every file exporting GFX creates, GFX00.bin to GFX33.bin. Importing from
+        // a folder that was never exported into is the usual reason importGFX fails.
+        static bool hasExportedGraphics(const fs::path& rom_path);
+
+        Result importGFX(const fs::path& rom_path);
+
+        // Imports GFX from a Graphics folder inside an archive (or a plain directory) instead of the one next to
+        // the ROM. Lunar Magic only reads from the latter, so the ROM is copied into a temporary directory next to
+        // the extracted graphics, imported into there and copied back once the import has succeeded, leaving the ROM
+        // untouched otherwise. Throws an InvalidPathException if the archive contains no Graphics folder or GFX files.
+        Result importGFXFromArchive(const fs::path& rom_path, const fs::path& archive_path);
+
+        // Like importGFX, but skips the import if it wouldn't change anything: the ROM is copied into a temporary
+        // directory, its GFX exported there and compared to the Graphics folder byte for byte. Nothing is kept between
+        // calls, so the export is paid for every time, which is still less churn than rewriting the ROM.
+        GFXImportOutcome importGFXIdempotent(const fs::path& rom_path);
+
+        // Whether both ROMs' GFX are byte for byte the same, e.g. to skip re-importing graphics into a variant. Each
+        // ROM is copied into a temporary directory and its GFX exported there, so neither ROM nor the Graphics
+        // folders next to them are touched.
+        bool graphicsMatch(const fs::path& rom_path, const fs::path& other_rom_path);
+
+        Result importExGFX(const fs::path& rom_path);
+
+        // Imports the ExGFX files of several directories together. Lunar Magic only reads the ExGraphics folder next
+        // to the ROM, so the files are merged into one next to a temporary copy of the ROM, imported there and the
+        // ROM copied back. Throws an InvalidPathException without importing anything if two directories contain
+        // a file of the same name (ignoring case, like Windows does). Returns the imported files, sorted by name.
+        std::vector<fs::path> importExGFXFromDirectories(const fs::path& rom_path,
+                                                         const std::vector<fs::path>& directories);
+
+        Result importAllGraphics(const fs::path& rom_path);
+
+        // Lunar Magic's -ImportAllGraphics already covers GFX and ExGFX in one process, this runs -ImportGFX and
+        // -ImportExGFX as two for when the steps need to be told apart. The ROM is backed up and checked once, the
+        // Result has the output of both in order and no operation. If -ImportExGFX fails the GFX are already
+        // imported, the exception's Result is the one of -ImportExGFX.
+        Result importGFXAndExGFX(const fs::path& rom_path);
+
+        // Lunar Magic's command line has no way to list or target ExGFX slots, so slots are read from the ROM
+        // directly and inserting copies the file to where -ImportExGFX picks it up (ExGraphics/ExGFX<slot>.bin)
+        static std::vector<ExGFXSlot> listExGFXSlots(const fs::path& rom_path);
+
+        static GraphicsUsage graphicsUsage(const fs::path& rom_path);
+
+        static MappingMode mappingMode(const fs::path& rom_path);
+
+        // read from the ROM without running Lunar Magic, which can't report it, see ROM::freeSpace for how exact it is
+        static FreeSpace freeSpace(const fs::path& rom_path);
+
+        // internal title, region, version and size of the ROM, read without running Lunar Magic
+        static ROMInfo romInfo(const fs::path& rom_path);
+
+        // Whether the level still is what the clean ROM has in that slot, i.e. importing over it wouldn't lose
+        // anything. Lunar Magic writes every saved level to a new place in the expanded area, so this compares the
+        // level's layer 1, layer 2 and sprite pointers between the two ROMs rather than the data itself.
+        static bool isLevelEmpty(const fs::path& rom_path, const fs::path& clean_rom_path, uint16_t level_number);
+
+        // number of screens of the level, read from the ROM without running Lunar Magic, see ROM::levelScreenCount
+        static uint8_t levelScreenCount(const fs::path& rom_path, uint16_t level_number);
+
+        // secondary exits of every level, read from the ROM without running Lunar Magic, see ROM::secondaryExits
+        static std::vector<SecondaryExit> listSecondaryExits(const fs::path& rom_path);
+
+        Result insertExGFX(const fs::path& rom_path, const fs::path& exgfx_path, uint16_t slot);
+
+        Result exportLevel(const fs::path& rom_path, const fs::path& mwl_path, uint16_t level_number);
+
+        // Exports the level, then reads the MWL back and writes its MWL::summary next to it, with the extension
+        // replaced by .txt or .json, for a reviewable file alongside the binary one
+        LevelExportWithSummary exportLevelWithSummary(const fs::path& rom_path, const fs::path& mwl_path,
+                                                      uint16_t level_number,
+                                                      SummaryFormat format = SummaryFormat::TEXT);
+
+        // importLevel, importMap16 and importCustomPalette throw a MissingInputFileException without running
+        // Lunar Magic if the file to import doesn't exist, or for importLevel, isn't an .mwl. importMap16 and
+        // importAllMap16 also throw an InvalidMap16Exception for a file that isn't a valid map16 file.
+        Result importLevel(const fs::path& rom_path, const fs::path& mwl_path,
+                         std::optional<uint16_t> level_number = std::nullopt);
+
+        // Like importLevel, but tells whether the level was newly created or replaced an existing one by checking
+        // isLevelEmpty against the clean ROM before and after importing, UNKNOWN if it's still empty afterwards.
+        // Without a level number, the outcome's is the one stored in the MWL, which Lunar Magic imports it as.
+        ImportLevelOutcome importLevelWithOutcome(const fs::path& rom_path, const fs::path& mwl_path,
+                                                  const fs::path& clean_rom_path,
+                                                  std::optional<uint16_t> level_number = std::nullopt);
+
+        // Lunar Magic has no validate-only mode, so this compares the MWL's level data size against the ROM's
+        // free space without running it. Both sides are estimates: Lunar Magic may compress or share data, and
+        // space freed by the level being replaced isn't counted. Throws a MissingInputFileException like importLevel.
+        static ImportFeasibility checkLevelImport(const fs::path& rom_path, const fs::path& mwl_path);
+
+        // Imports into the map16 of the level's tileset. There is no equivalent for the overworld's map16, no level
+        // number refers to it.
+        Result importMap16(const fs::path& rom_path, const fs::path& map16_path, uint16_t level_number,
+                        std::optional<std::pair<size_t, size_t>> coordinates = std::nullopt);
+
+        Result importCustomPalette(const fs::path& rom_path, const fs::path& palette_path, uint16_t level_number);
+
+        // Imports every .pal file in the directory into the level its name is, e.g. 105.pal into level 105, in level
+        // order. The names are all checked first: one that isn't 1-3 hex digits or names the same level as another
+        // throws an InvalidPathException, levels above MAX_LEVEL an InvalidLevelException, both without importing
+        // anything. A failed import doesn't stop the others, its outcome holds the exception instead.
+        std::vector<PaletteImportOutcome> importCustomPalettesFromDirectory(const fs::path& rom_path,
+                                                                            const fs::path& directory_path);
+
+        // levels are 000-1FF, every function taking a level number throws an InvalidLevelException for anything above
+        constexpr static uint16_t MAX_LEVEL{ 0x1FF };
+
+        // size of the shared palette file Lunar Magic 3.40 exports
+        constexpr static size_t SHARED_PALETTE_SIZE{ 0x7E2 };
+
+        // "line (xN)" for every run of N > 1 identical consecutive lines. Lines prefixed by setTimestampOutput count
+        // as identical if the text after the time is, and the run keeps the time of its first line.
+        static std::vector<std::string> collapseRepeatedLines(const std::vector<std::string>& lines);
+
+        // end of the last section of an MWL or map16 file, nullopt for anything else
+        static std::optional<size_t> expectedExportSize(const fs::path& path);
+
+        Result exportSharedPalette(const fs::path& rom_path, const fs::path& shared_palette_path);
+
+        Result importSharedPalette(const fs::path& rom_path, const fs::path& shared_palette_path);
+
+        Result exportAllMap16(const fs::path& rom_path, const fs::path& all_map16_path);
+
+        Result importAllMap16(const fs::path& rom_path, const fs::path& all_map16_path);
+
+        // applies the trailing data policy to the MWLs Lunar Magic wrote, leaving those of earlier exports alone
+        Result exportMultipleLevels(const fs::path& rom_path, const fs::path& directory_path,
+                                 const std::string& file_name_start,
+                                 std::optional<LevelExportOption> options = std::nullopt);
+
+        // The files exportMultipleLevels created with this file_name_start, sorted by level number. Other files
+        // in the directory, including MWLs with a different prefix, are ignored.
+        static std::vector<fs::path> collectExportedLevels(const fs::path& directory_path,
+                                                           const std::string& file_name_start);
+
+        // Lunar Magic always names exported levels "<prefix><level>.mwl", so this exports into a temporary
+        // directory and then moves every level to the path the template returns for it. Throws
+        // std::invalid_argument before moving anything if the template maps two levels to the same path.
+        Result exportMultipleLevels(const fs::path& rom_path, const LevelPathTemplate& path_for_level,
+                                    std::optional<LevelExportOption> options = std::nullopt);
+
+        // Sprites placed in the MWLs in the directory, as extra bits << 8 | sprite number, so normal sprites are
+        // just their number and e.g. PIXI's custom sprites (extra bit 2 set) are 0x200 and up
+        static std::set<uint16_t> usedSprites(const fs::path& levels_directory);
+
+        // usedSprites of every level in the ROM, exported into a temporary directory first
+        std::set<uint16_t> listUsedSprites(const fs::path& rom_path);
+
+        // Lunar Magic takes the level number to import an MWL to from its file name (the last 3 characters, in hex),
+        // not from the file, so a renamed MWL silently overwrites a different level. This lists every MWL in the
+        // directory whose file name disagrees with the level number stored in it. Files that can't be read as
+        // MWLs are skipped.
+        static std::vector<LevelFileNameMismatch> checkLevelFileNames(const fs::path& directory_path);
+
+        // last 3 characters of the file name without extension read as hex, nullopt if they aren't hex digits
+        static std::optional<uint16_t> levelNumberFromFileName(const fs::path& mwl_path);
+
+        // throws a LevelFileNameMismatchException before running Lunar Magic if checkLevelFileNames finds anything
+        // and checking is enabled through setCheckLevelFileNames
+        Result importMultipleLevels(const fs::path& rom_path, const fs::path& directory_path,
+                                 std::optional<LevelImportOption> options = std::nullopt);
+
+        // always passes the flags explicitly, so the policy is visible at the call site
+        Result importMultipleLevels(const fs::path& rom_path, const fs::path& directory_path,
+                                    SecondaryExitPolicy secondary_exits,
+                                    LevelImportOption options = LevelImportOption::NONE);
+
+        // Lunar Magic has no flag to clear a level, so this exports every level of the clean ROM into a temporary
+        // directory and imports the ones from first_level to last_level (inclusive) back over the ROM. Throws
+        // std::invalid_argument if the range is reversed or the ROM is smaller than the clean ROM, which means it
+        // isn't the same game or was never expanded from it. Levels are imported without any LevelImportOption,
+        // whatever setDefaultLevelImportOptions says, and a failing import throws like importMultipleLevels does.
+        // Returns the levels that were reset.
+        std::vector<uint16_t> resetLevels(const fs::path& rom_path, const fs::path& clean_rom_path,
+                                          uint16_t first_level, uint16_t last_level);
+
+        // Exports GFX, ExGFX, every level, all of map16 and the shared palette of a copy of the ROM into a
+        // temporary project tree, adds a manifest.json listing what's in it and which Lunar Magic version wrote
+        // the levels, then packs it into the archive (.zip, .tar, .tar.gz, ...). Returns the result of every
+        // export in that order. The ROM itself isn't modified or included.
+        std::vector<Result> exportProjectArchive(const fs::path& rom_path, const fs::path& archive_path);
+
+        // What importing the project tree exportProjectArchive writes (Graphics, ExGraphics, Levels/level XXX.mwl,
+        // all.map16, shared.pal) back into a ROM would do, in order, without running anything. Only what's present
+        // in the directory is planned; levels are imported one by one so each step names its level.
+        static BuildPlan buildProjectPlan(const fs::path& project_directory);
+
+        // Runs every step of buildProjectPlan against the ROM, staging GFX and ExGFX next to a copy of it the way
+        // importGFXFromArchive and importExGFXFromDirectories do. A failing step doesn't stop the build, the report
+        // lists every step with how it went. ExGFX steps have the imported files as their out.
+        BuildReport buildProject(const fs::path& rom_path, const fs::path& project_directory);
+
+        // The 6MB and 8MB sizes need an SA-1 ROM, expanding anything else to them throws an
+        // IncompatibleExpansionException without running Lunar Magic. ROMs whose mapping can't be read are passed
+        // through for Lunar Magic to judge.
+        Result expandROM(const fs::path& rom_path, ROMSize rom_size);
+
+        // bytes in a ROM of that size, without copier header
+        static size_t romSizeToBytes(ROMSize rom_size);
+
+        // Expands the ROM to rom_size if it's smaller, never shrinks it. Returns whether it was expanded.
+        bool ensureSize(const fs::path& rom_path, ROMSize rom_size);
+
+        Result changeCompression(const fs::path& rom_path, CompressionOption option);
+
+        // Lunar Magic doesn't report sizes, so used space is estimated from the ROM before and after
+        CompressionReport changeCompressionWithReport(const fs::path& rom_path, CompressionOption option);
+
+        // Applies each format to its own copy of the ROM in a temporary directory and reports how much space it
+        // used and how long Lunar Magic took, smallest result first (ties go to the faster format). The ROM itself
+        // isn't changed.
+        std::vector<CompressionBenchmark> benchmarkCompression(const fs::path& rom_path,
+                                                               const std::vector<CompressionOption>& formats);
+
+        // Lunar Magic's command line can only copy global ExAnimation between ROMs, see exportGlobalExanimation for
+        // storing it in a file. A level's own ExAnimation is part of its MWL, so exportLevel/importLevel store it
+        // and MWL::section(MWL::Section::EXANIMATION) gives access to the raw data.
+        Result transferGlobalExanimation(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+
+        // Same as exportCredits, the bytes transferring global ExAnimation changes in a copy of the clean ROM
+        Result exportGlobalExanimation(const fs::path& rom_path, const fs::path& exanimation_path,
+                                       const fs::path& clean_rom_path);
+
+        // Same as importCredits, transfers global ExAnimation from a copy of the clean ROM with the changes applied
+        Result importGlobalExanimation(const fs::path& rom_path, const fs::path& exanimation_path,
+                                       const fs::path& clean_rom_path);
+
+        // Lunar Magic 3.40 has no flag to export or import the overworld to/from a file, only this transfer.
+        // To store or share an overworld design on its own, keep it in a ROM and transfer from that.
+        Result transferOverworld(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+
+        // Lunar Magic's command line can only copy the title screen between ROMs, there is no file export/import
+        // for it (unlike title moves). Keeping a small ROM around that only carries the title screen and
+        // transferring from it is the closest equivalent.
+        Result transferTitleScreen(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+
+        // Same as the title screen, Lunar Magic's command line can only copy credits between ROMs, see exportCredits
+        // for storing them in a file
+        Result transferCredits(const fs::path& source_rom_path, const fs::path& destination_rom_path);
+
+        // Transfers the credits into a copy of the clean ROM and writes the bytes that differ from transferring the
+        // clean ROM's own credits as text, see ROM::writeChanges. Where Lunar Magic keeps edited credits isn't
+        // documented, this way the file has them wherever that is, without the hacks Lunar Magic installs on any
+        // transfer. It only fits the clean ROM it was exported against, the base ROM of the project.
+        Result exportCredits(const fs::path& rom_path, const fs::path& credits_path, const fs::path& clean_rom_path);
+
+        // Applies a file from exportCredits to a copy of the clean ROM and transfers the credits from there. Throws
+        // a ROMException if the file isn't in the ROM::writeChanges format.
+        Result importCredits(const fs::path& rom_path, const fs::path& credits_path, const fs::path& clean_rom_path);
+
+        // Title moves are stored as a ZSNES savestate, every one of them starts with this
+        constexpr static std::string_view ZST_SIGNATURE{ "ZSNES Save State File V" };
+        constexpr static size_t ZST_MINIMUM_SIZE{ 0x20000 };
+
+        // "<rom stem>.zst" next to the ROM, used by the overloads without a title moves path
+        static fs::path defaultTitleMovesPath(const fs::path& rom_path);
+
+        Result exportTitleMoves(const fs::path& rom_path, const fs::path& title_moves_path);
+
+        Result exportTitleMoves(const fs::path& rom_path);
+
+        // Throws an InvalidTitleMovesException without running Lunar Magic if the file isn't a ZSNES savestate,
+        // importing anything else would corrupt the title screen
+        Result importTitleMoves(const fs::path& rom_path, const fs::path& title_moves_path);
+
+        Result importTitleMoves(const fs::path& rom_path);
+
+        // the operations of this wrapper on one ROM, see ROMOperations
+        ROMOperations onROM(fs::path rom_path);
+
+        // Runs the operation with this wrapper and then with other, e.g. one set up with another Lunar Magic version,
+        // and compares what they printed line by line. A LunarMagicWrapperException counts as its Result, other
+        // exceptions are passed on. Both runs work on whatever the operation is given, so writing operations should
+        // be pointed at separate copies of the ROM.
+        ComparisonResult compareWith(LunarMagicWrapper& other,
+                                     const std::function<Result(LunarMagicWrapper& wrapper)>& operation);
+    };
+
+    // A sequence of operations on one ROM without repeating its path, e.g.
+    // lm.onROM("hack.smc").importGFX().importAllMap16("all.map16").importMultipleLevels("levels").
+    // Each call forwards to the wrapper's function of the same name and adds its Result to the log, exceptions
+    // propagate as usual, so the first failure ends the chain. Only valid as long as the wrapper is.
+    class ROMOperations {
+    private:
+        LunarMagicWrapper& wrapper;
+        const fs::path rom_path;
+        std::vector<Result> log{};
+
+    public:
+        ROMOperations(LunarMagicWrapper& wrapper, fs::path rom_path)
+        : wrapper(wrapper), rom_path(std::move(rom_path)) {}
+
+        [[nodiscard]] const fs::path& getROMPath() const {
+            return rom_path;
+        }
+
+        // Results of every operation so far, in order
+        [[nodiscard]] const std::vector<Result>& getLog() const {
+            return log;
+        }
+
+        ROMOperations& exportGFX() {
+            log.push_back(wrapper.exportGFX(rom_path));
+            return *this;
+        }
+
+        ROMOperations& importGFX() {
+            log.push_back(wrapper.importGFX(rom_path));
+            return *this;
+        }
+
+        ROMOperations& exportExGFX() {
+            log.push_back(wrapper.exportExGFX(rom_path));
+            return *this;
+        }
+
+        ROMOperations& importExGFX() {
+            log.push_back(wrapper.importExGFX(rom_path));
+            return *this;
+        }
+
+        ROMOperations& importAllGraphics() {
+            log.push_back(wrapper.importAllGraphics(rom_path));
+            return *this;
+        }
+
+        ROMOperations& importGFXAndExGFX() {
+            log.push_back(wrapper.importGFXAndExGFX(rom_path));
+            return *this;
+        }
+
+        ROMOperations& exportLevel(const fs::path& mwl_path, uint16_t level_number) {
+            log.push_back(wrapper.exportLevel(rom_path, mwl_path, level_number));
+            return *this;
+        }
+
+        ROMOperations& importLevel(const fs::path& mwl_path, std::optional<uint16_t> level_number = std::nullopt) {
+            log.push_back(wrapper.importLevel(rom_path, mwl_path, level_number));
+            return *this;
+        }
+
+        ROMOperations& exportMultipleLevels(const fs::path& directory_path, const std::string& file_name_start,
+                                            std::optional<LevelExportOption> options = std::nullopt) {
+            log.push_back(wrapper.exportMultipleLevels(rom_path, directory_path, file_name_start, options));
+            return *this;
+        }
+
+        ROMOperations& importMultipleLevels(const fs::path& directory_path,
+                                            std::optional<LevelImportOption> options = std::nullopt) {
+            log.push_back(wrapper.importMultipleLevels(rom_path, directory_path, options));
+            return *this;
+        }
+
+        ROMOperations& importMap16(const fs::path& map16_path, uint16_t level_number,
+                                   std::optional<std::pair<size_t, size_t>> coordinates = std::nullopt) {
+            log.push_back(wrapper.importMap16(rom_path, map16_path, level_number, coordinates));
+            return *this;
+        }
+
+        ROMOperations& exportAllMap16(const fs::path& all_map16_path) {
+            log.push_back(wrapper.exportAllMap16(rom_path, all_map16_path));
+            return *this;
+        }
+
+        ROMOperations& importAllMap16(const fs::path& all_map16_path) {
+            log.push_back(wrapper.importAllMap16(rom_path, all_map16_path));
+            return *this;
+        }
+
+        ROMOperations& importCustomPalette(const fs::path& palette_path, uint16_t level_number) {
+            log.push_back(wrapper.importCustomPalette(rom_path, palette_path, level_number));
+            return *this;
+        }
+
+        ROMOperations& exportSharedPalette(const fs::path& shared_palette_path) {
+            log.push_back(wrapper.exportSharedPalette(rom_path, shared_palette_path));
+            return *this;
+        }
+
+        ROMOperations& importSharedPalette(const fs::path& shared_palette_path) {
+            log.push_back(wrapper.importSharedPalette(rom_path, shared_palette_path));
+            return *this;
+        }
+
+        ROMOperations& expandROM(ROMSize rom_size) {
+            log.push_back(wrapper.expandROM(rom_path, rom_size));
+            return *this;
+        }
+
+        ROMOperations& changeCompression(CompressionOption option) {
+            log.push_back(wrapper.changeCompression(rom_path, option));
+            return *this;
+        }
     };
 
 } // lunar_magic_wrapper
@@ -0,0 +1,61 @@
+#ifndef LUNAR_MAGIC_WRAPPER_MAP16_H
+#define LUNAR_MAGIC_WRAPPER_MAP16_H
+
+#include <filesystem>
+#include <vector>
+#include <string>
+#include <string_view>
+#include <cstdint>
+#include <stdexcept>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    class Map16Exception : public std::runtime_error {
+    public:
+        explicit Map16Exception(const std::string& message) : std::runtime_error(message) {}
+    };
+
+    // little endian 32-bit value at the offset, the way LM16 and MWL files store their offsets and sizes
+    uint32_t readLong(const std::vector<uint8_t>& bytes, size_t offset);
+
+    // an (offset, size) entry of a file's section table
+    struct FileSection {
+        size_t offset{};
+        size_t size{};
+    };
+
+    // what the header of an LM16 file points to, see Map16File::sectionTable
+    struct Map16SectionTable {
+        size_t offset{};
+        size_t size{};
+        std::vector<FileSection> sections{};
+
+        // where the file should end: after the table or the last section, whichever is further in
+        [[nodiscard]] size_t end() const;
+    };
+
+    // The .map16 files exportAllMap16 writes and importMap16/importAllMap16 read: an "LM16" header pointing to a
+    // table of (offset, size) pairs, the first of which is the tile data.
+    class Map16File {
+    public:
+        constexpr static std::string_view SIGNATURE{ "LM16" };
+        constexpr static size_t HEADER_SIZE{ 0x18 };
+        // one 16x16 tile, four 8x8 tiles of 2 bytes each
+        constexpr static size_t TILE_SIZE{ 8 };
+
+        // Throws a Map16Exception if the signature is missing or the table isn't whole entries inside the file, the
+        // sections themselves aren't checked
+        static Map16SectionTable sectionTable(const std::vector<uint8_t>& bytes);
+
+        // Throws a Map16Exception for the first problem found: a missing signature, a section table or section
+        // outside of the file, which is what a truncated file looks like, or tile data that isn't whole tiles
+        static void validate(const std::vector<uint8_t>& bytes);
+
+        static void validate(const fs::path& map16_path);
+    };
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_MAP16_H
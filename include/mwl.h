@@ -0,0 +1,198 @@
+#ifndef LUNAR_MAGIC_WRAPPER_MWL_H
+#define LUNAR_MAGIC_WRAPPER_MWL_H
+
+#include <filesystem>
+#include <vector>
+#include <array>
+#include <string>
+#include <cstdint>
+#include <stdexcept>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    class MWLException : public std::runtime_error {
+    public:
+        explicit MWLException(const std::string& message) : std::runtime_error(message) {}
+    };
+
+    // Standard or extended layer 1 object, screen exits and screen jumps are not included
+    struct LevelObject {
+        uint8_t screen{};
+        uint8_t x{};
+        uint8_t y{};
+        // 0 for extended objects, whose number is in settings instead
+        uint8_t number{};
+        uint8_t settings{};
+
+        [[nodiscard]] bool isExtended() const {
+            return number == 0;
+        }
+
+        bool operator==(const LevelObject&) const = default;
+    };
+
+    struct ScreenExit {
+        uint8_t screen{};
+        uint16_t destination{};
+        bool secondary{};
+
+        bool operator==(const ScreenExit&) const = default;
+    };
+
+    struct Sprite {
+        uint8_t screen{};
+        uint8_t x{};
+        uint8_t y{};
+        uint8_t extra_bits{};
+        uint8_t number{};
+
+        bool operator==(const Sprite&) const = default;
+    };
+
+    struct ObjectMove {
+        LevelObject from{};
+        LevelObject to{};
+
+        bool operator==(const ObjectMove&) const = default;
+    };
+
+    struct SpriteMove {
+        Sprite from{};
+        Sprite to{};
+
+        bool operator==(const SpriteMove&) const = default;
+    };
+
+    // Differences between two levels, see MWL::diff. An object or sprite that only changed position is reported
+    // as moved, any other change as one removed and one added.
+    struct MWLDiff {
+        bool level_number_changed{};
+        bool primary_header_changed{};
+
+        std::vector<LevelObject> added_objects{};
+        std::vector<LevelObject> removed_objects{};
+        std::vector<ObjectMove> moved_objects{};
+
+        std::vector<Sprite> added_sprites{};
+        std::vector<Sprite> removed_sprites{};
+        std::vector<SpriteMove> moved_sprites{};
+
+        [[nodiscard]] bool empty() const {
+            return !level_number_changed && !primary_header_changed && added_objects.empty()
+                && removed_objects.empty() && moved_objects.empty() && added_sprites.empty()
+                && removed_sprites.empty() && moved_sprites.empty();
+        }
+    };
+
+    // What a level is at a glance, from the primary header and the decoded layer 1 and sprite data, see MWL::summary
+    struct LevelSummary {
+        uint16_t level_number{};
+        uint8_t screen_count{};
+        // the numbers Lunar Magic's "Change Properties in Header" dialog shows
+        uint8_t level_mode{};
+        uint8_t tileset{};
+        uint8_t music{};
+        size_t object_count{};
+        size_t sprite_count{};
+        std::vector<ScreenExit> screen_exits{};
+
+        // one "name: value" line per field, exits one per line below an "Exits:" line
+        [[nodiscard]] std::string toString() const;
+
+        // an object with the same fields, exits as an array of objects
+        [[nodiscard]] std::string toJSON() const;
+
+        bool operator==(const LevelSummary&) const = default;
+    };
+
+    // Level exported by Lunar Magic (-ExportLevel/-ExportMultLevels).
+    //
+    // An MWL starts with "LM", a format version and a table of (offset, size) pairs pointing at the sections
+    // below, followed by a short text identifying the Lunar Magic version that wrote it. The layer 1 and sprite
+    // sections hold the level data as it's stored in the ROM, each behind an 8 byte section header. Only the
+    // level number, layer 1 and sprite data are decoded so far, everything else is available as raw bytes.
+    class MWL {
+    public:
+        enum class Section {
+            LEVEL_INFORMATION,
+            LAYER1,
+            LAYER2,
+            SPRITES,
+            PALETTE,
+            SECONDARY_ENTRANCES,
+            EXANIMATION,
+            EXGFX_BYPASS
+        };
+
+        constexpr static size_t SECTION_COUNT{ 8 };
+        constexpr static size_t SECTION_HEADER_SIZE{ 8 };
+        constexpr static size_t PRIMARY_HEADER_SIZE{ 5 };
+
+    protected:
+        std::vector<uint8_t> data;
+        std::array<std::pair<uint32_t, uint32_t>, SECTION_COUNT> sections{};
+
+        std::vector<LevelObject> objects{};
+        std::vector<ScreenExit> screen_exits{};
+        std::vector<Sprite> sprites{};
+
+        void parseLayer1();
+
+        void parseSprites();
+
+    public:
+        explicit MWL(std::vector<uint8_t> bytes);
+
+        static MWL read(const fs::path& mwl_path);
+
+        // writes the file back out unchanged
+        void write(const fs::path& mwl_path) const;
+
+        [[nodiscard]] const std::vector<uint8_t>& bytes() const {
+            return data;
+        }
+
+        [[nodiscard]] uint16_t formatVersion() const;
+
+        // "Lunar Magic x.yz ..." text in the file header
+        [[nodiscard]] std::string creator() const;
+
+        [[nodiscard]] std::vector<uint8_t> section(Section section) const;
+
+        [[nodiscard]] uint16_t levelNumber() const;
+
+        [[nodiscard]] std::array<uint8_t, PRIMARY_HEADER_SIZE> primaryHeader() const;
+
+        // Bytes of layer 1, layer 2 and sprite data without their section headers, roughly what importing the
+        // level needs in the ROM's free space
+        [[nodiscard]] size_t levelDataSize() const;
+
+        [[nodiscard]] uint8_t screenCount() const {
+            return (primaryHeader()[0] & 0x1F) + 1;
+        }
+
+        [[nodiscard]] const std::vector<LevelObject>& getObjects() const {
+            return objects;
+        }
+
+        [[nodiscard]] const std::vector<ScreenExit>& getScreenExits() const {
+            return screen_exits;
+        }
+
+        [[nodiscard]] const std::vector<Sprite>& getSprites() const {
+            return sprites;
+        }
+
+        [[nodiscard]] LevelSummary summary() const;
+
+        // Compares objects and sprites as lists, ignoring their order. Objects that are identical in both levels
+        // are matched first, then the rest are paired up by number and settings (sprites by number and extra
+        // bits) in the order they appear and reported as moved.
+        static MWLDiff diff(const MWL& before, const MWL& after);
+    };
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_MWL_H
@@ -0,0 +1,87 @@
+#ifndef LUNAR_MAGIC_WRAPPER_OUTPUT_PARSE_H
+#define LUNAR_MAGIC_WRAPPER_OUTPUT_PARSE_H
+
+#include <filesystem>
+#include <vector>
+#include <string>
+#include <optional>
+#include <cstdint>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    enum class OutputLineKind {
+        INFO,
+        WARNING,
+        ERROR,
+        PROGRESS,
+        UNKNOWN
+    };
+
+    struct ClassifiedLine {
+    public:
+        const OutputLineKind kind;
+        const std::string line;
+
+        bool operator==(const ClassifiedLine&) const = default;
+    };
+
+    // Case insensitive phrases marking a line as one kind, checked in the order error, warning, progress, info. A
+    // line matching none is UNKNOWN.
+    struct ClassifierPatterns {
+    public:
+        std::vector<std::string> error{ "error", "failed", "unable to", "not found", "invalid" };
+        std::vector<std::string> warning{ "warning", "newer version" };
+        std::vector<std::string> progress{ "...", "compressing" };
+        std::vector<std::string> info{ "lunar magic", "imported", "exported", "written", "overwriting", "done" };
+    };
+
+    // Pieces of information in the lines Lunar Magic prints, usable on any Result's out/err. Recognized lines:
+    //
+    //   line                                        | level | path          | count
+    //   --------------------------------------------+-------+---------------+------
+    //   Importing level 105 from level.mwl...       | 105   | level.mwl     |
+    //   Exporting level 105 to level 105.mwl...     | 105   | level 105.mwl |
+    //   Level 105 imported as a new level.          | 105   |               |
+    //   Overwriting existing level 105.             | 105   |               |
+    //   12 levels exported.                         |       |               | 12
+    //   2,018 bytes written.                        |       |               | 2018
+    //
+    // Matching is case insensitive, anything that doesn't fit a column gives nullopt for it.
+    namespace output_parse {
+        // hex number after the word "level"
+        std::optional<uint16_t> levelNumber(const std::string& line);
+
+        // what follows "from" or "to", up to the end of the line without trailing dots, if it has an extension
+        std::optional<fs::path> filePath(const std::string& line);
+
+        // decimal number the line starts with, if a word follows it, nullopt if it doesn't fit a size_t
+        std::optional<size_t> count(const std::string& line);
+
+        // decimal number, thousands separated by commas or not, followed by "bytes written", nullopt if it doesn't
+        // fit a uint64_t
+        std::optional<uint64_t> bytesWritten(const std::string& line);
+
+        // sum of bytesWritten over every line that has one, nullopt if none does
+        std::optional<uint64_t> totalBytesWritten(const std::vector<std::string>& lines);
+
+        // the lines separated by '\n', without one at the end, empty for no lines
+        std::string joined(const std::vector<std::string>& lines);
+
+        // first line containing text, case sensitive, nullopt if there's none
+        std::optional<std::string> findLine(const std::vector<std::string>& lines, const std::string& text);
+
+        bool containsLine(const std::vector<std::string>& lines, const std::string& text);
+
+        // levelNumber of every line that has one, in order
+        std::vector<uint16_t> levelNumbers(const std::vector<std::string>& lines);
+
+        // every line with its kind, in order, e.g. for coloring or filtering output
+        std::vector<ClassifiedLine> classify(const std::vector<std::string>& lines,
+                                             const ClassifierPatterns& patterns = {});
+    }
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_OUTPUT_PARSE_H
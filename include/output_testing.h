@@ -0,0 +1,38 @@
+#ifndef LUNAR_MAGIC_WRAPPER_OUTPUT_TESTING_H
+#define LUNAR_MAGIC_WRAPPER_OUTPUT_TESTING_H
+
+#include <vector>
+#include <string>
+#include <stdexcept>
+
+namespace lunar_magic_wrapper {
+
+    class OutputMismatchException : public std::runtime_error {
+    public:
+        explicit OutputMismatchException(const std::string& message) : std::runtime_error(message) {}
+    };
+
+    // Assertions on a Result's out/err for tests of code using the wrapper, independent of the test framework.
+    // Only built with LM_WRAP_BUILD_TESTING_HELPERS, and not called "testing" so it doesn't clash with
+    // googletest's namespace in files using both.
+    //
+    // Each line is matched against the pattern at the same index. Patterns are globs, where * matches any run of
+    // characters and ? a single one, unless they start with "re:", in which case the rest has to match the whole
+    // line as an ECMAScript regex.
+    namespace output_testing {
+        // whether the line matches the pattern, see above
+        bool lineMatches(const std::string& line, const std::string& pattern);
+
+        // Empty if every line matches its pattern and there are as many lines as patterns, otherwise one line per
+        // difference: "line N: expected '<pattern>', got '<line>'", "line N: missing, expected '<pattern>'" past
+        // the end of the output and "line N: unexpected '<line>'" past the last pattern
+        std::string outputDiff(const std::vector<std::string>& actual, const std::vector<std::string>& expected_patterns);
+
+        // throws an OutputMismatchException with the outputDiff if there is one
+        void assertOutputMatches(const std::vector<std::string>& actual,
+                                 const std::vector<std::string>& expected_patterns);
+    }
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_OUTPUT_TESTING_H
@@ -0,0 +1,252 @@
+#ifndef LUNAR_MAGIC_WRAPPER_ROM_H
+#define LUNAR_MAGIC_WRAPPER_ROM_H
+
+#include <filesystem>
+#include <vector>
+#include <cstdint>
+#include <stdexcept>
+#include <array>
+#include <string_view>
+#include <string>
+#include <optional>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    class ROMException : public std::runtime_error {
+    public:
+        explicit ROMException(const std::string& message) : std::runtime_error(message) {}
+    };
+
+    struct ExGFXSlot {
+        uint16_t number{};
+        uint32_t snes_address{};
+    };
+
+    struct GraphicsUsage {
+    public:
+        std::vector<uint16_t> used_gfx{};
+        std::vector<uint16_t> used_exgfx{};
+        std::vector<uint16_t> free_exgfx{};
+
+        [[nodiscard]] size_t usedGFXCount() const {
+            return used_gfx.size();
+        }
+
+        [[nodiscard]] size_t usedExGFXCount() const {
+            return used_exgfx.size();
+        }
+    };
+
+    // a screen exit that leads to a secondary exit, see ROM::secondaryExits
+    struct SecondaryExit {
+        uint16_t source_level{};
+        uint8_t screen{};
+        uint16_t exit_number{};
+        // level the secondary exit leads to, nullopt for exits 100-1FF, which Lunar Magic keeps in its own tables
+        std::optional<uint16_t> destination_level{};
+
+        bool operator==(const SecondaryExit&) const = default;
+    };
+
+    // where a level's data is, see ROM::levelPointers
+    struct LevelPointers {
+        uint32_t layer1{};
+        uint32_t layer2{};
+        // bank 07 in SMW, Lunar Magic keeps the bank in a table of its own once sprite data is moved
+        uint16_t sprites{};
+
+        bool operator==(const LevelPointers&) const = default;
+    };
+
+    // a run of bytes that differ from another ROM, see ROM::changesFrom
+    struct ROMChange {
+        // PC offset, not counting a copier header
+        size_t offset{};
+        std::vector<uint8_t> bytes{};
+
+        bool operator==(const ROMChange&) const = default;
+    };
+
+    // how full a ROM is, see ROM::freeSpace
+    struct FreeSpace {
+    public:
+        const size_t total_bytes{};
+        const size_t used_bytes{};
+        const size_t free_bytes{};
+
+        // share of the whole ROM in use, 0-100
+        [[nodiscard]] double percentUsed() const {
+            return total_bytes == 0 ? 0.0 : 100.0 * static_cast<double>(used_bytes) / static_cast<double>(total_bytes);
+        }
+    };
+
+    // memory map the ROM's internal header declares, SMW and anything Lunar Magic produces is LOROM or SA1
+    enum class MappingMode {
+        LOROM,
+        HIROM,
+        EXLOROM,
+        EXHIROM,
+        SA1,
+        UNKNOWN
+    };
+
+    std::string_view mappingModeToString(MappingMode mode);
+
+    // what the internal header says about the ROM, see ROM::info
+    struct ROMInfo {
+    public:
+        // JIS X 0201 decoded to UTF-8, trailing spaces removed
+        const std::string title;
+        // destination code, 0x00 is Japan and 0x01 North America
+        const uint8_t region{};
+        const uint8_t version{};
+        // log2 of the size in KB
+        const uint8_t rom_size{};
+        const MappingMode mapping_mode{};
+    };
+
+    // Read-only view of a (LoROM) SMW ROM, used for the things Lunar Magic's command line can't tell us
+    class ROM {
+    protected:
+        std::vector<uint8_t> data;
+        bool has_copier_header{};
+
+    public:
+        constexpr static size_t COPIER_HEADER_SIZE{ 0x200 };
+
+        // PC offsets of the internal header for LoROM-style and HiROM-style maps
+        constexpr static size_t LOROM_HEADER{ 0x7FC0 };
+        constexpr static size_t HIROM_HEADER{ 0xFFC0 };
+
+        // LM keeps the pointers for ExGFX80-FF in a fixed table and points to the table for ExGFX100-FFF
+        constexpr static uint32_t EXGFX_80_TABLE{ 0x0FF600 };
+        constexpr static uint32_t EXGFX_100_TABLE_POINTER{ 0x0FF937 };
+
+        // SMW's GFX00-31 pointers are split into separate low/high/bank byte tables, GFX32 and GFX33 are loaded
+        // separately and always present
+        constexpr static uint32_t GFX_POINTER_TABLE_LOW{ 0x00B992 };
+        constexpr static uint32_t GFX_POINTER_TABLE_HIGH{ 0x00B9C4 };
+        constexpr static uint32_t GFX_POINTER_TABLE_BANK{ 0x00B9F6 };
+        constexpr static uint16_t GFX_TABLE_ENTRIES{ 0x32 };
+
+        // pointers to the layer 1, layer 2 and sprite data of levels 000-1FF
+        constexpr static uint32_t LAYER1_POINTER_TABLE{ 0x05E000 };
+        constexpr static uint32_t LAYER2_POINTER_TABLE{ 0x05E600 };
+        constexpr static uint32_t SPRITE_POINTER_TABLE{ 0x05EC00 };
+        constexpr static uint16_t LEVEL_COUNT{ 0x200 };
+
+        // destination level of secondary exits 00-FF, low byte in the first table and the high bit in bit 3 of the
+        // second one
+        constexpr static uint32_t SECONDARY_EXIT_LEVEL_TABLE{ 0x05F800 };
+        constexpr static uint32_t SECONDARY_EXIT_SETTINGS_TABLE{ 0x05FE00 };
+
+        // LM only ever places data past the original 512KB, and freed blocks there are zeroed
+        constexpr static size_t ORIGINAL_ROM_SIZE{ 0x80000 };
+        constexpr static size_t MIN_FREE_RUN{ 0x80 };
+
+        // every extension Lunar Magic opens as a ROM, every operation that writes to a ROM checks its path against these
+        constexpr static std::array<std::string_view, 4> EXTENSIONS{ ".smc", ".sfc", ".fig", ".swc" };
+
+        explicit ROM(std::vector<uint8_t> bytes);
+
+        // case insensitive
+        static bool hasROMExtension(const fs::path& rom_path);
+
+        static ROM fromFile(const fs::path& rom_path);
+
+        static size_t snesToPc(uint32_t snes_address);
+
+        [[nodiscard]] bool hasCopierHeader() const {
+            return has_copier_header;
+        }
+
+        [[nodiscard]] size_t size() const {
+            return data.size();
+        }
+
+        [[nodiscard]] const std::vector<uint8_t>& bytes() const {
+            return data;
+        }
+
+        [[nodiscard]] bool containsSnes(uint32_t snes_address) const;
+
+        [[nodiscard]] uint8_t byteAt(uint32_t snes_address) const;
+
+        [[nodiscard]] uint32_t longAt(uint32_t snes_address) const;
+
+        // Zeroed runs of at least MIN_FREE_RUN bytes in the expanded area, this is an estimate of what's free for
+        // Lunar Magic to use, not an exact reading of its allocation state
+        [[nodiscard]] size_t freeBytes() const;
+
+        [[nodiscard]] size_t usedBytes() const {
+            return size() - freeBytes();
+        }
+
+        // Totals of the whole ROM with free bytes as freeBytes counts them. The original 512KB always count as used
+        // and a ROM that was never expanded is full. Not what Lunar Magic would report: it tracks its blocks with
+        // RATS tags and can reuse space this counts as used, while zeroed runs inside its own data count as free.
+        [[nodiscard]] FreeSpace freeSpace() const {
+            const auto free{ freeBytes() };
+            return { size(), size() - free, free };
+        }
+
+        // Picks whichever of the two possible internal headers looks valid (matching checksum and complement,
+        // map mode byte fitting its location, reset vector in ROM) and reads the map mode byte from it
+        [[nodiscard]] MappingMode mappingMode() const;
+
+        // PC offset of the internal header, the HiROM one for HiROM and ExHiROM ROMs and the LoROM one otherwise
+        [[nodiscard]] size_t headerOffset() const;
+
+        // title, region, version and size bytes of the internal header
+        [[nodiscard]] ROMInfo info() const;
+
+        // checksum as stored in the internal header
+        [[nodiscard]] uint16_t headerChecksum() const;
+
+        // Sum of all bytes the way the SNES header expects it, with sizes that aren't a power of two summed as if
+        // the remainder was mirrored up to the next one (3MB and 6MB ROMs) and the header's checksum/complement
+        // counted as 0000/FFFF, so the result doesn't depend on what's stored there
+        [[nodiscard]] uint16_t computeChecksum() const;
+
+        // writes the computed checksum and its complement into the ROM file's header, returns the checksum
+        static uint16_t fixChecksum(const fs::path& rom_path);
+
+        // Runs of bytes where this ROM differs from the original one, in order. Bytes past the end of the original
+        // count as changed, a ROM shorter than the original only compares up to its own size.
+        [[nodiscard]] std::vector<ROMChange> changesFrom(const ROM& original) const;
+
+        // Writes the changes as text, one "<offset>: <bytes>" line in hex per up to 16 bytes, e.g.
+        // "0C9F00: 43 52 45 44", so they can be diffed and edited
+        static void writeChanges(const fs::path& changes_path, const std::vector<ROMChange>& changes);
+
+        // reads what writeChanges wrote, blank lines and lines starting with '#' are skipped. Throws a ROMException
+        // for any other line that doesn't fit the format.
+        static std::vector<ROMChange> readChanges(const fs::path& changes_path);
+
+        // writes the changes into the ROM file, keeping a copier header, and grows it if they go past its end
+        static void applyChanges(const fs::path& rom_path, const std::vector<ROMChange>& changes);
+
+        // ExGFX slots that have a pointer into the ROM assigned, sorted by slot number, none for a ROM that hasn't
+        // been expanded past ORIGINAL_ROM_SIZE since it has no ExGFX tables yet
+        [[nodiscard]] std::vector<ExGFXSlot> usedExGFXSlots() const;
+
+        // used GFX and ExGFX file numbers plus the ExGFX numbers that are still free, each sorted
+        [[nodiscard]] GraphicsUsage graphicsUsage() const;
+
+        // layer 1, layer 2 and sprite data pointers of a level from the tables above
+        [[nodiscard]] LevelPointers levelPointers(uint16_t level) const;
+
+        // From the level's primary header, like MWL::screenCount. Throws a ROMException if the level's layer 1
+        // pointer doesn't point into the ROM.
+        [[nodiscard]] uint8_t levelScreenCount(uint16_t level) const;
+
+        // Every screen exit in the layer 1 data of every level that leads to a secondary exit, sorted by source
+        // level and then by where the exit is in the level data. Levels without valid layer 1 pointer are skipped.
+        [[nodiscard]] std::vector<SecondaryExit> secondaryExits() const;
+    };
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_ROM_H
@@ -0,0 +1,75 @@
+#ifndef LUNAR_MAGIC_WRAPPER_SESSION_H
+#define LUNAR_MAGIC_WRAPPER_SESSION_H
+
+#include <filesystem>
+#include <optional>
+#include <system_error>
+
+namespace fs = std::filesystem;
+
+namespace lunar_magic_wrapper {
+
+    // Lunar Magic couldn't be started at all, code() is the operating system's error. Still a std::system_error, so
+    // one catch covers it along with other I/O errors.
+    class LaunchException : public std::system_error {
+    public:
+        LaunchException(std::error_code code, const std::string& message) : std::system_error(code, message) {}
+
+        explicit LaunchException(const std::system_error& error) : std::system_error(error) {}
+    };
+
+    // A Lunar Magic editor window started through LunarMagicWrapper::open.
+    //
+    // The session owns a handle to the process, not the process itself: destroying the session just lets go of
+    // the handle and leaves the editor running, unless killOnDestruction(true) was set. wait() blocks until the
+    // user closes the editor, tryWait() checks without blocking, kill() terminates it along with any processes it
+    // started (a job object on Windows, a process group elsewhere). Once the exit code has been collected by either
+    // wait function it is cached, so calling them again is fine. A moved-from session owns nothing, waiting on it
+    // gives MOVED_FROM_EXIT_CODE and kill() does nothing.
+    class LunarMagicSession {
+    private:
+#ifdef _WIN32
+        void* process_handle{};
+        // job object containing the editor and everything it starts
+        void* job_handle{};
+
+        void terminate();
+#else
+        int pid{ -1 };
+#endif
+        std::optional<int> exit_code{};
+        bool kill_on_destruction{};
+
+        // lets go of the process, killing it first if kill_on_destruction is set
+        void release();
+
+    public:
+        constexpr static int MOVED_FROM_EXIT_CODE{ -1 };
+
+        // throws a LaunchException if the editor can't be started
+        LunarMagicSession(const fs::path& lunar_magic_path, const fs::path& rom_path);
+
+        LunarMagicSession(const LunarMagicSession&) = delete;
+        LunarMagicSession& operator=(const LunarMagicSession&) = delete;
+
+        LunarMagicSession(LunarMagicSession&& other) noexcept;
+        LunarMagicSession& operator=(LunarMagicSession&& other) noexcept;
+
+        ~LunarMagicSession();
+
+        // throw a std::system_error if the process can't be waited on
+        int wait();
+
+        std::optional<int> tryWait();
+
+        void kill();
+
+        LunarMagicSession& killOnDestruction(bool kill) {
+            kill_on_destruction = kill;
+            return *this;
+        }
+    };
+
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_SESSION_H
@@ -0,0 +1,218 @@
+#include "../include/lunar_magic_wrapper.h"
+
+#ifdef _WIN32
+#define WIN32_LEAN_AND_MEAN
+#define NOMINMAX
+#include <windows.h>
+#else
+#include <cerrno>
+#include <csignal>
+#include <thread>
+#include <fcntl.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+#endif
+
+namespace lunar_magic_wrapper {
+#ifdef _WIN32
+    uint32_t LunarMagicWrapper::priorityCreationFlags(ProcessPriority priority) {
+        switch (priority) {
+            case ProcessPriority::IDLE:
+                return IDLE_PRIORITY_CLASS;
+            case ProcessPriority::BELOW_NORMAL:
+                return BELOW_NORMAL_PRIORITY_CLASS;
+            default:
+                return NORMAL_PRIORITY_CLASS;
+        }
+    }
+
+    std::optional<int> LunarMagicWrapper::runDirectly(const std::vector<std::string> &arguments,
+                                                      const fs::path &out_path, const fs::path &err_path,
+                                                      bool hide_window, ProcessPriority priority,
+                                                      std::stop_token stop) {
+        // directArguments already rejected anything containing quotes, so quoting is all that's needed
+        std::wstring command_line{};
+        for (const auto& argument : arguments) {
+            if (!command_line.empty()) {
+                command_line += L' ';
+            }
+
+            const auto wide_argument{ fs::path(argument).wstring() };
+            if (wide_argument.empty() || wide_argument.find_first_of(L" \t") != std::wstring::npos) {
+                command_line += L'"' + wide_argument + L'"';
+            } else {
+                command_line += wide_argument;
+            }
+        }
+
+        SECURITY_ATTRIBUTES inheritable{ sizeof(SECURITY_ATTRIBUTES), nullptr, TRUE };
+        const auto out_handle{ CreateFileW(out_path.c_str(), GENERIC_WRITE, FILE_SHARE_READ, &inheritable,
+                                           CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL, nullptr) };
+        const auto err_handle{ CreateFileW(err_path.c_str(), GENERIC_WRITE, FILE_SHARE_READ, &inheritable,
+                                           CREATE_ALWAYS, FILE_ATTRIBUTE_NORMAL, nullptr) };
+
+        if (out_handle == INVALID_HANDLE_VALUE || err_handle == INVALID_HANDLE_VALUE) {
+            if (out_handle != INVALID_HANDLE_VALUE) {
+                CloseHandle(out_handle);
+            }
+            if (err_handle != INVALID_HANDLE_VALUE) {
+                CloseHandle(err_handle);
+            }
+            return std::nullopt;
+        }
+
+        STARTUPINFOW startup_info{};
+        startup_info.cb = sizeof(startup_info);
+        startup_info.dwFlags = STARTF_USESTDHANDLES;
+        startup_info.hStdInput = GetStdHandle(STD_INPUT_HANDLE);
+        startup_info.hStdOutput = out_handle;
+        startup_info.hStdError = err_handle;
+        if (hide_window) {
+            startup_info.dwFlags |= STARTF_USESHOWWINDOW;
+            startup_info.wShowWindow = SW_HIDE;
+        }
+        PROCESS_INFORMATION process_info{};
+
+        // whatever Lunar Magic starts itself ends up in the job too, so stopping it doesn't leave those running
+        auto job{ stop.stop_possible() ? CreateJobObjectW(nullptr, nullptr) : nullptr };
+
+        const auto started{ CreateProcessW(nullptr, command_line.data(), nullptr, nullptr, TRUE,
+                                           (hide_window ? CREATE_NO_WINDOW : 0) | priorityCreationFlags(priority)
+                                           | (job ? CREATE_SUSPENDED : 0),
+                                           nullptr, nullptr, &startup_info, &process_info) };
+
+        CloseHandle(out_handle);
+        CloseHandle(err_handle);
+
+        if (!started) {
+            if (job) {
+                CloseHandle(job);
+            }
+            return std::nullopt;
+        }
+
+        // suspended until it's in the job, it can't start anything before then
+        if (job) {
+            if (!AssignProcessToJobObject(job, process_info.hProcess)) {
+                CloseHandle(job);
+                job = nullptr;
+            }
+            ResumeThread(process_info.hThread);
+        }
+
+        while (WaitForSingleObject(process_info.hProcess, stop.stop_possible() ? 10 : INFINITE) == WAIT_TIMEOUT) {
+            if (stop.stop_requested()) {
+                if (job) {
+                    TerminateJobObject(job, 1);
+                } else {
+                    TerminateProcess(process_info.hProcess, 1);
+                }
+                WaitForSingleObject(process_info.hProcess, INFINITE);
+                break;
+            }
+        }
+        DWORD exit_code{};
+        GetExitCodeProcess(process_info.hProcess, &exit_code);
+        CloseHandle(process_info.hThread);
+        CloseHandle(process_info.hProcess);
+        if (job) {
+            CloseHandle(job);
+        }
+
+        return static_cast<int>(exit_code);
+    }
+#else
+    std::optional<int> LunarMagicWrapper::niceValue(ProcessPriority priority) {
+        switch (priority) {
+            case ProcessPriority::IDLE:
+                return 19;
+            case ProcessPriority::BELOW_NORMAL:
+                return 10;
+            default:
+                return std::nullopt;
+        }
+    }
+
+    std::optional<int> LunarMagicWrapper::runDirectly(const std::vector<std::string> &arguments,
+                                                      const fs::path &out_path, const fs::path &err_path,
+                                                      bool /* hide_window */, ProcessPriority priority,
+                                                      std::stop_token stop) {
+        std::vector<char*> argv{};
+        for (const auto& argument : arguments) {
+            argv.push_back(const_cast<char*>(argument.c_str()));
+        }
+        argv.push_back(nullptr);
+
+        // a process group of its own, so stopping it takes whatever Lunar Magic started along. Only when it can be
+        // stopped, outside of the terminal's foreground group it wouldn't get the terminal's Ctrl+C anymore.
+        const auto own_group{ stop.stop_possible() };
+
+        const auto pid{ fork() };
+
+        if (pid == -1) {
+            return std::nullopt;
+        }
+
+        if (pid == 0) {
+            if (own_group) {
+                setpgid(0, 0);
+            }
+
+            const auto out_fd{ ::open(out_path.c_str(), O_WRONLY | O_CREAT | O_TRUNC, 0644) };
+            const auto err_fd{ ::open(err_path.c_str(), O_WRONLY | O_CREAT | O_TRUNC, 0644) };
+            if (out_fd == -1 || err_fd == -1) {
+                _exit(127);
+            }
+
+            dup2(out_fd, STDOUT_FILENO);
+            dup2(err_fd, STDERR_FILENO);
+            close(out_fd);
+            close(err_fd);
+
+            // only ever lowers the priority, raising it again needs privileges
+            if (const auto nice_value{ niceValue(priority) }) {
+                errno = 0;
+                const auto current{ getpriority(PRIO_PROCESS, 0) };
+                if (errno == 0 && current < *nice_value) {
+                    setpriority(PRIO_PROCESS, 0, *nice_value);
+                }
+            }
+
+            // searches PATH like the shell would, for prefixes such as "wine"
+            execvp(argv[0], argv.data());
+            _exit(127);
+        }
+
+        // also from here, a stop requested right away mustn't miss the group if the child hasn't set it yet
+        if (own_group) {
+            setpgid(pid, pid);
+        }
+
+        // std::system returns the raw wait status here, so the Result looks the same either way
+        int status{};
+        bool killed{};
+        while (true) {
+            const auto waited{ waitpid(pid, &status, stop.stop_possible() && !killed ? WNOHANG : 0) };
+            if (waited == -1 && errno == EINTR) {
+                continue;
+            }
+            // e.g. ECHILD with SIGCHLD ignored, std::system gives -1 too when it can't get the child's status
+            if (waited == -1) {
+                return -1;
+            }
+            if (waited != 0) {
+                break;
+            }
+
+            if (stop.stop_requested()) {
+                ::kill(own_group ? -pid : pid, SIGKILL);
+                killed = true;
+            } else {
+                std::this_thread::sleep_for(std::chrono::milliseconds(10));
+            }
+        }
+        return status;
+    }
+#endif
+} // lunar_magic_wrapper
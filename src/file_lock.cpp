@@ -0,0 +1,121 @@
+#include "../include/file_lock.h"
+
+#include <system_error>
+#include <thread>
+#include <utility>
+
+#ifdef _WIN32
+#define WIN32_LEAN_AND_MEAN
+#define NOMINMAX
+#include <windows.h>
+#else
+#include <cerrno>
+#include <fcntl.h>
+#include <sys/file.h>
+#include <unistd.h>
+#endif
+
+namespace lunar_magic_wrapper {
+    namespace {
+        constexpr std::chrono::milliseconds RETRY_INTERVAL{ 10 };
+    }
+
+#ifdef _WIN32
+    std::optional<ROMFileLock> ROMFileLock::tryAcquire(const fs::path &rom_path, std::chrono::milliseconds timeout) {
+        const auto handle{ CreateFileW(rom_path.c_str(), GENERIC_READ,
+                                       FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE, nullptr,
+                                       OPEN_EXISTING, FILE_ATTRIBUTE_NORMAL, nullptr) };
+        if (handle == INVALID_HANDLE_VALUE) {
+            throw fs::filesystem_error("Failed to open ROM for locking", rom_path,
+                                       { static_cast<int>(GetLastError()), std::system_category() });
+        }
+
+        const auto deadline{ std::chrono::steady_clock::now() + timeout };
+        while (true) {
+            // a byte nobody reads, locking the contents would lock out Lunar Magic too
+            OVERLAPPED overlapped{};
+            overlapped.OffsetHigh = 0x7FFFFFFF;
+            if (LockFileEx(handle, LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY, 0, 1, 0, &overlapped)) {
+                return ROMFileLock(handle);
+            }
+
+            if (GetLastError() != ERROR_LOCK_VIOLATION || std::chrono::steady_clock::now() >= deadline) {
+                CloseHandle(handle);
+                return std::nullopt;
+            }
+            std::this_thread::sleep_for(RETRY_INTERVAL);
+        }
+    }
+
+    ROMFileLock::ROMFileLock(ROMFileLock &&other) noexcept
+    : file_handle(std::exchange(other.file_handle, nullptr)) {}
+
+    ROMFileLock &ROMFileLock::operator=(ROMFileLock &&other) noexcept {
+        if (this != &other) {
+            release();
+            file_handle = std::exchange(other.file_handle, nullptr);
+        }
+
+        return *this;
+    }
+
+    bool ROMFileLock::ownsLock() const {
+        return file_handle != nullptr;
+    }
+
+    void ROMFileLock::release() {
+        if (file_handle) {
+            // closing the handle releases its locks
+            CloseHandle(std::exchange(file_handle, nullptr));
+        }
+    }
+#else
+    std::optional<ROMFileLock> ROMFileLock::tryAcquire(const fs::path &rom_path, std::chrono::milliseconds timeout) {
+        // close-on-exec, Lunar Magic would otherwise inherit the descriptor and keep the lock alive
+        const auto fd{ ::open(rom_path.c_str(), O_RDONLY | O_CLOEXEC) };
+        if (fd == -1) {
+            throw fs::filesystem_error("Failed to open ROM for locking", rom_path,
+                                       { errno, std::generic_category() });
+        }
+
+        const auto deadline{ std::chrono::steady_clock::now() + timeout };
+        while (true) {
+            if (flock(fd, LOCK_EX | LOCK_NB) == 0) {
+                return ROMFileLock(fd);
+            }
+
+            if ((errno != EWOULDBLOCK && errno != EINTR) || std::chrono::steady_clock::now() >= deadline) {
+                close(fd);
+                return std::nullopt;
+            }
+            std::this_thread::sleep_for(RETRY_INTERVAL);
+        }
+    }
+
+    ROMFileLock::ROMFileLock(ROMFileLock &&other) noexcept : fd(std::exchange(other.fd, -1)) {}
+
+    ROMFileLock &ROMFileLock::operator=(ROMFileLock &&other) noexcept {
+        if (this != &other) {
+            release();
+            fd = std::exchange(other.fd, -1);
+        }
+
+        return *this;
+    }
+
+    bool ROMFileLock::ownsLock() const {
+        return fd != -1;
+    }
+
+    void ROMFileLock::release() {
+        if (fd != -1) {
+            // closing the last descriptor of the open file releases the flock
+            close(std::exchange(fd, -1));
+        }
+    }
+#endif
+
+    ROMFileLock::~ROMFileLock() {
+        release();
+    }
+} // lunar_magic_wrapper
@@ -1,6 +1,299 @@
 #include "../include/lunar_magic_wrapper.h"
+#include "text.h"
+
+#include <random>
+#include <iterator>
+#include <algorithm>
+#include <cctype>
+#include <map>
+#include <deque>
+#include <numeric>
+#include <thread>
+#include <atomic>
+#include <chrono>
+#include <future>
+#include <cstdlib>
+#include <cstdio>
+#include <iostream>
 
 namespace lunar_magic_wrapper {
+    namespace {
+        // the first and last `limit` lines of everything added, without holding on to what's in between
+        class BoundedLines {
+            std::optional<size_t> limit;
+            std::vector<std::string> head{};
+            std::deque<std::string> tail{};
+            size_t dropped{};
+
+        public:
+            explicit BoundedLines(std::optional<size_t> limit) : limit(limit) {}
+
+            void add(std::string line) {
+                if (!limit || head.size() < *limit) {
+                    head.push_back(std::move(line));
+                    return;
+                }
+
+                tail.push_back(std::move(line));
+                if (tail.size() > *limit) {
+                    tail.pop_front();
+                    ++dropped;
+                }
+            }
+
+            [[nodiscard]] bool truncated() const {
+                return dropped != 0;
+            }
+
+            std::vector<std::string> lines() && {
+                auto lines{ std::move(head) };
+                if (dropped != 0) {
+                    lines.push_back(fmt::format("... ({} lines omitted) ...", dropped));
+                }
+                std::move(tail.begin(), tail.end(), std::back_inserter(lines));
+
+                return lines;
+            }
+        };
+
+        // how a shell would need the arguments written, for echoing commands that don't go through one
+        std::string commandLine(const std::vector<std::string>& arguments) {
+            std::string command_line{};
+            for (const auto& argument : arguments) {
+                if (!command_line.empty()) {
+                    command_line += ' ';
+                }
+
+                if (argument.empty() || argument.find_first_of(" \t") != std::string::npos) {
+                    command_line += '"' + argument + '"';
+                } else {
+                    command_line += argument;
+                }
+            }
+
+            return command_line;
+        }
+
+        std::string jsonString(const std::string& value) {
+            std::string escaped{ "\"" };
+            for (const auto c : value) {
+                switch (c) {
+                    case '"':
+                        escaped += "\\\"";
+                        break;
+
+                    case '\\':
+                        escaped += "\\\\";
+                        break;
+
+                    default:
+                        if (static_cast<unsigned char>(c) < 0x20) {
+                            escaped += fmt::format("\\u{:04x}", static_cast<int>(c));
+                        } else {
+                            escaped += c;
+                        }
+                }
+            }
+
+            return escaped + "\"";
+        }
+
+        // sorted generic paths of the files below directory, relative to root
+        std::vector<std::string> relativeFiles(const fs::path& root, const fs::path& directory) {
+            std::vector<std::string> files{};
+            if (fs::is_directory(directory)) {
+                for (const auto& entry : fs::recursive_directory_iterator(directory)) {
+                    if (entry.is_regular_file()) {
+                        files.push_back(fs::relative(entry.path(), root).generic_string());
+                    }
+                }
+            }
+
+            std::sort(files.begin(), files.end());
+            return files;
+        }
+
+        bool sameContents(const fs::path& path, const fs::path& other_path) {
+            if (fs::file_size(path) != fs::file_size(other_path)) {
+                return false;
+            }
+
+            std::ifstream file(path, std::ios::binary);
+            std::ifstream other_file(other_path, std::ios::binary);
+            return std::equal(std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>(),
+                              std::istreambuf_iterator<char>(other_file), std::istreambuf_iterator<char>());
+        }
+
+        // end of the last of entry_count (offset, size) pairs, nullopt if the table doesn't fit into the file
+        std::optional<size_t> sectionTableEnd(const std::vector<uint8_t>& bytes, size_t table_offset,
+                                              size_t entry_count) {
+            if (table_offset + entry_count * 8 > bytes.size()) {
+                return std::nullopt;
+            }
+
+            size_t end{ table_offset + entry_count * 8 };
+            for (size_t i{}; i < entry_count; ++i) {
+                end = std::max(end, static_cast<size_t>(readLong(bytes, table_offset + i * 8))
+                    + readLong(bytes, table_offset + i * 8 + 4));
+            }
+
+            return end;
+        }
+
+        // runs on another thread while the hooks run every interval on this one, if there are any
+        template<typename Run>
+        auto runWithActivity(Run run, const std::vector<LunarMagicWrapper::ActivityHook>& hooks,
+                             std::chrono::milliseconds interval) {
+            if (hooks.empty()) {
+                return run();
+            }
+
+            auto running{ std::async(std::launch::async, std::move(run)) };
+            while (running.wait_for(interval) != std::future_status::ready) {
+                for (const auto& hook : hooks) {
+                    hook();
+                }
+            }
+
+            return running.get();
+        }
+
+        // Runs run(stop_token) on another thread, requesting a stop on the source once neither output file has
+        // grown for timeout. Sets stalled if it did.
+        template<typename Run>
+        auto runWatchingOutput(Run run, const fs::path& out_path, const fs::path& err_path,
+                               std::chrono::milliseconds timeout, bool& stalled, std::stop_source& stop) {
+            const auto output_size{ [&] {
+                std::uintmax_t size{};
+                for (const auto& path : { out_path, err_path }) {
+                    std::error_code ec{};
+                    const auto file_size{ fs::file_size(path, ec) };
+                    size += ec ? 0 : file_size;
+                }
+                return size;
+            } };
+
+            auto running{ std::async(std::launch::async, [&] { return run(stop.get_token()); }) };
+
+            auto last_size{ output_size() };
+            auto last_progress{ std::chrono::steady_clock::now() };
+            const auto poll_interval{ std::min<std::chrono::milliseconds>(timeout / 4 + std::chrono::milliseconds(1),
+                                                                          std::chrono::milliseconds(50)) };
+
+            while (running.wait_for(poll_interval) != std::future_status::ready) {
+                if (const auto size{ output_size() }; size != last_size) {
+                    last_size = size;
+                    last_progress = std::chrono::steady_clock::now();
+                } else if (!stalled && std::chrono::steady_clock::now() - last_progress >= timeout) {
+                    stalled = true;
+                    stop.request_stop();
+                }
+            }
+
+            return running.get();
+        }
+
+        // Runs run() on another thread and records when each line of the output files first shows up, reading
+        // them as far as they've been written every few milliseconds
+        template<typename Run>
+        auto runRecordingLineTimes(Run run, const fs::path& out_path, const fs::path& err_path,
+                                   std::vector<std::chrono::system_clock::time_point>& out_times,
+                                   std::vector<std::chrono::system_clock::time_point>& err_times) {
+            std::streamoff out_read{};
+            std::streamoff err_read{};
+            const auto record_file{ [](const fs::path& path, std::streamoff& read,
+                                       std::vector<std::chrono::system_clock::time_point>& times,
+                                       std::chrono::system_clock::time_point now) {
+                std::ifstream file(path, std::ios::binary);
+                if (!file || !file.seekg(read)) {
+                    return;
+                }
+
+                for (char c{}; file.get(c); ++read) {
+                    if (c == '\n') {
+                        times.push_back(now);
+                    }
+                }
+            } };
+            // both files get the same time, so a line of one can't appear to be later than one printed after it
+            const auto record{ [&] {
+                const auto now{ std::chrono::system_clock::now() };
+                record_file(out_path, out_read, out_times, now);
+                record_file(err_path, err_read, err_times, now);
+            } };
+
+            auto running{ std::async(std::launch::async, std::move(run)) };
+            while (running.wait_for(std::chrono::milliseconds(10)) != std::future_status::ready) {
+                record();
+            }
+
+            record();
+            return running.get();
+        }
+
+        // e.g. 2026-01-31T12:00:00.250Z
+        std::string isoTimestamp(std::chrono::system_clock::time_point time) {
+            const auto days{ std::chrono::floor<std::chrono::days>(time) };
+            const std::chrono::year_month_day date{ days };
+            const std::chrono::hh_mm_ss clock{ std::chrono::floor<std::chrono::milliseconds>(time - days) };
+
+            return fmt::format("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+                               static_cast<int>(date.year()), static_cast<unsigned>(date.month()),
+                               static_cast<unsigned>(date.day()), clock.hours().count(), clock.minutes().count(),
+                               clock.seconds().count(), clock.subseconds().count());
+        }
+
+        // GFX00.bin to GFX33.bin that aren't in the Graphics folder, ignoring case like Windows does
+        std::vector<std::string> missingGraphicsFiles(const fs::path& graphics_directory) {
+            std::set<std::string> present{};
+            if (fs::is_directory(graphics_directory)) {
+                for (const auto& entry : fs::directory_iterator(graphics_directory)) {
+                    const auto name{ lower(entry.path().filename().string()) };
+                    if (entry.is_regular_file()) {
+                        present.insert(name);
+                    }
+                }
+            }
+
+            std::vector<std::string> missing{};
+            // GFX32 and GFX33 aren't in the pointer tables but get exported all the same
+            for (uint16_t number{}; number < ROM::GFX_TABLE_ENTRIES + 2; ++number) {
+                if (!present.contains(fmt::format("gfx{:02x}.bin", number))) {
+                    missing.push_back(fmt::format("GFX{:02X}.bin", number));
+                }
+            }
+
+            return missing;
+        }
+
+        // see ComparisonResult
+        std::vector<std::string> lineDifferences(const std::vector<std::string>& lines,
+                                                 const std::vector<std::string>& other_lines) {
+            const auto quoted{ [](const std::vector<std::string>& from, size_t i) {
+                return i < from.size() ? fmt::format("'{}'", from[i]) : std::string{ "nothing" };
+            } };
+
+            std::vector<std::string> differences{};
+            for (size_t i{}; i < std::max(lines.size(), other_lines.size()); ++i) {
+                if (i >= lines.size() || i >= other_lines.size() || lines[i] != other_lines[i]) {
+                    differences.push_back(fmt::format("line {}: {} vs {}", i + 1, quoted(lines, i),
+                                                      quoted(other_lines, i)));
+                }
+            }
+
+            return differences;
+        }
+
+        std::string jsonArray(const std::vector<std::string>& values) {
+            std::string array{ "[" };
+            for (const auto& value : values) {
+                array += (array.size() > 1 ? ", " : "") + jsonString(value);
+            }
+
+            return array + "]";
+        }
+    }
+
     std::string LunarMagicWrapper::romSizeToString(ROMSize rom_size) {
         switch(rom_size) {
             case ROMSize::_2MB:
@@ -9,318 +302,2401 @@ namespace lunar_magic_wrapper {
             case ROMSize::_3MB:
                 return "3MB";
 
-            case ROMSize::_4MB:
-                return "4MB";
+            case ROMSize::_4MB:
+                return "4MB";
+
+            case ROMSize::_6MB_SA1:
+                return "6MB_SA1";
+
+            case ROMSize::_8MB_SA1:
+                return "8MB_SA1";
+        }
+
+        throw std::runtime_error("Unknown ROM size passed");
+    }
+
+    std::string LunarMagicWrapper::compressionOptionToString(CompressionOption compression_option) {
+        switch(compression_option) {
+            case CompressionOption::LC_LZ2_Orig:
+                return "LC_LZ2_Orig";
+
+            case CompressionOption::LC_LZ2_Speed:
+                return "LC_LZ2_Speed";
+
+            case CompressionOption::LC_LZ3:
+                return "LC_LZ3";
+        }
+
+        throw std::runtime_error("Unknown compression option passed");
+    }
+
+    std::set<Operation> LunarMagicWrapper::parseSupportedOperations(const std::vector<std::string> &usage_lines) {
+        std::set<Operation> operations{};
+        for (const auto& line : usage_lines) {
+            const auto lower_line{ lower(line) };
+
+            for (const auto operation : ALL_OPERATIONS) {
+                const auto flag{ "-" + lower(operationToString(operation)) };
+
+                // the flag has to end where the name does, "-ExportLevel" isn't in "-ExportLevelsAsText"
+                for (auto position{ lower_line.find(flag) }; position != std::string::npos;
+                     position = lower_line.find(flag, position + 1)) {
+                    const auto end{ position + flag.size() };
+                    if (end == lower_line.size() || !std::isalnum(static_cast<unsigned char>(lower_line[end]))) {
+                        operations.insert(operation);
+                        break;
+                    }
+                }
+            }
+        }
+
+        return operations;
+    }
+
+    std::set<Operation> LunarMagicWrapper::supportedOperations(const std::optional<fs::path> &usage_path) {
+        if (usage_path) {
+            std::ifstream usage_file(*usage_path);
+            std::vector<std::string> lines{};
+            std::string line;
+            while (std::getline(usage_file, line)) {
+                lines.push_back(line);
+            }
+
+            if (auto operations{ parseSupportedOperations(lines) }; !operations.empty()) {
+                return operations;
+            }
+        }
+
+        return { ALL_OPERATIONS.begin(), ALL_OPERATIONS.end() };
+    }
+
+    std::string LunarMagicWrapper::operationToString(Operation operation) {
+        switch(operation) {
+            case Operation::EXPORT_GFX:
+                return "ExportGFX";
+
+            case Operation::EXPORT_EXGFX:
+                return "ExportExGFX";
+
+            case Operation::IMPORT_GFX:
+                return "ImportGFX";
+
+            case Operation::IMPORT_EXGFX:
+                return "ImportExGFX";
+
+            case Operation::IMPORT_ALL_GRAPHICS:
+                return "ImportAllGraphics";
+
+            case Operation::EXPORT_LEVEL:
+                return "ExportLevel";
+
+            case Operation::IMPORT_LEVEL:
+                return "ImportLevel";
+
+            case Operation::IMPORT_MAP16:
+                return "ImportMap16";
+
+            case Operation::IMPORT_CUSTOM_PALETTE:
+                return "ImportCustomPalette";
+
+            case Operation::EXPORT_SHARED_PALETTE:
+                return "ExportSharedPalette";
+
+            case Operation::IMPORT_SHARED_PALETTE:
+                return "ImportSharedPalette";
+
+            case Operation::EXPORT_ALL_MAP16:
+                return "ExportAllMap16";
+
+            case Operation::IMPORT_ALL_MAP16:
+                return "ImportAllMap16";
+
+            case Operation::EXPORT_MULTIPLE_LEVELS:
+                return "ExportMultLevels";
+
+            case Operation::IMPORT_MULTIPLE_LEVELS:
+                return "ImportMultLevels";
+
+            case Operation::EXPAND_ROM:
+                return "ExpandROM";
+
+            case Operation::CHANGE_COMPRESSION:
+                return "ChangeCompression";
+
+            case Operation::TRANSFER_GLOBAL_EXANIMATION:
+                return "TransferLevelGlobalExAnim";
+
+            case Operation::TRANSFER_OVERWORLD:
+                return "TransferOverworld";
+
+            case Operation::TRANSFER_TITLE_SCREEN:
+                return "TransferTitleScreen";
+
+            case Operation::TRANSFER_CREDITS:
+                return "TransferCredits";
+
+            case Operation::EXPORT_TITLE_MOVES:
+                return "ExportTitleMoves";
+
+            case Operation::IMPORT_TITLE_MOVES:
+                return "ImportTitleMoves";
+        }
+
+        throw std::runtime_error("Unknown operation passed");
+    }
+
+    std::optional<LunarMagicVersion> parseBanner(const std::vector<std::string> &lines) {
+        constexpr std::string_view BANNER{ "Lunar Magic " };
+
+        for (size_t i{}; i < std::min(lines.size(), BANNER_LINES); ++i) {
+            const auto& line{ lines[i] };
+            const auto start{ line.find(BANNER) };
+            if (start == std::string::npos) {
+                continue;
+            }
+
+            int major_version{};
+            int minor_version{};
+            if (std::sscanf(line.c_str() + start + BANNER.size(), "%d.%d", &major_version, &minor_version) == 2) {
+                return LunarMagicVersion{ major_version, minor_version };
+            }
+        }
+
+        return std::nullopt;
+    }
+
+    bool OutputMarkers::contains(const Result &result, const std::string &marker) {
+        if (marker.empty()) {
+            return false;
+        }
+
+        const auto contains_marker{ [&](const std::string& line) {
+            return std::search(line.begin(), line.end(), marker.begin(), marker.end(), [](char a, char b) {
+                return std::tolower(static_cast<unsigned char>(a)) == std::tolower(static_cast<unsigned char>(b));
+            }) != line.end();
+        } };
+
+        return std::any_of(result.out.begin(), result.out.end(), contains_marker)
+            || std::any_of(result.err.begin(), result.err.end(), contains_marker);
+    }
+
+    std::optional<ROMSize> OutputMarkers::suggestedROMSize(const Result &result) const {
+        constexpr std::array<std::pair<std::string_view, ROMSize>, 5> SIZES{ {
+            { "2MB", ROMSize::_2MB }, { "3MB", ROMSize::_3MB }, { "4MB", ROMSize::_4MB },
+            { "6MB", ROMSize::_6MB_SA1 }, { "8MB", ROMSize::_8MB_SA1 }
+        } };
+
+        for (const auto* lines : { &result.out, &result.err }) {
+            for (const auto& line : *lines) {
+                if (!contains({ 0, { line }, {} }, rom_too_small)) {
+                    continue;
+                }
+
+                for (const auto& [name, size] : SIZES) {
+                    if (line.find(name) != std::string::npos) {
+                        return size;
+                    }
+                }
+            }
+        }
+
+        return std::nullopt;
+    }
+
+    std::optional<std::string> OutputMarkers::newerROMFormatWarning(const Result &result) const {
+        for (const auto* lines : { &result.out, &result.err }) {
+            for (const auto& line : *lines) {
+                if (contains({ 0, { line }, {} }, newer_rom_format)) {
+                    return line;
+                }
+            }
+        }
+
+        return std::nullopt;
+    }
+
+    void LunarMagicWrapper::throwIfKnownFailure(const fs::path &rom_path, const Result &result) const {
+        if (auto warning{ output_markers.newerROMFormatWarning(result) }) {
+            throw NewerROMFormatException(fmt::format(
+                "'{}' was saved by a newer version of Lunar Magic than '{}', which has written to it anyway and may have "
+                "dropped data it doesn't know about: {}",
+                rom_path.string(), lunar_magic_path.string(), *warning
+            ), result, rom_path, std::move(*warning));
+        }
+
+        if (OutputMarkers::contains(result, output_markers.rom_too_small)) {
+            std::error_code ec{};
+            const auto file_size{ fs::file_size(rom_path, ec) };
+            const auto current_size{ ec ? 0 : file_size - file_size % 0x8000 };
+
+            auto suggested_size{ output_markers.suggestedROMSize(result) };
+            if (!suggested_size) {
+                if (current_size < 0x200000) {
+                    suggested_size = ROMSize::_2MB;
+                } else if (current_size < 0x300000) {
+                    suggested_size = ROMSize::_3MB;
+                } else if (current_size < 0x400000) {
+                    suggested_size = ROMSize::_4MB;
+                }
+            }
+
+            throw NeedsExpansionException(fmt::format(
+                "'{}' ({} bytes) needs to be expanded{} before this operation",
+                rom_path.string(), current_size,
+                suggested_size ? " to " + romSizeToString(*suggested_size) : ""
+            ), result, current_size, suggested_size);
+        }
+    }
+
+    void LunarMagicWrapper::throwIfUnexpectedMapping(const fs::path &rom_path) const {
+        if (!expected_mapping_mode) {
+            return;
+        }
+
+        const auto actual{ mappingMode(rom_path) };
+        if (actual != *expected_mapping_mode) {
+            throw UnexpectedMappingException(fmt::format(
+                "'{}' is a {} ROM, expected {}",
+                rom_path.string(), mappingModeToString(actual), mappingModeToString(*expected_mapping_mode)
+            ), rom_path, actual);
+        }
+    }
+
+    void LunarMagicWrapper::throwIfMissingInput(const fs::path &path, const std::string &description,
+                                                const std::optional<std::string> &extension) {
+        if (!fs::is_regular_file(path)) {
+            throw MissingInputFileException(fmt::format(
+                "{} '{}' does not exist", description, path.string()
+            ), path);
+        }
+
+        if (extension && !hasExtension(path, *extension)) {
+            throw MissingInputFileException(fmt::format(
+                "{} '{}' does not have the '{}' extension", description, path.string(), *extension
+            ), path);
+        }
+    }
+
+    void LunarMagicWrapper::throwIfInvalidMap16(const fs::path &map16_path) {
+        throwIfMissingInput(map16_path, "Map16 file");
+
+        try {
+            Map16File::validate(map16_path);
+        } catch (const Map16Exception& e) {
+            throw InvalidMap16Exception(fmt::format(
+                "Map16 file '{}' is invalid: {}", map16_path.string(), e.what()
+            ), map16_path);
+        }
+    }
+
+    void LunarMagicWrapper::throwIfNotROM(const fs::path &rom_path) {
+        if (!ROM::hasROMExtension(rom_path)) {
+            throw InvalidPathException(fmt::format(
+                "'{}' doesn't have a ROM extension, Lunar Magic only opens .smc, .sfc, .fig and .swc files",
+                rom_path.string()
+            ), rom_path);
+        }
+    }
+
+    ROMFileLock LunarMagicWrapper::beforeWrite(const fs::path &rom_path) const {
+        throwIfNotROM(rom_path);
+
+        ROMFileLock lock{};
+        if (file_lock_timeout) {
+            auto acquired{ ROMFileLock::tryAcquire(rom_path, *file_lock_timeout) };
+            if (!acquired) {
+                throw ROMLockedException(fmt::format(
+                    "'{}' is still locked by another process after waiting {}ms", rom_path.string(),
+                    file_lock_timeout->count()
+                ), rom_path);
+            }
+            lock = std::move(*acquired);
+        }
+
+        throwIfUnexpectedMapping(rom_path);
+
+        if (backup_before_write) {
+            const auto backup_path{ makeBackupPath(rom_path) };
+            fs::create_directories(backup_path.parent_path());
+            fs::copy_file(rom_path, backup_path);
+
+            for (const auto& hook : backup_hooks) {
+                hook(rom_path, backup_path);
+            }
+        }
+
+        return lock;
+    }
+
+    void LunarMagicWrapper::afterWrite(const fs::path &rom_path, const Result &result) const {
+        if (auto_fix_checksum) {
+            ROM::fixChecksum(rom_path);
+        }
+
+        if (minimum_free_space) {
+            const auto rom{ ROM::fromFile(rom_path) };
+            if (rom.freeBytes() < *minimum_free_space) {
+                throw OutOfSpaceException(fmt::format(
+                    "Only 0x{:X} of 0x{:X} bytes appear to be free in '{}' after {}, less than the required 0x{:X}",
+                    rom.freeBytes(), rom.size(), rom_path.string(),
+                    result.operation ? operationToString(*result.operation) : "the operation", *minimum_free_space
+                ), result, rom.usedBytes(), rom.size());
+            }
+        }
+    }
+
+    fs::path LunarMagicWrapper::makeBackupPath(const fs::path &rom_path) const {
+        const auto now{ std::chrono::system_clock::now() };
+        const auto days{ std::chrono::floor<std::chrono::days>(now) };
+        const std::chrono::year_month_day date{ days };
+        const std::chrono::hh_mm_ss time{ std::chrono::floor<std::chrono::milliseconds>(now - days) };
+
+        const auto directory{ backup_directory.value_or(rom_path.parent_path()) };
+        const auto stem{ fmt::format("{}.{:04}{:02}{:02}-{:02}{:02}{:02}{:03}", rom_path.filename().string(),
+                                     static_cast<int>(date.year()), static_cast<unsigned>(date.month()),
+                                     static_cast<unsigned>(date.day()), time.hours().count(),
+                                     time.minutes().count(), time.seconds().count(), time.subseconds().count()) };
+
+        // operations can follow each other within the same millisecond
+        auto backup_path{ directory / (stem + ".bak") };
+        for (size_t i{ 1 }; fs::exists(backup_path); ++i) {
+            backup_path = directory / fmt::format("{}-{}.bak", stem, i);
+        }
+
+        return backup_path;
+    }
+
+    void LunarMagicWrapper::throwIfGraphicsFolderMissing(const fs::path &rom_path, const Result &result) const {
+        if (OutputMarkers::contains(result, output_markers.missing_graphics_folder)) {
+            const auto expected_path{ graphicsDirectory(rom_path) };
+            throw MissingGraphicsFolderException(fmt::format(
+                "Graphics folder '{}' for '{}' not found, it needs to exist before graphics can be imported",
+                expected_path.string(), rom_path.string()
+            ), result, expected_path);
+        }
+    }
+
+    void LunarMagicWrapper::throwIfNoExportedGraphics(const fs::path &rom_path) const {
+        if (!check_exported_graphics) {
+            return;
+        }
+
+        const auto graphics_directory{ graphicsDirectory(rom_path) };
+        if (auto missing{ missingGraphicsFiles(graphics_directory) }; !missing.empty()) {
+            const auto message{ fmt::format(
+                "Graphics folder '{}' for '{}' is missing {} exported file(s), e.g. '{}', export GFX before importing them",
+                graphics_directory.string(), rom_path.string(), missing.size(), missing.front()
+            ) };
+            throw NoExportedGraphicsException(message, graphics_directory, std::move(missing));
+        }
+    }
+
+    std::string LunarMagicWrapper::buildCommand(const std::string &call_string, const fs::path &out_path,
+                                                const fs::path &err_path) const {
+        std::string prefix{};
+        for (const auto& token : command_prefix) {
+            prefix += fmt::format(R"("{}" )", token);
+        }
+
+        std::string suffix{};
+        for (const auto& token : command_suffix) {
+            suffix += fmt::format(R"( "{}")", token);
+        }
+
+        std::string code_page_prefix{};
+#ifdef _WIN32
+        if (code_page) {
+            code_page_prefix = fmt::format("chcp {} >nul && ", *code_page);
+        }
+#endif
+
+        return fmt::format(R"("{}{}"{}" {}{} > "{}" 2> "{}"")", code_page_prefix, prefix, executablePath().string(),
+                           call_string, suffix, out_path.string(), err_path.string());
+    }
+
+    std::string LunarMagicWrapper::outputPath(const fs::path &path) const {
+        if (create_output_directories && path.has_parent_path()) {
+            fs::create_directories(path.parent_path());
+        }
+
+        return resolvePath(path, false);
+    }
+
+    std::string LunarMagicWrapper::resolvePath(const fs::path &path, bool must_exist) const {
+        if (!canonicalize_paths) {
+            return path.string();
+        }
+
+        std::error_code ec{};
+        auto resolved{ must_exist ? fs::canonical(path, ec) : fs::weakly_canonical(path, ec) };
+        if (ec) {
+            throw InvalidPathException(fmt::format(
+                "Failed to resolve '{}': {}", path.string(), ec.message()
+            ), path);
+        }
+
+        // drop the extended-length prefix Windows may add, Lunar Magic doesn't understand it
+        auto resolved_string{ resolved.string() };
+        if (resolved_string.starts_with(R"(\\?\)") && !resolved_string.starts_with(R"(\\?\UNC\)")) {
+            resolved_string.erase(0, 4);
+        }
+
+        return resolved_string;
+    }
+
+    fs::path LunarMagicWrapper::makeTempDirectory(const std::vector<fs::path> &fallbacks) {
+        std::random_device device{};
+        std::mt19937_64 generator{ device() };
+
+        std::string failures{};
+        std::error_code last_error{};
+        const auto add_failure{ [&](const std::string& location, std::error_code error) {
+            failures += fmt::format("{}'{}' ({})", failures.empty() ? "" : ", ", location, error.message());
+            last_error = error;
+        } };
+
+        std::vector<fs::path> locations{};
+        std::error_code ec{};
+        if (auto system_temp{ fs::temp_directory_path(ec) }; !ec) {
+            locations.push_back(std::move(system_temp));
+        } else {
+            add_failure("system temp directory", ec);
+        }
+        locations.insert(locations.end(), fallbacks.begin(), fallbacks.end());
+
+        for (const auto& location : locations) {
+            while (true) {
+                auto path{ location / fmt::format("lm_wrapper_{:016x}", generator()) };
+                if (fs::create_directory(path, ec)) {
+                    return path;
+                }
+                if (ec) {
+                    add_failure(location.string(), ec);
+                    break;
+                }
+            }
+        }
+
+        throw TempDirectoryException(fmt::format("Failed to create a temporary directory, tried {}", failures),
+                                     last_error);
+    }
+
+    LunarMagicWrapper::TempDirectory::TempDirectory(fs::path directory) : directory{ std::move(directory) } {}
+
+    LunarMagicWrapper::TempDirectory::~TempDirectory() {
+        // nothing to do about a failure here, and throwing from a destructor would be worse
+        std::error_code ec{};
+        fs::remove_all(directory, ec);
+    }
+
+    const fs::path& LunarMagicWrapper::TempDirectory::path() const {
+        return directory;
+    }
+
+    std::string Diagnostics::toString() const {
+        const auto join{ [](const std::vector<std::string>& tokens) {
+            std::string joined{};
+            for (const auto& token : tokens) {
+                joined += (joined.empty() ? "" : " ") + token;
+            }
+            return joined.empty() ? std::string{ "(none)" } : joined;
+        } };
+
+        std::string text{};
+        text += fmt::format("Lunar Magic path: {}\n", lunar_magic_path.string());
+        text += fmt::format("Lunar Magic exists: {}{}\n", lunar_magic_exists ? "yes" : "no",
+                            lunar_magic_is_directory ? " (directory, not the executable)" : "");
+        text += fmt::format("Lunar Magic version: {}\n", lunar_magic_version.value_or("unknown"));
+        text += fmt::format("OS: {} ({})\n", os, architecture);
+        text += fmt::format("Shell available: {}\n", shell_available ? "yes" : "no");
+        text += fmt::format("Command prefix: {}\n", join(command_prefix));
+        text += fmt::format("Command suffix: {}\n", join(command_suffix));
+        text += fmt::format("Canonicalize paths: {}\n", canonicalize_paths ? "yes" : "no");
+        text += fmt::format("Tee log: {}\n", tee_log_path ? tee_log_path->string() : "(none)");
+        text += fmt::format("Expected mapping mode: {}\n",
+                            expected_mapping_mode ? mappingModeToString(*expected_mapping_mode) : "(any)");
+        text += fmt::format("Auto fix checksum: {}\n", auto_fix_checksum ? "yes" : "no");
+        text += fmt::format("Backups: {}\n", backups ? backups->string() : "(off)");
+        text += fmt::format("Minimum free space: {}\n",
+                            minimum_free_space ? fmt::format("0x{:X} bytes", *minimum_free_space) : "(off)");
+
+        return text;
+    }
+
+    Diagnostics LunarMagicWrapper::diagnostics() const {
+#if defined(_WIN32)
+        constexpr auto OS{ "Windows" };
+#elif defined(__APPLE__)
+        constexpr auto OS{ "macOS" };
+#elif defined(__linux__)
+        constexpr auto OS{ "Linux" };
+#else
+        constexpr auto OS{ "unknown" };
+#endif
+
+#if defined(_M_X64) || defined(__x86_64__)
+        constexpr auto ARCHITECTURE{ "x86_64" };
+#elif defined(_M_IX86) || defined(__i386__)
+        constexpr auto ARCHITECTURE{ "x86" };
+#elif defined(_M_ARM64) || defined(__aarch64__)
+        constexpr auto ARCHITECTURE{ "arm64" };
+#else
+        constexpr auto ARCHITECTURE{ "unknown" };
+#endif
+
+        std::error_code ec{};
+        const auto exists{ fs::exists(lunar_magic_path, ec) };
+        const auto is_directory{ fs::is_directory(lunar_magic_path, ec) };
+
+        return {
+            lunar_magic_path,
+            exists,
+            is_directory,
+            exists && !is_directory ? readFileVersion(lunar_magic_path) : std::nullopt,
+            OS,
+            ARCHITECTURE,
+            // only asks whether there is a command processor, doesn't start one
+            std::system(nullptr) != 0,
+            command_prefix,
+            command_suffix,
+            canonicalize_paths,
+            tee_log_path,
+            expected_mapping_mode,
+            auto_fix_checksum,
+            backup_before_write ? std::optional{ backup_directory.value_or("(next to the ROM)") } : std::nullopt,
+            minimum_free_space
+        };
+    }
+
+    fs::path LunarMagicWrapper::executablePath() const {
+        std::error_code ec{};
+        if (!fs::is_directory(lunar_magic_path, ec)) {
+            return lunar_magic_path;
+        }
+
+        for (const auto name : EXECUTABLE_NAMES) {
+            const auto candidate{ lunar_magic_path / name };
+            if (fs::is_regular_file(candidate, ec)) {
+                return candidate;
+            }
+        }
+
+        throw LunarMagicMissingException(fmt::format(
+            "'{}' is a directory, not Lunar Magic's executable, and contains none of the usual executable names",
+            lunar_magic_path.string()
+        ), lunar_magic_path);
+    }
+
+    std::optional<fs::path> LunarMagicWrapper::findLunarMagic() {
+#ifdef _WIN32
+        constexpr auto PATH_SEPARATOR{ ';' };
+#else
+        constexpr auto PATH_SEPARATOR{ ':' };
+#endif
+        std::vector<fs::path> directories{};
+
+        if (const auto* path_variable{ std::getenv("PATH") }) {
+            std::string_view remaining{ path_variable };
+            while (!remaining.empty()) {
+                const auto separator{ remaining.find(PATH_SEPARATOR) };
+                const auto entry{ remaining.substr(0, separator) };
+                if (!entry.empty()) {
+                    directories.emplace_back(entry);
+                }
+                remaining = separator == std::string_view::npos ? "" : remaining.substr(separator + 1);
+            }
+        }
+
+        for (const auto* variable : { "ProgramFiles", "ProgramFiles(x86)", "LOCALAPPDATA" }) {
+            if (const auto* value{ std::getenv(variable) }) {
+                directories.push_back(fs::path(value) / "Lunar Magic");
+            }
+        }
+        directories.emplace_back("C:/Lunar Magic");
+
+        for (const auto& directory : directories) {
+            for (const auto name : EXECUTABLE_NAMES) {
+                std::error_code ec{};
+                const auto candidate{ directory / name };
+                if (fs::is_regular_file(candidate, ec)) {
+                    return candidate;
+                }
+            }
+        }
+
+        return std::nullopt;
+    }
+
+    std::optional<LunarMagicWrapper> LunarMagicWrapper::fromDefaultLocation() {
+        if (auto path{ findLunarMagic() }) {
+            return LunarMagicWrapper(std::move(*path));
+        }
+
+        return std::nullopt;
+    }
+
+    std::mutex &LunarMagicWrapper::romMutex(const fs::path &rom_path) {
+        static std::mutex registry_mutex{};
+        static std::map<fs::path, std::mutex> rom_mutexes{};
+
+        std::error_code ec{};
+        auto key{ fs::weakly_canonical(rom_path, ec) };
+        if (ec) {
+            key = fs::absolute(rom_path);
+        }
+
+        std::lock_guard lock{ registry_mutex };
+        return rom_mutexes[key];
+    }
+
+    std::optional<std::vector<std::string>> LunarMagicWrapper::directArguments(const std::string &call_string) const {
+        // characters cmd or sh would do something with, beyond the quotes buildCommand puts around paths
+        constexpr std::string_view SHELL_CHARACTERS{ "&|<>^%!;$`*?~()\n\r" };
+
+#ifdef _WIN32
+        // chcp only exists in the shell
+        if (code_page) {
+            return std::nullopt;
+        }
+#endif
+
+        std::vector<std::string> arguments{ command_prefix.begin(), command_prefix.end() };
+        arguments.push_back(executablePath().string());
+        arguments.insert(arguments.end(), command_suffix.begin(), command_suffix.end());
+
+        for (const auto& argument : arguments) {
+            if (argument.find_first_of(SHELL_CHARACTERS) != std::string::npos || argument.find('"') != std::string::npos) {
+                return std::nullopt;
+            }
+        }
+
+        if (call_string.find_first_of(SHELL_CHARACTERS) != std::string::npos) {
+            return std::nullopt;
+        }
+
+        // split the call the way the shell would, quotes group words and are dropped
+        std::vector<std::string> call_arguments{};
+        std::optional<std::string> current{};
+        bool quoted{};
+
+        for (const auto c : call_string) {
+            if (c == '"') {
+                quoted = !quoted;
+                current = current.value_or("");
+            } else if ((c == ' ' || c == '\t') && !quoted) {
+                if (current) {
+                    call_arguments.push_back(*current);
+                    current.reset();
+                }
+            } else {
+                current = current.value_or("") + c;
+            }
+        }
+
+        if (quoted) {
+            return std::nullopt;
+        }
+        if (current) {
+            call_arguments.push_back(*current);
+        }
+
+        arguments.insert(arguments.begin() + static_cast<std::ptrdiff_t>(command_prefix.size()) + 1,
+                         call_arguments.begin(), call_arguments.end());
+        return arguments;
+    }
+
+    std::vector<fs::path> LunarMagicWrapper::tempFallbacks(const std::string &arguments) const {
+        std::vector<fs::path> fallbacks{};
+
+        // every operation passes the ROM it's about first
+        if (arguments.starts_with('"')) {
+            if (const auto end{ arguments.find('"', 1) }; end != std::string::npos) {
+                fallbacks.push_back(fs::path(arguments.substr(1, end - 1)).parent_path());
+            }
+        }
+
+        if (tee_log_path) {
+            fallbacks.push_back(tee_log_path->parent_path());
+        }
+
+        return fallbacks;
+    }
+
+    Result LunarMagicWrapper::call(Operation operation, const std::string &arguments, std::stop_token cancel) {
+        const auto call_string{ fmt::format("-{} {}", operationToString(operation), arguments) };
+
+        if (cancel.stop_requested()) {
+            throw CancelledException(fmt::format("'{}' was cancelled before it started", call_string),
+                                     Result{ 0, {}, {}, false, operation });
+        }
+
+        if (time_budget && getTimeSpent() >= *time_budget) {
+            const auto spent{ std::chrono::duration_cast<std::chrono::milliseconds>(getTimeSpent()) };
+            throw BudgetExceededException(fmt::format(
+                "Not running '{}', Lunar Magic already ran for {}ms of the {}ms budget",
+                call_string, spent.count(), time_budget->count()
+            ), *time_budget, spent);
+        }
+        const auto start{ std::chrono::steady_clock::now() };
+
+        int exit_code{};
+        bool stalled{};
+        std::atomic<bool> cancelled{};
+        BoundedLines out_lines{ max_output_lines };
+        BoundedLines err_lines{ max_output_lines };
+
+        if (spawner) {
+            for (const auto& hook : before_hooks) {
+                hook(call_string);
+            }
+
+            if (echo_commands) {
+                std::cerr << "+ " << call_string << std::endl;
+            }
+
+            const auto spawned{ runWithActivity([&] { return spawner(call_string); }, activity_hooks,
+                                                activity_interval) };
+            exit_code = spawned.error_code.value_or(0);
+            const auto prefix{ timestamp_output ? isoTimestamp(std::chrono::system_clock::now()) + ' ' : "" };
+            for (const auto& line : spawned.out) {
+                out_lines.add(prefix + line);
+            }
+            for (const auto& line : spawned.err) {
+                err_lines.add(prefix + line);
+            }
+        } else {
+            // throws for an unusable lunar_magic_path before there's a temporary directory to clean up
+            executablePath();
+
+            // unique per call so concurrent calls don't read each other's output
+            const TempDirectory temp_directory{ makeTempDirectory(tempFallbacks(arguments)) };
+            const auto temp_out{ temp_directory.path() / "lm_wrapper_out.log" };
+            const auto temp_err{ temp_directory.path() / "lm_wrapper_err.log" };
+
+            const auto str{ buildCommand(call_string, temp_out, temp_err) };
+            const auto direct_arguments{ directArguments(call_string) };
+
+            for (const auto& hook : before_hooks) {
+                hook(call_string);
+            }
+
+            // the process is killed through this, for a stalled prompt or once the caller cancels
+            std::stop_source stop{};
+            const std::stop_callback on_cancel{ cancel, [&] {
+                cancelled = true;
+                stop.request_stop();
+            } };
+
+            const auto run_lunar_magic{ [&] {
+                // skipping the shell saves starting a second process on every call
+                std::optional<int> direct_exit_code{};
+                if (direct_arguments) {
+                    if (echo_commands) {
+                        std::cerr << "+ " << commandLine(*direct_arguments) << std::endl;
+                    }
+                    if (prompt_timeout) {
+                        direct_exit_code = runWatchingOutput([&](std::stop_token stop_token) {
+                            return runDirectly(*direct_arguments, temp_out, temp_err, hide_window,
+                                               process_priority, std::move(stop_token));
+                        }, temp_out, temp_err, *prompt_timeout, stalled, stop);
+                    } else if (cancel.stop_possible()) {
+                        direct_exit_code = runDirectly(*direct_arguments, temp_out, temp_err, hide_window,
+                                                       process_priority, stop.get_token());
+                    } else {
+                        direct_exit_code = runDirectly(*direct_arguments, temp_out, temp_err, hide_window,
+                                                       process_priority);
+                    }
+                }
+
+                if (direct_exit_code) {
+                    return *direct_exit_code;
+                }
+
+                if (echo_commands) {
+                    std::cerr << "+ " << str << std::endl;
+                }
+                return std::system(str.c_str());
+            } };
+
+            std::vector<std::chrono::system_clock::time_point> out_times{};
+            std::vector<std::chrono::system_clock::time_point> err_times{};
+            exit_code = runWithActivity([&] {
+                if (timestamp_output) {
+                    return runRecordingLineTimes(run_lunar_magic, temp_out, temp_err, out_times, err_times);
+                }
+                return run_lunar_magic();
+            }, activity_hooks, activity_interval);
+
+            // a last line without a line break wasn't seen while running
+            const auto read_lines{ [&](const fs::path& path, const auto& times, BoundedLines& lines) {
+                std::ifstream file(path);
+                std::string line;
+                for (size_t i{}; std::getline(file, line); ++i) {
+                    if (timestamp_output) {
+                        line = fmt::format("{} {}", isoTimestamp(i < times.size() ? times[i]
+                                                                 : std::chrono::system_clock::now()), line);
+                    }
+                    lines.add(line);
+                }
+            } };
+            read_lines(temp_out, out_times, out_lines);
+            read_lines(temp_err, err_times, err_lines);
+        }
+
+        *time_spent += (std::chrono::steady_clock::now() - start).count();
+
+        const auto truncated{ out_lines.truncated() || err_lines.truncated() };
+        auto out{ std::move(out_lines).lines() };
+        auto err{ std::move(err_lines).lines() };
+
+        if (collapse_repeated_lines) {
+            out = collapseRepeatedLines(out);
+            err = collapseRepeatedLines(err);
+        }
+
+        const auto succeeded{ success_policy ? success_policy(operation, exit_code, out, err) : exit_code == 0 };
+        Result result{ exit_code, out, err, succeeded, operation, truncated };
+
+        teeLog(call_string, result);
+
+        for (const auto& hook : after_hooks) {
+            hook(call_string, result);
+        }
+
+        if (cancelled) {
+            throw CancelledException(fmt::format("'{}' was cancelled", call_string), result);
+        }
+
+        if (stalled) {
+            throw InteractivePromptException(fmt::format(
+                "'{}' printed nothing for {}ms while running and was killed, it's probably waiting on a prompt",
+                call_string, prompt_timeout->count()
+            ), result, *prompt_timeout);
+        }
+
+        return result;
+    }
+
+    std::optional<std::pair<std::chrono::system_clock::time_point, std::string>> LunarMagicWrapper::splitTimestamp(
+            const std::string &line) {
+        int year{};
+        unsigned month{};
+        unsigned day{};
+        unsigned hours{};
+        unsigned minutes{};
+        unsigned seconds{};
+        unsigned milliseconds{};
+        int length{};
+        if (std::sscanf(line.c_str(), "%4d-%2u-%2uT%2u:%2u:%2u.%3uZ%n", &year, &month, &day, &hours, &minutes,
+                        &seconds, &milliseconds, &length) != 7 || length != 24 || line.size() < 25 || line[24] != ' ') {
+            return std::nullopt;
+        }
+
+        const std::chrono::year_month_day date{ std::chrono::year{ year }, std::chrono::month{ month },
+                                                std::chrono::day{ day } };
+        if (!date.ok()) {
+            return std::nullopt;
+        }
+
+        const auto time{ std::chrono::sys_days{ date } + std::chrono::hours{ hours } + std::chrono::minutes{ minutes }
+                         + std::chrono::seconds{ seconds } + std::chrono::milliseconds{ milliseconds } };
+        return std::make_pair(std::chrono::system_clock::time_point{ time }, line.substr(25));
+    }
+
+    void LunarMagicWrapper::teeLog(const std::string &call_string, const Result &result) const {
+        if (!tee_log_path) {
+            return;
+        }
+
+        static std::mutex tee_mutex{};
+        std::lock_guard lock{ tee_mutex };
+
+        std::ofstream log_file(*tee_log_path, std::ios::app);
+        log_file << fmt::format("=== {} (exit code {}) ===\n", call_string, result.error_code.value_or(0));
+        for (const auto& line : result.out) {
+            log_file << line << '\n';
+        }
+        for (const auto& line : result.err) {
+            log_file << line << '\n';
+        }
+    }
+
+    std::vector<BatchResult> LunarMagicWrapper::forEachROM(const std::vector<fs::path> &rom_paths,
+                                                           size_t parallelism,
+                                                           const std::function<Result(const fs::path &)> &operation) {
+        std::vector<std::optional<BatchResult>> slots(rom_paths.size());
+        std::atomic<size_t> next{};
+
+        const auto worker{ [&] {
+            for (auto i{ next++ }; i < rom_paths.size(); i = next++) {
+                const auto& rom_path{ rom_paths[i] };
+
+                try {
+                    std::lock_guard lock{ romMutex(rom_path) };
+                    slots[i].emplace(BatchResult{ rom_path, operation(rom_path), nullptr });
+                } catch (...) {
+                    slots[i].emplace(BatchResult{ rom_path, std::nullopt, std::current_exception() });
+                }
+            }
+        } };
+
+        std::vector<std::thread> threads{};
+        for (size_t i{}; i < std::max<size_t>(1, std::min(parallelism, rom_paths.size())); ++i) {
+            threads.emplace_back(worker);
+        }
+        for (auto& thread : threads) {
+            thread.join();
+        }
+
+        std::vector<BatchResult> results{};
+        results.reserve(slots.size());
+        for (auto& slot : slots) {
+            results.push_back(std::move(*slot));
+        }
+
+        return results;
+    }
+
+    std::vector<BatchResult> LunarMagicWrapper::importGFXMany(const std::vector<fs::path> &rom_paths,
+                                                              size_t parallelism) {
+        return forEachROM(rom_paths, parallelism, [this](const fs::path& rom_path) {
+            return importGFX(rom_path);
+        });
+    }
+
+    std::vector<BatchResult> LunarMagicWrapper::importSharedPaletteMany(const std::vector<fs::path> &rom_paths,
+                                                                        const fs::path &shared_palette_path,
+                                                                        size_t parallelism) {
+        throwIfMissingInput(shared_palette_path, "Shared palette file");
+
+        if (const auto size{ fs::file_size(shared_palette_path) }; size != SHARED_PALETTE_SIZE) {
+            throw InvalidPathException(fmt::format(
+                "Shared palette file '{}' is 0x{:X} bytes, expected 0x{:X}",
+                shared_palette_path.string(), size, SHARED_PALETTE_SIZE
+            ), shared_palette_path);
+        }
+
+        return forEachROM(rom_paths, parallelism, [&](const fs::path& rom_path) {
+            return importSharedPalette(rom_path, shared_palette_path);
+        });
+    }
+
+    std::vector<uint8_t> LunarMagicWrapper::withROMBytes(const std::vector<uint8_t> &rom_bytes,
+                                                         const std::function<void(const fs::path &)> &operation) {
+        const TempDirectory temp_directory{ makeTempDirectory() };
+        const auto rom_path{ temp_directory.path() / "rom.smc" };
+
+        {
+            std::ofstream rom_file(rom_path, std::ios::binary);
+            rom_file.write(reinterpret_cast<const char*>(rom_bytes.data()),
+                           static_cast<std::streamsize>(rom_bytes.size()));
+        }
+
+        operation(rom_path);
+
+        std::ifstream rom_file(rom_path, std::ios::binary);
+        std::vector<uint8_t> result{ std::istreambuf_iterator<char>(rom_file), std::istreambuf_iterator<char>() };
+        rom_file.close();
+
+        return result;
+    }
+
+    std::optional<fs::path> LunarMagicWrapper::findGraphicsFolder(const fs::path &root) {
+        const auto is_gfx_file{ [](const fs::directory_entry& entry) {
+            const auto name{ lower(entry.path().filename().string()) };
+            return entry.is_regular_file() && name.starts_with("gfx") && name.ends_with(".bin");
+        } };
+
+        if (std::any_of(fs::directory_iterator(root), fs::directory_iterator(), is_gfx_file)) {
+            return root;
+        }
+
+        std::optional<fs::path> shallowest{};
+        int shallowest_depth{};
+        for (auto it{ fs::recursive_directory_iterator(root) }; it != fs::recursive_directory_iterator(); ++it) {
+            const auto name{ lower(it->path().filename().string()) };
+
+            if (it->is_directory() && name == "graphics" && (!shallowest || it.depth() < shallowest_depth)) {
+                shallowest = it->path();
+                shallowest_depth = it.depth();
+            }
+        }
+
+        return shallowest;
+    }
+
+    void LunarMagicWrapper::extractArchive(const fs::path &archive_path, const fs::path &destination) {
+        fs::create_directories(destination);
+
+#ifdef _WIN32
+        const auto command{ fmt::format(R"(""tar" -xf "{}" -C "{}"")", archive_path.string(), destination.string()) };
+#else
+        const auto command{ hasExtension(archive_path, ".zip")
+            ? fmt::format(R"(unzip -q -o "{}" -d "{}" > /dev/null 2>&1)", archive_path.string(), destination.string())
+            : fmt::format(R"(tar -xf "{}" -C "{}" > /dev/null 2>&1)", archive_path.string(), destination.string()) };
+#endif
+
+        if (std::system(command.c_str()) != 0) {
+            throw InvalidPathException(fmt::format("Failed to extract archive '{}'", archive_path.string()),
+                                       archive_path);
+        }
+    }
+
+    void LunarMagicWrapper::createArchive(const fs::path &directory_path, const fs::path &archive_path) {
+        const auto absolute_archive{ fs::absolute(archive_path) };
+        fs::remove(absolute_archive);
+
+#ifdef _WIN32
+        const auto command{ fmt::format(R"(""tar" -a -cf "{}" -C "{}" ."")",
+                                        absolute_archive.string(), directory_path.string()) };
+#else
+        const auto command{ hasExtension(archive_path, ".zip")
+            ? fmt::format(R"(cd "{}" && zip -q -r "{}" . > /dev/null 2>&1)",
+                          directory_path.string(), absolute_archive.string())
+            : fmt::format(R"(tar -a -cf "{}" -C "{}" . > /dev/null 2>&1)",
+                          absolute_archive.string(), directory_path.string()) };
+#endif
+
+        if (std::system(command.c_str()) != 0) {
+            throw InvalidPathException(fmt::format("Failed to create archive '{}'", archive_path.string()),
+                                       archive_path);
+        }
+    }
+
+    std::optional<std::string> LunarMagicWrapper::readFileVersion(const fs::path &executable_path) {
+        constexpr std::array<char, 4> SIGNATURE{ '\xBD', '\x04', '\xEF', '\xFE' };
+
+        std::ifstream executable(executable_path, std::ios::binary);
+        if (!executable) {
+            return std::nullopt;
+        }
+
+        const std::vector<char> bytes{ std::istreambuf_iterator<char>(executable), std::istreambuf_iterator<char>() };
+        const auto signature{ std::search(bytes.begin(), bytes.end(), SIGNATURE.begin(), SIGNATURE.end()) };
+
+        // signature, struct version, then the most and least significant halves of the file version
+        if (std::distance(signature, bytes.end()) < 16) {
+            return std::nullopt;
+        }
+
+        const auto word{ [&](size_t offset) {
+            const auto* at{ &*(signature + static_cast<std::ptrdiff_t>(offset)) };
+            return static_cast<uint8_t>(at[0]) | (static_cast<uint8_t>(at[1]) << 8);
+        } };
+
+        return fmt::format("{}.{}.{}.{}", word(10), word(8), word(14), word(12));
+    }
+
+    Result LunarMagicWrapper::importGFXFromArchive(const fs::path &rom_path, const fs::path &archive_path) {
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        auto source{ archive_path };
+        if (!fs::is_directory(archive_path)) {
+            source = temp_directory.path() / "extracted";
+            extractArchive(archive_path, source);
+        }
+
+        const auto graphics_folder{ findGraphicsFolder(source) };
+        if (!graphics_folder) {
+            throw InvalidPathException(fmt::format(
+                "'{}' contains neither a Graphics folder nor GFX files", archive_path.string()
+            ), archive_path);
+        }
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        const auto staging{ temp_directory.path() / "staging" };
+        const auto staged_rom{ staging / rom_path.filename() };
+        fs::create_directories(staging);
+        fs::copy(*graphics_folder, graphicsDirectory(staged_rom), fs::copy_options::recursive);
+        fs::copy_file(rom_path, staged_rom);
+
+        auto result{ call(Operation::IMPORT_GFX, fmt::format(R"("{}")", inputPath(staged_rom))) };
+
+        throwIfKnownFailure(rom_path, result);
+        throwIfGraphicsFolderMissing(rom_path, result);
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to import GFX from '{}' into '{}' using '{}'",
+                archive_path.string(), rom_path.string(), lunar_magic_path.string()
+            ), result);
+        }
+
+        fs::copy_file(staged_rom, rom_path, fs::copy_options::overwrite_existing);
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    Result LunarMagicWrapper::exportGFX(const fs::path &rom_path) {
+        return exportGFX(rom_path, {});
+    }
+
+    Result LunarMagicWrapper::exportGFX(const fs::path &rom_path, std::stop_token cancel) {
+        auto result{ call(Operation::EXPORT_GFX, fmt::format(R"("{}")", inputPath(rom_path)), std::move(cancel)) };
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to export GFX from '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()),
+                result
+            );
+        }
+
+        return result;
+    }
+
+    int LunarMagicWrapper::exportGFXInto(const fs::path &rom_path, std::ostream &output) {
+        const auto result{ call(Operation::EXPORT_GFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        for (const auto* lines : { &result.out, &result.err }) {
+            for (const auto& line : *lines) {
+                output << line << '\n';
+            }
+        }
+
+        return result.error_code.value_or(0);
+    }
+
+    std::future<Result> LunarMagicWrapper::exportGFXAsync(const fs::path &rom_path, std::stop_token cancel) {
+        return std::async(std::launch::async, [this, rom_path, cancel = std::move(cancel)] {
+            return exportGFX(rom_path, cancel);
+        });
+    }
+
+    Result LunarMagicWrapper::exportExGFX(const fs::path &rom_path) {
+        auto result{ call(Operation::EXPORT_EXGFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to export ExGFX from '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), result);
+        }
+
+        return result;
+    }
+
+    fs::path LunarMagicWrapper::graphicsDirectory(const fs::path &rom_path) {
+        return rom_path.parent_path() / "Graphics";
+    }
+
+    fs::path LunarMagicWrapper::exGraphicsDirectory(const fs::path &rom_path) {
+        return rom_path.parent_path() / "ExGraphics";
+    }
+
+    bool LunarMagicWrapper::graphicsDirectoryExists(const fs::path &rom_path) {
+        return fs::is_directory(graphicsDirectory(rom_path));
+    }
+
+    bool LunarMagicWrapper::exGraphicsDirectoryExists(const fs::path &rom_path) {
+        return fs::is_directory(exGraphicsDirectory(rom_path));
+    }
+
+    bool LunarMagicWrapper::hasExportedGraphics(const fs::path &rom_path) {
+        return missingGraphicsFiles(graphicsDirectory(rom_path)).empty();
+    }
+
+    Result LunarMagicWrapper::importGFX(const fs::path &rom_path) {
+        throwIfNoExportedGraphics(rom_path);
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_GFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        throwIfKnownFailure(rom_path, result);
+        throwIfGraphicsFolderMissing(rom_path, result);
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to import GFX into '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), result);
+        }
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    GFXImportOutcome LunarMagicWrapper::importGFXIdempotent(const fs::path &rom_path) {
+        const auto graphics_folder{ graphicsDirectory(rom_path) };
+        if (!graphicsDirectoryExists(rom_path)) {
+            return { importGFX(rom_path), ImportChange::IMPORTED };
+        }
+
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        // exporting next to the actual ROM would overwrite the graphics about to be compared
+        const auto staged_rom{ temp_directory.path() / rom_path.filename() };
+        fs::copy_file(rom_path, staged_rom);
+        auto export_result{ exportGFX(staged_rom) };
+
+        bool unchanged{ true };
+        for (const auto& file : relativeFiles(graphics_folder, graphics_folder)) {
+            const auto exported{ graphicsDirectory(staged_rom) / file };
+            if (!fs::is_regular_file(exported) || !sameContents(exported, graphics_folder / file)) {
+                unchanged = false;
+                break;
+            }
+        }
+
+        if (unchanged) {
+            return { std::move(export_result), ImportChange::UNCHANGED };
+        }
+
+        return { importGFX(rom_path), ImportChange::IMPORTED };
+    }
+
+    bool LunarMagicWrapper::graphicsMatch(const fs::path &rom_path, const fs::path &other_rom_path) {
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        // separate directories, the ROMs may well have the same file name
+        const auto staged_rom{ temp_directory.path() / "a" / rom_path.filename() };
+        const auto staged_other_rom{ temp_directory.path() / "b" / other_rom_path.filename() };
+        fs::create_directories(staged_rom.parent_path());
+        fs::create_directories(staged_other_rom.parent_path());
+        fs::copy_file(rom_path, staged_rom);
+        fs::copy_file(other_rom_path, staged_other_rom);
+
+        exportGFX(staged_rom);
+        exportGFX(staged_other_rom);
+
+        const auto graphics_folder{ graphicsDirectory(staged_rom) };
+        const auto other_graphics_folder{ graphicsDirectory(staged_other_rom) };
+        const auto files{ relativeFiles(graphics_folder, graphics_folder) };
+
+        const auto match{ files == relativeFiles(other_graphics_folder, other_graphics_folder)
+            && std::all_of(files.begin(), files.end(), [&](const std::string& file) {
+                return sameContents(graphics_folder / file, other_graphics_folder / file);
+            }) };
+
+        return match;
+    }
+
+    Result LunarMagicWrapper::importExGFX(const fs::path &rom_path) {
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_EXGFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        throwIfKnownFailure(rom_path, result);
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to import ExGFX into '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), result);
+        }
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    Result LunarMagicWrapper::importAllGraphics(const fs::path &rom_path) {
+        throwIfNoExportedGraphics(rom_path);
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_ALL_GRAPHICS, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        throwIfKnownFailure(rom_path, result);
+        throwIfGraphicsFolderMissing(rom_path, result);
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to import all graphics into '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), result);
+        }
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    Result LunarMagicWrapper::importGFXAndExGFX(const fs::path &rom_path) {
+        throwIfNoExportedGraphics(rom_path);
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto gfx_result{ call(Operation::IMPORT_GFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        throwIfKnownFailure(rom_path, gfx_result);
+        throwIfGraphicsFolderMissing(rom_path, gfx_result);
+
+        if (!gfx_result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to import GFX into '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), gfx_result);
+        }
+
+        auto exgfx_result{ call(Operation::IMPORT_EXGFX, fmt::format(R"("{}")", inputPath(rom_path))) };
+
+        throwIfKnownFailure(rom_path, exgfx_result);
+
+        if (!exgfx_result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Imported GFX but failed to import ExGFX into '{}' using '{}'",
+                rom_path.string(),
+                lunar_magic_path.string()
+            ), exgfx_result);
+        }
+
+        auto out{ gfx_result.out };
+        out.insert(out.end(), exgfx_result.out.begin(), exgfx_result.out.end());
+        auto err{ gfx_result.err };
+        err.insert(err.end(), exgfx_result.err.begin(), exgfx_result.err.end());
+
+        Result result{ 0, std::move(out), std::move(err), true, std::nullopt,
+                       gfx_result.truncated || exgfx_result.truncated };
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    std::vector<ExGFXSlot> LunarMagicWrapper::listExGFXSlots(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).usedExGFXSlots();
+    }
+
+    GraphicsUsage LunarMagicWrapper::graphicsUsage(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).graphicsUsage();
+    }
+
+    MappingMode LunarMagicWrapper::mappingMode(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).mappingMode();
+    }
+
+    FreeSpace LunarMagicWrapper::freeSpace(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).freeSpace();
+    }
+
+    ROMInfo LunarMagicWrapper::romInfo(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).info();
+    }
+
+    bool LunarMagicWrapper::isLevelEmpty(const fs::path &rom_path, const fs::path &clean_rom_path,
+                                         uint16_t level_number) {
+        throwIfInvalidLevels({ level_number });
+
+        return ROM::fromFile(rom_path).levelPointers(level_number)
+            == ROM::fromFile(clean_rom_path).levelPointers(level_number);
+    }
+
+    uint8_t LunarMagicWrapper::levelScreenCount(const fs::path &rom_path, uint16_t level_number) {
+        throwIfInvalidLevels({ level_number });
+
+        return ROM::fromFile(rom_path).levelScreenCount(level_number);
+    }
+
+    std::vector<SecondaryExit> LunarMagicWrapper::listSecondaryExits(const fs::path &rom_path) {
+        return ROM::fromFile(rom_path).secondaryExits();
+    }
+
+    Result LunarMagicWrapper::insertExGFX(const fs::path &rom_path, const fs::path &exgfx_path, uint16_t slot) {
+        if (slot < 0x80 || slot > 0xFFF) {
+            throw std::invalid_argument(fmt::format("ExGFX slot {:X} is outside of the valid range 80-FFF", slot));
+        }
+
+        throwIfMissingInput(exgfx_path, "ExGFX file");
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        // -ImportExGFX takes the whole ExGraphics folder next to the ROM, a staged copy of the ROM with only this
+        // file next to it keeps both the other files and the project's ExGraphics folder out of it
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+        const auto staged_rom{ temp_directory.path() / rom_path.filename() };
+        fs::create_directories(exGraphicsDirectory(staged_rom));
+        fs::copy_file(exgfx_path, exGraphicsDirectory(staged_rom) / fmt::format("ExGFX{:X}.bin", slot));
+        fs::copy_file(rom_path, staged_rom);
+
+        auto result{ call(Operation::IMPORT_EXGFX, fmt::format(R"("{}")", inputPath(staged_rom))) };
+
+        throwIfKnownFailure(rom_path, result);
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to insert ExGFX '{}' into slot {:X} of '{}' using '{}'",
+                exgfx_path.string(), slot, rom_path.string(), lunar_magic_path.string()
+            ), result);
+        }
+
+        fs::copy_file(staged_rom, rom_path, fs::copy_options::overwrite_existing);
+
+        afterWrite(rom_path, result);
+
+        return result;
+    }
+
+    std::vector<fs::path> LunarMagicWrapper::importExGFXFromDirectories(const fs::path &rom_path,
+                                                                        const std::vector<fs::path> &directories) {
+        std::map<std::string, fs::path> files{};
+        for (const auto& directory : directories) {
+            for (const auto& entry : fs::directory_iterator(directory)) {
+                if (!entry.is_regular_file()) {
+                    continue;
+                }
+
+                const auto key{ lower(entry.path().filename().string()) };
+
+                if (const auto [existing, inserted]{ files.emplace(key, entry.path()) }; !inserted) {
+                    throw InvalidPathException(fmt::format(
+                        "ExGFX file '{}' is in both '{}' and '{}'",
+                        entry.path().filename().string(), existing->second.parent_path().string(),
+                        directory.string()
+                    ), entry.path());
+                }
+            }
+        }
+
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        const auto staged_rom{ temp_directory.path() / rom_path.filename() };
+        fs::create_directories(exGraphicsDirectory(staged_rom));
+        for (const auto& [key, source] : files) {
+            fs::copy_file(source, exGraphicsDirectory(staged_rom) / source.filename());
+        }
+        fs::copy_file(rom_path, staged_rom);
+
+        // the actual ROM has been backed up already, a backup of the staged copy would be redundant
+        auto staged_wrapper{ *this };
+        staged_wrapper.backup_before_write = false;
+        staged_wrapper.importExGFX(staged_rom);
+
+        fs::copy_file(staged_rom, rom_path, fs::copy_options::overwrite_existing);
+
+        std::vector<fs::path> imported{};
+        for (const auto& [key, source] : files) {
+            imported.push_back(source);
+        }
+
+        return imported;
+    }
+
+    void LunarMagicWrapper::throwIfInvalidLevels(const std::vector<uint16_t> &levels) {
+        std::vector<uint16_t> invalid{};
+        std::copy_if(levels.begin(), levels.end(), std::back_inserter(invalid),
+                     [](uint16_t level) { return level > MAX_LEVEL; });
+
+        if (invalid.empty()) {
+            return;
+        }
+
+        std::string listed{};
+        for (const auto level : invalid) {
+            listed += fmt::format("{}{:03X}", listed.empty() ? "" : ", ", level);
+        }
+
+        const auto message{ fmt::format(
+            "{} {} {} outside of the valid range 000-{:03X}",
+            invalid.size() == 1 ? "Level" : "Levels", listed, invalid.size() == 1 ? "is" : "are", MAX_LEVEL
+        ) };
+        throw InvalidLevelException(message, std::move(invalid));
+    }
+
+    Result LunarMagicWrapper::exportLevel(const fs::path &rom_path, const fs::path &mwl_path, uint16_t level_number) {
+        throwIfInvalidLevels({ level_number });
+
+        auto result{ call(Operation::EXPORT_LEVEL, fmt::format(R"("{}" "{}" {:X})", inputPath(rom_path), outputPath(mwl_path), level_number)) };
+
+        if (!result.succeeded) {
+            throw LunarMagicWrapperException(fmt::format(
+                "Failed to export level {:03X} from '{}' to '{}' using '{}'",
+                level_number,
+                rom_path.string(),
+                mwl_path.string(),
+                lunar_magic_path.string()
+            ), result);
+        }
+
+        checkExportSize(mwl_path, result);
+
+        return result;
+    }
+
+    LevelExportWithSummary LunarMagicWrapper::exportLevelWithSummary(const fs::path &rom_path,
+                                                                     const fs::path &mwl_path, uint16_t level_number,
+                                                                     SummaryFormat format) {
+        auto result{ exportLevel(rom_path, mwl_path, level_number) };
+        auto summary{ MWL::read(mwl_path).summary() };
+
+        auto summary_path{ mwl_path };
+        summary_path.replace_extension(format == SummaryFormat::JSON ? ".json" : ".txt");
+
+        std::ofstream summary_file(summary_path);
+        if (!summary_file) {
+            throw InvalidPathException(fmt::format(
+                "Failed to open '{}' to write the summary of level {:03X}", summary_path.string(), level_number
+            ), summary_path);
+        }
+        summary_file << (format == SummaryFormat::JSON ? summary.toJSON() : summary.toString());
 
-            case ROMSize::_6MB_SA1:
-                return "6MB_SA1";
+        return { std::move(result), mwl_path, std::move(summary_path), std::move(summary) };
+    }
 
-            case ROMSize::_8MB_SA1:
-                return "8MB_SA1";
+    Result LunarMagicWrapper::importLevel(const fs::path &rom_path, const fs::path &mwl_path,
+                                       std::optional<uint16_t> level_number) {
+        if (level_number) {
+            throwIfInvalidLevels({ *level_number });
         }
+        throwIfMissingInput(mwl_path, "MWL file", ".mwl");
 
-        throw std::runtime_error("Unknown ROM size passed");
+        if (level_number) {
+            const auto rom_lock{ beforeWrite(rom_path) };
+
+            auto result{ call(Operation::IMPORT_LEVEL, fmt::format(R"("{}" "{}" {:X})", inputPath(rom_path),
+                                             inputPath(mwl_path), *level_number)) };
+
+            throwIfKnownFailure(rom_path, result);
+
+            if (!result.succeeded) {
+                throw LunarMagicWrapperException(fmt::format(
+                    "Failed to import level {:03X} from '{}' into '{}' using '{}'",
+                    *level_number, mwl_path.string(), rom_path.string(),
+                    lunar_magic_path.string()
+                ), result);
+            }
+
+            afterWrite(rom_path, result);
+
+            return result;
+        } else {
+            const auto rom_lock{ beforeWrite(rom_path) };
+
+            auto result{ call(Operation::IMPORT_LEVEL, fmt::format(R"("{}" "{}")", inputPath(rom_path), inputPath(mwl_path))) };
+
+            throwIfKnownFailure(rom_path, result);
+
+            if (!result.succeeded) {
+                throw LunarMagicWrapperException(fmt::format(
+                    "Failed to import level from '{}' into '{}' using '{}'",
+                    mwl_path.string(), rom_path.string(),
+                    lunar_magic_path.string()
+                ), result);
+            }
+
+            afterWrite(rom_path, result);
+
+            return result;
+        }
     }
 
-    std::string LunarMagicWrapper::compressionOptionToString(CompressionOption compression_option) {
-        switch(compression_option) {
-            case CompressionOption::LC_LZ2_Orig:
-                return "LC_LZ2_Orig";
+    ImportLevelOutcome LunarMagicWrapper::importLevelWithOutcome(const fs::path &rom_path, const fs::path &mwl_path,
+                                                                 const fs::path &clean_rom_path,
+                                                                 std::optional<uint16_t> level_number) {
+        throwIfMissingInput(mwl_path, "MWL file", ".mwl");
+        throwIfMissingInput(clean_rom_path, "Clean ROM");
 
-            case CompressionOption::LC_LZ2_Speed:
-                return "LC_LZ2_Speed";
+        const auto level{ level_number.value_or(MWL::read(mwl_path).levelNumber()) };
+        const auto was_empty{ isLevelEmpty(rom_path, clean_rom_path, level) };
 
-            case CompressionOption::LC_LZ3:
-                return "LC_LZ3";
+        auto result{ importLevel(rom_path, mwl_path, level_number) };
+
+        auto status{ LevelImportStatus::UNKNOWN };
+        if (!isLevelEmpty(rom_path, clean_rom_path, level)) {
+            status = was_empty ? LevelImportStatus::CREATED : LevelImportStatus::OVERWRITTEN;
         }
 
-        throw std::runtime_error("Unknown compression option passed");
+        return { std::move(result), status, level };
     }
 
-    Result LunarMagicWrapper::exportGFX(const fs::path &rom_path) {
-        auto result{ call(fmt::format(R"(-ExportGFX "{}")", rom_path.string())) };
+    ImportFeasibility LunarMagicWrapper::checkLevelImport(const fs::path &rom_path, const fs::path &mwl_path) {
+        throwIfMissingInput(mwl_path, "MWL file", ".mwl");
+
+        const auto estimated_bytes{ MWL::read(mwl_path).levelDataSize() };
+        const auto free_bytes{ ROM::fromFile(rom_path).freeBytes() };
+
+        return { estimated_bytes <= free_bytes, estimated_bytes, free_bytes };
+    }
+
+    std::string LunarMagicWrapper::coordinatesToString(std::pair<size_t, size_t> coordinates) const {
+        return fmt::format("{:X}{}{:X}", coordinates.first, coordinate_separator, coordinates.second);
+    }
+
+    Result LunarMagicWrapper::importMap16(const fs::path &rom_path, const fs::path &map16_path, uint16_t level_number,
+                                       std::optional<std::pair<size_t, size_t>> coordinates) {
+        // TODO check why this has a level number parameter (it's not documented ...)
+        throwIfInvalidLevels({ level_number });
+        throwIfInvalidMap16(map16_path);
+
+        if (coordinates) {
+            // TODO check if this is actually how coordinates are handled (it's not documented ...)
+            const auto rom_lock{ beforeWrite(rom_path) };
+
+            auto result{ call(Operation::IMPORT_MAP16, fmt::format(R"("{}" "{}" {:X} {})", inputPath(rom_path), inputPath(map16_path),
+                                    level_number, coordinatesToString(*coordinates))) };
+
+            throwIfKnownFailure(rom_path, result);
+
+            if (!result.succeeded) {
+                throw LunarMagicWrapperException(fmt::format(
+                        "Failed to import map16 file '{}' into level {:03X} of '{}' at coordinates X={:X}, Y={:X} "
+                        "using '{}'",
+                        map16_path.string(), level_number, rom_path.string(), coordinates->first, coordinates->second,
+                        lunar_magic_path.string()
+                ), result);
+            }
+
+            afterWrite(rom_path, result);
+
+            return result;
+        } else {
+            const auto rom_lock{ beforeWrite(rom_path) };
+
+            auto result{ call(Operation::IMPORT_MAP16, fmt::format(R"("{}" "{}" {:X})", inputPath(rom_path), inputPath(map16_path),
+                                    level_number)) };
+
+            throwIfKnownFailure(rom_path, result);
+
+            if (!result.succeeded) {
+                throw LunarMagicWrapperException(fmt::format(
+                        "Failed to import map16 file '{}' into level {:03X} of '{}' using '{}'",
+                        map16_path.string(), level_number, rom_path.string(), lunar_magic_path.string()
+                ), result);
+            }
+
+            afterWrite(rom_path, result);
+
+            return result;
+        }
+    }
+
+    Result LunarMagicWrapper::importCustomPalette(const fs::path &rom_path, const fs::path &palette_path,
+                                                uint16_t level_number) {
+        throwIfInvalidLevels({ level_number });
+        throwIfMissingInput(palette_path, "Palette file");
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_CUSTOM_PALETTE, fmt::format(R"("{}" "{}" {:X})", inputPath(rom_path), inputPath(palette_path),
+                                level_number)) };
+
+        throwIfKnownFailure(rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to export GFX from '{}' using '{}'",
-                rom_path.string(),
-                lunar_magic_path.string()),
-                result
-            );
+                "Failed to import custom palette '{}' into level {:03X} of '{}' using '{}'",
+                palette_path.string(), level_number, rom_path.string(), lunar_magic_path.string()
+            ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
 
-    Result LunarMagicWrapper::exportExGFX(const fs::path &rom_path) {
-        auto result{ call(fmt::format(R"(-ExportExGFX "{}")", rom_path.string())) };
+    std::vector<PaletteImportOutcome> LunarMagicWrapper::importCustomPalettesFromDirectory(
+            const fs::path &rom_path, const fs::path &directory_path) {
+        std::map<uint16_t, fs::path> palettes{};
+        for (const auto& entry : fs::directory_iterator(directory_path)) {
+            if (!entry.is_regular_file() || !hasExtension(entry.path(), ".pal")) {
+                continue;
+            }
+
+            const auto stem{ entry.path().stem().string() };
+            if (stem.empty() || stem.size() > 3
+                || !std::all_of(stem.begin(), stem.end(), [](unsigned char c) { return std::isxdigit(c); })) {
+                throw InvalidPathException(fmt::format(
+                    "Palette file name '{}' in '{}' isn't a level number, expected e.g. '105.pal'",
+                    entry.path().filename().string(), directory_path.string()
+                ), entry.path());
+            }
+
+            const auto level_number{ static_cast<uint16_t>(std::stoul(stem, nullptr, 16)) };
+            if (const auto [existing, inserted]{ palettes.emplace(level_number, entry.path()) }; !inserted) {
+                throw InvalidPathException(fmt::format(
+                    "Palette files '{}' and '{}' in '{}' are both for level {:03X}",
+                    existing->second.filename().string(), entry.path().filename().string(),
+                    directory_path.string(), level_number
+                ), entry.path());
+            }
+        }
+
+        std::vector<uint16_t> level_numbers{};
+        for (const auto& [level_number, palette_path] : palettes) {
+            level_numbers.push_back(level_number);
+        }
+        throwIfInvalidLevels(level_numbers);
+
+        std::vector<PaletteImportOutcome> outcomes{};
+        for (const auto& [level_number, palette_path] : palettes) {
+            try {
+                outcomes.push_back({ level_number, palette_path,
+                                     importCustomPalette(rom_path, palette_path, level_number), nullptr });
+            } catch (...) {
+                outcomes.push_back({ level_number, palette_path, std::nullopt, std::current_exception() });
+            }
+        }
+
+        return outcomes;
+    }
+
+    std::vector<std::string> LunarMagicWrapper::collapseRepeatedLines(const std::vector<std::string> &lines) {
+        const auto without_timestamp{ [](const std::string& line) {
+            auto split{ splitTimestamp(line) };
+            return split ? std::move(split->second) : line;
+        } };
+
+        std::vector<std::string> collapsed{};
+        for (size_t i{}; i < lines.size();) {
+            const auto text{ without_timestamp(lines[i]) };
+            auto end{ i + 1 };
+            while (end < lines.size() && without_timestamp(lines[end]) == text) {
+                ++end;
+            }
+
+            collapsed.push_back(end - i == 1 ? lines[i] : fmt::format("{} (x{})", lines[i], end - i));
+            i = end;
+        }
+
+        return collapsed;
+    }
+
+    std::optional<size_t> LunarMagicWrapper::expectedExportSize(const fs::path &path) {
+        std::ifstream file(path, std::ios::binary);
+        const std::vector<uint8_t> bytes{ std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>() };
+
+        const auto starts_with{ [&](std::string_view signature) {
+            return bytes.size() >= signature.size() && std::equal(signature.begin(), signature.end(), bytes.begin());
+        } };
+
+        if (starts_with(Map16File::SIGNATURE)) {
+            try {
+                return Map16File::sectionTable(bytes).end();
+            } catch (const Map16Exception&) {
+                return std::nullopt;
+            }
+        }
+
+        if (starts_with("LM") && bytes.size() >= 8) {
+            return sectionTableEnd(bytes, readLong(bytes, 4), MWL::SECTION_COUNT);
+        }
+
+        return std::nullopt;
+    }
+
+    void LunarMagicWrapper::checkExportSize(const fs::path &path, const Result &result) const {
+        if (trailing_data_policy == TrailingDataPolicy::IGNORE) {
+            return;
+        }
+
+        // shared palettes are plain color data, nothing in them says how long they are
+        const auto expected_size{ result.operation == Operation::EXPORT_SHARED_PALETTE
+            ? std::make_optional(SHARED_PALETTE_SIZE) : expectedExportSize(path) };
+        const auto actual_size{ fs::file_size(path) };
+        if (!expected_size || actual_size == *expected_size) {
+            return;
+        }
+
+        if (trailing_data_policy == TrailingDataPolicy::TRUNCATE && actual_size > *expected_size) {
+            fs::resize_file(path, *expected_size);
+            return;
+        }
+
+        throw MalformedExportException(fmt::format(
+            "Exported file '{}' is {} bytes, expected {}", path.string(), actual_size, *expected_size
+        ), result, path, *expected_size, actual_size);
+    }
+
+    Result LunarMagicWrapper::exportSharedPalette(const fs::path &rom_path, const fs::path &shared_palette_path) {
+        auto result{ call(Operation::EXPORT_SHARED_PALETTE, fmt::format(R"("{}" {})", inputPath(rom_path), outputPath(shared_palette_path))) };
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to export ExGFX from '{}' using '{}'",
-                rom_path.string(),
-                lunar_magic_path.string()
+                "Failed to export shared palette to '{}' from '{}' using '{}'",
+                shared_palette_path.string(), rom_path.string(), lunar_magic_path.string()
             ), result);
         }
 
+        checkExportSize(shared_palette_path, result);
+
         return result;
     }
 
-    Result LunarMagicWrapper::importGFX(const fs::path &rom_path) {
-        auto result{ call(fmt::format(R"(-ImportExGFX "{}")", rom_path.string())) };
+    Result LunarMagicWrapper::importSharedPalette(const fs::path &rom_path, const fs::path &shared_palette_path) {
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_SHARED_PALETTE, fmt::format(R"("{}" {})", inputPath(rom_path), inputPath(shared_palette_path))) };
+
+        throwIfKnownFailure(rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to import GFX into '{}' using '{}'",
-                rom_path.string(),
-                lunar_magic_path.string()
+                "Failed to import shared palette '{}' into '{}' using '{}'",
+                shared_palette_path.string(), rom_path.string(), lunar_magic_path.string()
             ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
 
-    Result LunarMagicWrapper::importExGFX(const fs::path &rom_path) {
-        auto result{ call(fmt::format(R"(-ImportExGFX "{}")", rom_path.string())) };
+    Result LunarMagicWrapper::exportAllMap16(const fs::path &rom_path, const fs::path &all_map16_path) {
+        auto result{ call(Operation::EXPORT_ALL_MAP16, fmt::format(R"("{}" {})", inputPath(rom_path), outputPath(all_map16_path))) };
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to import ExGFX into '{}' using '{}'",
-                rom_path.string(),
-                lunar_magic_path.string()
+                "Failed to export all map16 file to '{}' from '{}' using '{}'",
+                all_map16_path.string(), rom_path.string(), lunar_magic_path.string()
             ), result);
         }
 
+        checkExportSize(all_map16_path, result);
+
         return result;
     }
 
-    Result LunarMagicWrapper::importAllGraphics(const fs::path &rom_path) {
-        auto result{ call(fmt::format(R"(-ImportAllGraphics "{}")", rom_path.string())) };
+    Result LunarMagicWrapper::importAllMap16(const fs::path &rom_path, const fs::path &all_map16_path) {
+        throwIfInvalidMap16(all_map16_path);
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_ALL_MAP16, fmt::format(R"("{}" {})", inputPath(rom_path), inputPath(all_map16_path))) };
+
+        throwIfKnownFailure(rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to import all graphics into '{}' using '{}'",
-                rom_path.string(),
-                lunar_magic_path.string()
+                "Failed to import all map16 file '{}' into '{}' using '{}'",
+                all_map16_path.string(), rom_path.string(), lunar_magic_path.string()
             ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
 
-    Result LunarMagicWrapper::exportLevel(const fs::path &rom_path, const fs::path &mwl_path, uint16_t level_number) {
-        auto result{ call(fmt::format(R"(-ExportLevel "{}" "{}" {:X})", rom_path.string(), mwl_path.string(), level_number)) };
+    Result LunarMagicWrapper::exportMultipleLevels(const fs::path &rom_path, const fs::path &directory_path,
+                                                 const std::string &file_name_start,
+                                                 std::optional<LevelExportOption> options) {
+        auto full_path{ directory_path };
+        full_path /= file_name_start;
+
+        if (!options) {
+            options = default_level_export_options;
+        }
+
+        auto arguments{ fmt::format(R"("{}" "{}")", inputPath(rom_path), outputPath(full_path)) };
+        std::string options_description{};
+        if (options) {
+            const auto options_as_int{ static_cast<size_t>(*options) };
+            arguments += fmt::format(" {:X}", options_as_int);
+            options_description = fmt::format(" with options '{:X}'", options_as_int);
+        }
+
+        // exports left in the directory by earlier runs aren't this call's to check
+        std::map<fs::path, fs::file_time_type> earlier_exports{};
+        if (fs::is_directory(directory_path)) {
+            for (const auto& mwl_path : collectExportedLevels(directory_path, file_name_start)) {
+                earlier_exports.emplace(mwl_path, fs::last_write_time(mwl_path));
+            }
+        }
 
+        auto result{ call(Operation::EXPORT_MULTIPLE_LEVELS, arguments) };
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
-                "Failed to export level {:03X} from '{}' to '{}' using '{}'",
-                level_number,
-                rom_path.string(),
-                mwl_path.string(),
+                "Failed to export multiple levels with prefix '{}' to '{}' from '{}'{} using '{}'",
+                file_name_start, directory_path.string(), rom_path.string(), options_description,
                 lunar_magic_path.string()
             ), result);
         }
 
-        return result;
+        for (const auto& mwl_path : collectExportedLevels(directory_path, file_name_start)) {
+            const auto earlier{ earlier_exports.find(mwl_path) };
+            if (earlier == earlier_exports.end() || earlier->second != fs::last_write_time(mwl_path)) {
+                checkExportSize(mwl_path, result);
+            }
+        }
+
+        return result;
+    }
+
+    std::vector<std::pair<uint16_t, fs::path>> LunarMagicWrapper::exportedLevels(const fs::path &directory_path,
+                                                                                 const std::string &file_name_start) {
+        std::vector<std::pair<uint16_t, fs::path>> levels{};
+
+        const auto lower_start{ lower(file_name_start) };
+        for (const auto& entry : fs::directory_iterator(directory_path)) {
+            const auto stem{ entry.path().stem().string() };
+            if (!hasExtension(entry.path(), ".mwl") || !lower(stem).starts_with(lower_start)) {
+                continue;
+            }
+
+            const auto number_part{ stem.substr(file_name_start.size()) };
+            if (number_part.empty() || number_part.size() > 3
+                || !std::all_of(number_part.begin(), number_part.end(),
+                                [](unsigned char c) { return std::isxdigit(c); })) {
+                continue;
+            }
+
+            levels.emplace_back(static_cast<uint16_t>(std::stoul(number_part, nullptr, 16)), entry.path());
+        }
+
+        std::sort(levels.begin(), levels.end());
+        return levels;
+    }
+
+    std::vector<fs::path> LunarMagicWrapper::collectExportedLevels(const fs::path &directory_path,
+                                                                   const std::string &file_name_start) {
+        std::vector<fs::path> paths{};
+        for (auto& [level_number, path] : exportedLevels(directory_path, file_name_start)) {
+            paths.push_back(std::move(path));
+        }
+
+        return paths;
+    }
+
+    void LunarMagicWrapper::placeExportedLevels(const fs::path &directory_path, const std::string &file_name_start,
+                                                const LevelPathTemplate &path_for_level) {
+        std::map<fs::path, uint16_t> targets{};
+        std::vector<std::pair<fs::path, fs::path>> moves{};
+
+        for (const auto& [level_number, source] : exportedLevels(directory_path, file_name_start)) {
+            const auto target{ path_for_level(level_number) };
+
+            if (const auto [existing, inserted]{ targets.emplace(target, level_number) }; !inserted) {
+                throw std::invalid_argument(fmt::format(
+                    "Levels {:03X} and {:03X} would both be exported to '{}'",
+                    existing->second, level_number, target.string()
+                ));
+            }
+
+            moves.emplace_back(source, target);
+        }
+
+        for (const auto& [source, target] : moves) {
+            if (target.has_parent_path()) {
+                fs::create_directories(target.parent_path());
+            }
+            fs::copy_file(source, target, fs::copy_options::overwrite_existing);
+            fs::remove(source);
+        }
+    }
+
+    Result LunarMagicWrapper::exportMultipleLevels(const fs::path &rom_path, const LevelPathTemplate &path_for_level,
+                                                   std::optional<LevelExportOption> options) {
+        constexpr auto FILE_NAME_START{ "level " };
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        auto result{ exportMultipleLevels(rom_path, temp_directory.path(), FILE_NAME_START, options) };
+        placeExportedLevels(temp_directory.path(), FILE_NAME_START, path_for_level);
+
+        return result;
+    }
+
+    std::set<uint16_t> LunarMagicWrapper::usedSprites(const fs::path &levels_directory) {
+        std::set<uint16_t> sprites{};
+        for (const auto& entry : fs::directory_iterator(levels_directory)) {
+            if (!hasExtension(entry.path(), ".mwl")) {
+                continue;
+            }
+
+            const auto mwl{ MWL::read(entry.path()) };
+            for (const auto& sprite : mwl.getSprites()) {
+                sprites.insert(static_cast<uint16_t>((sprite.extra_bits << 8) | sprite.number));
+            }
+        }
+
+        return sprites;
+    }
+
+    std::set<uint16_t> LunarMagicWrapper::listUsedSprites(const fs::path &rom_path) {
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        // every level, whatever defaults are configured
+        exportMultipleLevels(rom_path, temp_directory.path(), "level ", LevelExportOption::NONE);
+        auto sprites{ usedSprites(temp_directory.path()) };
+
+        return sprites;
+    }
+
+    std::optional<uint16_t> LunarMagicWrapper::levelNumberFromFileName(const fs::path &mwl_path) {
+        const auto stem{ mwl_path.stem().string() };
+        if (stem.size() < 3) {
+            return std::nullopt;
+        }
+
+        const auto digits{ stem.substr(stem.size() - 3) };
+        if (!std::all_of(digits.begin(), digits.end(), [](unsigned char c) { return std::isxdigit(c); })) {
+            return std::nullopt;
+        }
+
+        return static_cast<uint16_t>(std::stoul(digits, nullptr, 16));
+    }
+
+    std::vector<LevelFileNameMismatch> LunarMagicWrapper::checkLevelFileNames(const fs::path &directory_path) {
+        std::vector<fs::path> mwl_paths{};
+        for (const auto& entry : fs::directory_iterator(directory_path)) {
+            if (entry.is_regular_file() && hasExtension(entry.path(), ".mwl")) {
+                mwl_paths.push_back(entry.path());
+            }
+        }
+        std::sort(mwl_paths.begin(), mwl_paths.end());
+
+        std::vector<LevelFileNameMismatch> mismatches{};
+        for (const auto& mwl_path : mwl_paths) {
+            uint16_t embedded_level{};
+            try {
+                embedded_level = MWL::read(mwl_path).levelNumber();
+            } catch (const MWLException&) {
+                continue;
+            }
+
+            const auto file_name_level{ levelNumberFromFileName(mwl_path) };
+            if (file_name_level != embedded_level) {
+                mismatches.push_back({ mwl_path, file_name_level, embedded_level });
+            }
+        }
+
+        return mismatches;
     }
 
-    Result LunarMagicWrapper::importLevel(const fs::path &rom_path, const fs::path &mwl_path,
-                                       std::optional<uint16_t> level_number) {
-        if (level_number) {
-            auto result{ call(fmt::format(R"(-ImportLevel "{}" "{}" {:X})", rom_path.string(),
-                                             mwl_path.string(), *level_number)) };
+    Result LunarMagicWrapper::importMultipleLevels(const fs::path &rom_path, const fs::path &directory_path,
+                                                 std::optional<LevelImportOption> options) {
+        if (!options) {
+            options = default_level_import_options;
+        }
 
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                    "Failed to import level {:03X} from '{}' into '{}' using '{}'",
-                    *level_number, mwl_path.string(), rom_path.string(),
-                    lunar_magic_path.string()
-                ), result);
+        if (check_level_file_names) {
+            if (auto mismatches{ checkLevelFileNames(directory_path) }; !mismatches.empty()) {
+                const auto& first{ mismatches.front() };
+                throw LevelFileNameMismatchException(fmt::format(
+                    "{} MWL file(s) in '{}' are named after a different level than they contain, e.g. '{}' contains level {:03X}",
+                    mismatches.size(), directory_path.string(), first.path.filename().string(), first.embedded_level
+                ), std::move(mismatches));
             }
+        }
 
-            return result;
-        } else {
-            auto result{ call(fmt::format(R"(-ImportLevel "{}" "{}")", rom_path.string(), mwl_path.string())) };
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                    "Failed to import level from '{}' into '{}' using '{}'",
-                    mwl_path.string(), rom_path.string(),
-                    lunar_magic_path.string()
-                ), result);
-            }
+        if (options) {
+            const auto options_as_int{ static_cast<size_t>(*options) };
 
-            return result;
-        }
-    }
+            const auto rom_lock{ beforeWrite(rom_path) };
 
-    Result LunarMagicWrapper::importMap16(const fs::path &rom_path, const fs::path &map16_path, uint16_t level_number,
-                                       std::optional<std::pair<size_t, size_t>> coordinates) {
-        // TODO check why this has a level number parameter (it's not documented ...)
+            auto result{ call(Operation::IMPORT_MULTIPLE_LEVELS, fmt::format(R"("{}" "{}" {:X})",
+                                             inputPath(rom_path), inputPath(directory_path), options_as_int)) };
 
-        if (coordinates) {
-            // TODO check if this is actually how coordinates are handled (it's not documented ...)
-            auto result{ call(fmt::format(R"(-ImportMap16 "{}" "{}" {:X} {:X},{:X})", rom_path.string(), map16_path.string(),
-                                    level_number, coordinates->first, coordinates->second)) };
+            throwIfKnownFailure(rom_path, result);
 
             if (!result.succeeded) {
                 throw LunarMagicWrapperException(fmt::format(
-                        "Failed to import map16 file '{}' into level {:03X} of '{}' at coordinates X={:X}, Y={:X} "
-                        "using '{}'",
-                        map16_path.string(), level_number, rom_path.string(), coordinates->first, coordinates->second,
-                        lunar_magic_path.string()
+                    "Failed to import multiple levels from '{}' into '{}' with options '{:X}' using '{}'",
+                    directory_path.string(), rom_path.string(), options_as_int, lunar_magic_path.string()
                 ), result);
             }
 
+            afterWrite(rom_path, result);
+
             return result;
         } else {
-            auto result{ call(fmt::format(R"(-ImportMap16 "{}" "{}" {:X})", rom_path.string(), map16_path.string(),
-                                    level_number)) };
+            const auto rom_lock{ beforeWrite(rom_path) };
+
+            auto result{ call(Operation::IMPORT_MULTIPLE_LEVELS, fmt::format(R"("{}" "{}")",
+                                             inputPath(rom_path), inputPath(directory_path))) };
+
+            throwIfKnownFailure(rom_path, result);
 
             if (!result.succeeded) {
                 throw LunarMagicWrapperException(fmt::format(
-                        "Failed to import map16 file '{}' into level {:03X} of '{}' using '{}'",
-                        map16_path.string(), level_number, rom_path.string(), lunar_magic_path.string()
+                    "Failed to import multiple levels from '{}' into '{}' using '{}'",
+                    directory_path.string(), rom_path.string(), lunar_magic_path.string()
                 ), result);
             }
 
+            afterWrite(rom_path, result);
+
             return result;
         }
     }
 
-    Result LunarMagicWrapper::importCustomPalette(const fs::path &rom_path, const fs::path &palette_path,
-                                                uint16_t level_number) {
-        auto result{ call(fmt::format(R"(-ImportCustomPalette "{}" "{}" {:X})", rom_path.string(), palette_path.string(),
-                                level_number)) };
+    Result LunarMagicWrapper::importMultipleLevels(const fs::path &rom_path, const fs::path &directory_path,
+                                                   SecondaryExitPolicy secondary_exits, LevelImportOption options) {
+        return importMultipleLevels(rom_path, directory_path, withSecondaryExitPolicy(options, secondary_exits));
+    }
 
-        if (!result.succeeded) {
-            throw LunarMagicWrapperException(fmt::format(
-                "Failed to import custom palette '{}' into level {:03X} of '{}' using '{}'",
-                palette_path.string(), level_number, rom_path.string(), lunar_magic_path.string()
-            ), result);
+    std::vector<uint16_t> LunarMagicWrapper::resetLevels(const fs::path &rom_path, const fs::path &clean_rom_path,
+                                                         uint16_t first_level, uint16_t last_level) {
+        throwIfInvalidLevels({ first_level, last_level });
+        if (first_level > last_level) {
+            throw std::invalid_argument(fmt::format(
+                "Level range {:03X}-{:03X} ends before it starts", first_level, last_level
+            ));
         }
 
-        return result;
-    }
+        const auto rom_size{ ROM::fromFile(rom_path).size() };
+        const auto clean_rom_size{ ROM::fromFile(clean_rom_path).size() };
+        if (rom_size < clean_rom_size) {
+            throw std::invalid_argument(fmt::format(
+                "'{}' (0x{:X} bytes) is smaller than the clean ROM '{}' (0x{:X} bytes)",
+                rom_path.string(), rom_size, clean_rom_path.string(), clean_rom_size
+            ));
+        }
 
-    Result LunarMagicWrapper::exportSharedPalette(const fs::path &rom_path, const fs::path &shared_palette_path) {
-        auto result{ call(fmt::format(R"(-ExportSharedPalette "{}" {})", rom_path.string(), shared_palette_path.string())) };
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+        const auto reset_directory{ temp_directory.path() / "reset" };
 
-        if (!result.succeeded) {
-            throw LunarMagicWrapperException(fmt::format(
-                "Failed to export shared palette to '{}' from '{}' using '{}'",
-                shared_palette_path.string(), rom_path.string(), lunar_magic_path.string()
-            ), result);
+        exportMultipleLevels(clean_rom_path, temp_directory.path(), "level ", LevelExportOption::NONE);
+
+        std::vector<uint16_t> reset{};
+        fs::create_directories(reset_directory);
+        for (const auto& [level_number, mwl_path] : exportedLevels(temp_directory.path(), "level ")) {
+            if (level_number >= first_level && level_number <= last_level) {
+                fs::rename(mwl_path, reset_directory / mwl_path.filename());
+                reset.push_back(level_number);
+            }
         }
 
-        return result;
-    }
+        // explicit options, a project-wide default like clearing secondary exits has no place in a reset
+        if (!reset.empty()) {
+            importMultipleLevels(rom_path, reset_directory, LevelImportOption::NONE);
+        }
 
-    Result LunarMagicWrapper::importSharedPalette(const fs::path &rom_path, const fs::path &shared_palette_path) {
-        auto result{ call(fmt::format(R"(-ImportSharedPalette "{}" {})", rom_path.string(), shared_palette_path.string())) };
+        return reset;
+    }
 
-        if (!result.succeeded) {
-            throw LunarMagicWrapperException(fmt::format(
-                "Failed to import shared palette '{}' into '{}' using '{}'",
-                shared_palette_path.string(), rom_path.string(), lunar_magic_path.string()
-            ), result);
+    std::vector<Result> LunarMagicWrapper::exportProjectArchive(const fs::path &rom_path, const fs::path &archive_path) {
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+        const auto project{ temp_directory.path() / "project" };
+        const auto project_rom{ temp_directory.path() / rom_path.filename() };
+
+        fs::create_directories(project);
+        fs::copy_file(rom_path, project_rom);
+
+        // GFX and ExGFX always end up next to the ROM
+        std::vector<Result> results{};
+        results.push_back(exportGFX(project_rom));
+        results.push_back(exportExGFX(project_rom));
+        for (const auto& folder : { graphicsDirectory(project_rom), exGraphicsDirectory(project_rom) }) {
+            if (fs::exists(folder)) {
+                fs::rename(folder, project / folder.filename());
+            }
         }
 
-        return result;
-    }
+        fs::create_directories(project / "Levels");
+        results.push_back(exportMultipleLevels(project_rom, project / "Levels", "level ", LevelExportOption::NONE));
+        results.push_back(exportAllMap16(project_rom, project / "all.map16"));
+        results.push_back(exportSharedPalette(project_rom, project / "shared.pal"));
 
-    Result LunarMagicWrapper::exportAllMap16(const fs::path &rom_path, const fs::path &all_map16_path) {
-        auto result{ call(fmt::format(R"(-ExportAllMap16 "{}" {})", rom_path.string(), all_map16_path.string())) };
+        const auto levels{ relativeFiles(project, project / "Levels") };
 
-        if (!result.succeeded) {
-            throw LunarMagicWrapperException(fmt::format(
-                "Failed to export all map16 file to '{}' from '{}' using '{}'",
-                all_map16_path.string(), rom_path.string(), lunar_magic_path.string()
-            ), result);
+        std::string lunar_magic_version{ "unknown" };
+        if (!levels.empty()) {
+            lunar_magic_version = MWL::read(project / levels.front()).creator();
         }
 
-        return result;
+        std::ofstream manifest(project / "manifest.json");
+        manifest << "{\n"
+                 << fmt::format("  \"rom\": {},\n", jsonString(rom_path.filename().string()))
+                 << fmt::format("  \"lunar_magic\": {},\n", jsonString(lunar_magic_version))
+                 << "  \"contents\": {\n"
+                 << fmt::format("    \"graphics\": {},\n", jsonArray(relativeFiles(project, project / "Graphics")))
+                 << fmt::format("    \"exgraphics\": {},\n", jsonArray(relativeFiles(project, project / "ExGraphics")))
+                 << fmt::format("    \"levels\": {},\n", jsonArray(levels))
+                 << "    \"all_map16\": \"all.map16\",\n"
+                 << "    \"shared_palette\": \"shared.pal\"\n"
+                 << "  }\n"
+                 << "}\n";
+        manifest.close();
+
+        createArchive(project, archive_path);
+
+        return results;
     }
 
-    Result LunarMagicWrapper::importAllMap16(const fs::path &rom_path, const fs::path &all_map16_path) {
-        auto result{ call(fmt::format(R"(-ImportAllMap16 "{}" {})", rom_path.string(), all_map16_path.string())) };
+    std::string BuildStep::toString() const {
+        if (level_number) {
+            return fmt::format("{} {} as level {:03X}", LunarMagicWrapper::operationToString(operation),
+                               source.generic_string(), *level_number);
+        }
 
-        if (!result.succeeded) {
-            throw LunarMagicWrapperException(fmt::format(
-                "Failed to import all map16 file '{}' into '{}' using '{}'",
-                all_map16_path.string(), rom_path.string(), lunar_magic_path.string()
-            ), result);
+        return fmt::format("{} {}", LunarMagicWrapper::operationToString(operation), source.generic_string());
+    }
+
+    std::string BuildPlan::toString() const {
+        std::string text{};
+        for (size_t i{}; i < steps.size(); ++i) {
+            text += fmt::format("{}. {}\n", i + 1, steps[i].toString());
         }
 
-        return result;
+        return text;
     }
 
-    Result LunarMagicWrapper::exportMultipleLevels(const fs::path &rom_path, const fs::path &directory_path,
-                                                 const std::string &file_name_start,
-                                                 std::optional<LevelExportOption> options) {
-        auto full_path{ directory_path };
-        full_path /= file_name_start;
+    BuildPlan LunarMagicWrapper::buildProjectPlan(const fs::path &project_directory) {
+        std::vector<BuildStep> steps{};
 
-        if (options) {
-            const auto options_as_int{ static_cast<size_t>(*options) };
-            auto result{ call(fmt::format(R"(-ExportMultLevels "{}" "{}" {:X})",
-                                             rom_path.string(), full_path.string(), options_as_int)) };
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                    "Failed to export multiple levels with prefix '{}' to '{}' from '{}' with options '{:X}' using '{}'",
-                    file_name_start, directory_path.string(), rom_path.string(), options_as_int,
-                    lunar_magic_path.string()
-                ), result);
-            }
+        // GFX and ExGFX first, levels may use them
+        if (fs::is_directory(project_directory / "Graphics")) {
+            steps.push_back({ Operation::IMPORT_GFX, "Graphics" });
+        }
+        if (fs::is_directory(project_directory / "ExGraphics")) {
+            steps.push_back({ Operation::IMPORT_EXGFX, "ExGraphics" });
+        }
 
-            return result;
-        } else {
-            auto result{ call(fmt::format(R"(-ExportMultLevels "{}" "{}")",
-                                             rom_path.string(), full_path.string())) };
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                        "Failed to export multiple levels with prefix '{}' to '{}' from '{}' using '{}'",
-                        file_name_start, directory_path.string(), rom_path.string(),
-                        lunar_magic_path.string()
-                ), result);
+        if (fs::is_directory(project_directory / "Levels")) {
+            for (const auto& [level_number, mwl_path] : exportedLevels(project_directory / "Levels", "level ")) {
+                steps.push_back({ Operation::IMPORT_LEVEL, fs::path("Levels") / mwl_path.filename(), level_number });
             }
+        }
 
-            return result;
+        if (fs::is_regular_file(project_directory / "all.map16")) {
+            steps.push_back({ Operation::IMPORT_ALL_MAP16, "all.map16" });
+        }
+        if (fs::is_regular_file(project_directory / "shared.pal")) {
+            steps.push_back({ Operation::IMPORT_SHARED_PALETTE, "shared.pal" });
         }
+
+        return { std::move(steps) };
     }
 
-    Result LunarMagicWrapper::importMultipleLevels(const fs::path &rom_path, const fs::path &directory_path,
-                                                 std::optional<LevelImportOption> options) {
-        if (options) {
-            const auto options_as_int{ static_cast<size_t>(*options) };
+    bool BuildReport::succeeded() const {
+        return std::all_of(steps.begin(), steps.end(), [](const StepResult& step) { return step.succeeded; });
+    }
 
-            auto result{ call(fmt::format(R"(-ImportMultLevels "{}" "{}" {:X})",
-                                             rom_path.string(), directory_path.string(), options_as_int)) };
+    std::vector<StepResult> BuildReport::failures() const {
+        std::vector<StepResult> failed{};
+        std::copy_if(steps.begin(), steps.end(), std::back_inserter(failed),
+                     [](const StepResult& step) { return !step.succeeded; });
+        return failed;
+    }
 
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                    "Failed to import multiple levels from '{}' into '{}' with options '{:X}' using '{}'",
-                    directory_path.string(), rom_path.string(), options_as_int, lunar_magic_path.string()
-                ), result);
+    std::vector<std::string> BuildReport::mergedLog(bool prefixed) const {
+        std::vector<std::string> lines{};
+        for (const auto& step : steps) {
+            const auto prefix{ !prefixed ? std::string{}
+                : fmt::format("[{}] ", step.operation ? LunarMagicWrapper::operationToString(*step.operation)
+                                                      : step.name) };
+
+            for (const auto* step_lines : { &step.out, &step.err }) {
+                for (const auto& line : *step_lines) {
+                    lines.push_back(prefix + line);
+                }
             }
+        }
 
-            return result;
-        } else {
-            auto result{ call(fmt::format(R"(-ImportMultLevels "{}" "{}")",
-                                             rom_path.string(), directory_path.string())) };
+        return lines;
+    }
 
-            if (!result.succeeded) {
-                throw LunarMagicWrapperException(fmt::format(
-                    "Failed to import multiple levels from '{}' into '{}' using '{}'",
-                    directory_path.string(), rom_path.string(), lunar_magic_path.string()
-                ), result);
+    std::string BuildReport::toString() const {
+        const auto failed{ failures().size() };
+
+        std::string text{ failed == 0
+            ? fmt::format("Build succeeded, {} step(s)\n", steps.size())
+            : fmt::format("Build failed, {} of {} step(s) failed\n", failed, steps.size()) };
+
+        for (const auto& step : steps) {
+            text += fmt::format("  {:<6} {} ({}ms)\n", step.succeeded ? "ok" : "FAILED", step.name,
+                                std::chrono::duration_cast<std::chrono::milliseconds>(step.duration).count());
+            if (!step.succeeded) {
+                for (const auto& line : step.err) {
+                    text += fmt::format("    {}\n", line);
+                }
             }
+        }
 
-            return result;
+        return text;
+    }
+
+    BuildReport LunarMagicWrapper::buildProject(const fs::path &rom_path, const fs::path &project_directory) {
+        std::vector<StepResult> results{};
+
+        for (const auto& step : buildProjectPlan(project_directory).steps) {
+            const auto source{ project_directory / step.source };
+            const auto start{ std::chrono::steady_clock::now() };
+            const auto elapsed{ [&] { return std::chrono::steady_clock::now() - start; } };
+
+            try {
+                std::optional<Result> result{};
+                std::vector<std::string> exgfx_files{};
+
+                switch (step.operation) {
+                    case Operation::IMPORT_GFX:
+                        result.emplace(importGFXFromArchive(rom_path, project_directory));
+                        break;
+
+                    case Operation::IMPORT_EXGFX:
+                        for (const auto& exgfx_path : importExGFXFromDirectories(rom_path, { source })) {
+                            exgfx_files.push_back(exgfx_path.filename().string());
+                        }
+                        break;
+
+                    case Operation::IMPORT_LEVEL:
+                        result.emplace(importLevel(rom_path, source, step.level_number));
+                        break;
+
+                    case Operation::IMPORT_ALL_MAP16:
+                        result.emplace(importAllMap16(rom_path, source));
+                        break;
+
+                    case Operation::IMPORT_SHARED_PALETTE:
+                        result.emplace(importSharedPalette(rom_path, source));
+                        break;
+
+                    default:
+                        throw std::logic_error(fmt::format("'{}' isn't a build step", step.toString()));
+                }
+
+                results.push_back({ step.toString(), true, elapsed(), result ? result->out : exgfx_files,
+                                    result ? result->err : std::vector<std::string>{}, step.operation });
+            } catch (const LunarMagicWrapperException& e) {
+                auto err{ e.getResult().err };
+                err.emplace_back(e.what());
+                results.push_back({ step.toString(), false, elapsed(), e.getResult().out, std::move(err),
+                                    step.operation });
+            } catch (const std::exception& e) {
+                results.push_back({ step.toString(), false, elapsed(), {}, { e.what() }, step.operation });
+            }
         }
+
+        return { std::move(results) };
+    }
+
+    ROMOperations LunarMagicWrapper::onROM(fs::path rom_path) {
+        return { *this, std::move(rom_path) };
+    }
+
+    ComparisonResult LunarMagicWrapper::compareWith(LunarMagicWrapper &other,
+                                                    const std::function<Result(LunarMagicWrapper &)> &operation) {
+        const auto run{ [&](LunarMagicWrapper& wrapper) {
+            try {
+                return operation(wrapper);
+            } catch (const LunarMagicWrapperException& e) {
+                return e.getResult();
+            }
+        } };
+
+        auto result{ run(*this) };
+        auto other_result{ run(other) };
+        auto out_differences{ lineDifferences(result.out, other_result.out) };
+        auto err_differences{ lineDifferences(result.err, other_result.err) };
+
+        return { std::move(result), std::move(other_result), std::move(out_differences), std::move(err_differences) };
     }
 
     Result LunarMagicWrapper::expandROM(const fs::path &rom_path, ROMSize rom_size) {
-        auto result{ call(fmt::format(R"(-ExpandROM "{}" {})", rom_path.string(), romSizeToString(rom_size))) };
+        if (rom_size == ROMSize::_6MB_SA1 || rom_size == ROMSize::_8MB_SA1) {
+            const auto mapping{ ROM::fromFile(rom_path).mappingMode() };
+            if (mapping != MappingMode::SA1 && mapping != MappingMode::UNKNOWN) {
+                throw IncompatibleExpansionException(fmt::format(
+                    "'{}' is {}, it needs to be converted to SA-1 before it can be expanded to {}",
+                    rom_path.string(), mappingModeToString(mapping), romSizeToString(rom_size)
+                ), mapping, rom_size);
+            }
+        }
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::EXPAND_ROM, fmt::format(R"("{}" {})", inputPath(rom_path), romSizeToString(rom_size))) };
+
+        throwIfKnownFailure(rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -329,13 +2705,49 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
 
+    size_t LunarMagicWrapper::romSizeToBytes(ROMSize rom_size) {
+        switch (rom_size) {
+            case ROMSize::_2MB:
+                return 0x200000;
+
+            case ROMSize::_3MB:
+                return 0x300000;
+
+            case ROMSize::_4MB:
+                return 0x400000;
+
+            case ROMSize::_6MB_SA1:
+                return 0x600000;
+
+            case ROMSize::_8MB_SA1:
+                return 0x800000;
+        }
+
+        throw std::runtime_error("Unknown ROM size passed");
+    }
+
+    bool LunarMagicWrapper::ensureSize(const fs::path &rom_path, ROMSize rom_size) {
+        if (ROM::fromFile(rom_path).size() >= romSizeToBytes(rom_size)) {
+            return false;
+        }
+
+        expandROM(rom_path, rom_size);
+        return true;
+    }
+
     Result LunarMagicWrapper::changeCompression(const fs::path &rom_path, CompressionOption option) {
-        auto result{ call(fmt::format(R"(-ChangeCompression "{}" {})", rom_path.string(),
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::CHANGE_COMPRESSION, fmt::format(R"("{}" {})", inputPath(rom_path),
                                          compressionOptionToString(option))) };
 
+        throwIfKnownFailure(rom_path, result);
+
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
                 "Failed to change compression in '{}' to {} using '{}'",
@@ -344,13 +2756,65 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
 
+    CompressionReport LunarMagicWrapper::changeCompressionWithReport(const fs::path &rom_path,
+                                                                     CompressionOption option) {
+        const auto before_bytes{ ROM::fromFile(rom_path).usedBytes() };
+        auto result{ changeCompression(rom_path, option) };
+        const auto after_bytes{ ROM::fromFile(rom_path).usedBytes() };
+
+        return { std::move(result), option, before_bytes, after_bytes };
+    }
+
+    std::vector<CompressionBenchmark> LunarMagicWrapper::benchmarkCompression(
+            const fs::path &rom_path, const std::vector<CompressionOption> &formats) {
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+
+        // the copies are thrown away, backing them up would be pointless
+        auto staged_wrapper{ *this };
+        staged_wrapper.backup_before_write = false;
+
+        std::vector<CompressionBenchmark> benchmarks{};
+        for (size_t i{}; i < formats.size(); ++i) {
+            const auto staging{ temp_directory.path() / std::to_string(i) };
+            const auto staged_rom{ staging / rom_path.filename() };
+            fs::create_directories(staging);
+            fs::copy_file(rom_path, staged_rom);
+
+            const auto start{ std::chrono::steady_clock::now() };
+            auto report{ staged_wrapper.changeCompressionWithReport(staged_rom, formats[i]) };
+            const auto duration{ std::chrono::steady_clock::now() - start };
+
+            benchmarks.push_back({ std::move(report), duration });
+        }
+
+        std::vector<size_t> order(benchmarks.size());
+        std::iota(order.begin(), order.end(), 0);
+        std::stable_sort(order.begin(), order.end(), [&](size_t a, size_t b) {
+            return std::pair(benchmarks[a].report.after_bytes, benchmarks[a].duration)
+                < std::pair(benchmarks[b].report.after_bytes, benchmarks[b].duration);
+        });
+
+        std::vector<CompressionBenchmark> sorted{};
+        for (const auto i : order) {
+            sorted.push_back(benchmarks[i]);
+        }
+
+        return sorted;
+    }
+
     Result LunarMagicWrapper::transferGlobalExanimation(const fs::path &source_rom_path,
                                                       const fs::path &destination_rom_path) {
-        auto result{ call(fmt::format(R"(-TransferLevelGlobalExAnim "{}" "{}")",
-                                         destination_rom_path.string(), source_rom_path.string())) };
+        const auto rom_lock{ beforeWrite(destination_rom_path) };
+
+        auto result{ call(Operation::TRANSFER_GLOBAL_EXANIMATION, fmt::format(R"("{}" "{}")",
+                                         inputPath(destination_rom_path), inputPath(source_rom_path))) };
+
+        throwIfKnownFailure(destination_rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -359,12 +2823,30 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(destination_rom_path, result);
+
         return result;
     }
 
+    Result LunarMagicWrapper::exportGlobalExanimation(const fs::path &rom_path, const fs::path &exanimation_path,
+                                                      const fs::path &clean_rom_path) {
+        return exportThroughTransfer(&LunarMagicWrapper::transferGlobalExanimation, rom_path, exanimation_path,
+                                     clean_rom_path);
+    }
+
+    Result LunarMagicWrapper::importGlobalExanimation(const fs::path &rom_path, const fs::path &exanimation_path,
+                                                      const fs::path &clean_rom_path) {
+        return importThroughTransfer(&LunarMagicWrapper::transferGlobalExanimation, rom_path, exanimation_path,
+                                     clean_rom_path, "Global ExAnimation file");
+    }
+
     Result LunarMagicWrapper::transferOverworld(const fs::path &source_rom_path, const fs::path &destination_rom_path) {
-        auto result{ call(fmt::format(R"(-TransferOverworld "{}" "{}")",
-                                         destination_rom_path.string(), source_rom_path.string())) };
+        const auto rom_lock{ beforeWrite(destination_rom_path) };
+
+        auto result{ call(Operation::TRANSFER_OVERWORLD, fmt::format(R"("{}" "{}")",
+                                         inputPath(destination_rom_path), inputPath(source_rom_path))) };
+
+        throwIfKnownFailure(destination_rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -373,12 +2855,18 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(destination_rom_path, result);
+
         return result;
     }
 
     Result LunarMagicWrapper::transferTitleScreen(const fs::path &source_rom_path, const fs::path &destination_rom_path) {
-        auto result{ call(fmt::format(R"(-TransferTitleScreen "{}" "{}")",
-                                         destination_rom_path.string(), source_rom_path.string())) };
+        const auto rom_lock{ beforeWrite(destination_rom_path) };
+
+        auto result{ call(Operation::TRANSFER_TITLE_SCREEN, fmt::format(R"("{}" "{}")",
+                                         inputPath(destination_rom_path), inputPath(source_rom_path))) };
+
+        throwIfKnownFailure(destination_rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -387,12 +2875,18 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(destination_rom_path, result);
+
         return result;
     }
 
     Result LunarMagicWrapper::transferCredits(const fs::path &source_rom_path, const fs::path &destination_rom_path) {
-        auto result{ call(fmt::format(R"(-TransferCredits "{}" "{}")",
-                                         destination_rom_path.string(), source_rom_path.string())) };
+        const auto rom_lock{ beforeWrite(destination_rom_path) };
+
+        auto result{ call(Operation::TRANSFER_CREDITS, fmt::format(R"("{}" "{}")",
+                                         inputPath(destination_rom_path), inputPath(source_rom_path))) };
+
+        throwIfKnownFailure(destination_rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -401,12 +2895,100 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(destination_rom_path, result);
+
+        return result;
+    }
+
+    Result LunarMagicWrapper::exportCredits(const fs::path &rom_path, const fs::path &credits_path,
+                                            const fs::path &clean_rom_path) {
+        return exportThroughTransfer(&LunarMagicWrapper::transferCredits, rom_path, credits_path, clean_rom_path);
+    }
+
+    Result LunarMagicWrapper::importCredits(const fs::path &rom_path, const fs::path &credits_path,
+                                            const fs::path &clean_rom_path) {
+        return importThroughTransfer(&LunarMagicWrapper::transferCredits, rom_path, credits_path, clean_rom_path,
+                                     "Credits file");
+    }
+
+    Result LunarMagicWrapper::exportThroughTransfer(Transfer transfer, const fs::path &rom_path,
+                                                    const fs::path &changes_path, const fs::path &clean_rom_path) {
+        throwIfMissingInput(clean_rom_path, "Clean ROM");
+
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+        const auto baseline_rom{ transferredBaseline(transfer, clean_rom_path, temp_directory.path()) };
+
+        const auto staged_rom{ temp_directory.path() / "staged" / clean_rom_path.filename() };
+        fs::create_directories(staged_rom.parent_path());
+        fs::copy_file(clean_rom_path, staged_rom);
+        auto result{ (stagedWrapper().*transfer)(rom_path, staged_rom) };
+
+        ROM::writeChanges(outputPath(changes_path),
+                          ROM::fromFile(staged_rom).changesFrom(ROM::fromFile(baseline_rom)));
+
         return result;
     }
 
+    LunarMagicWrapper LunarMagicWrapper::stagedWrapper() const {
+        auto staged_wrapper{ *this };
+        staged_wrapper.backup_before_write = false;
+        staged_wrapper.auto_fix_checksum = false;
+        staged_wrapper.minimum_free_space = std::nullopt;
+        return staged_wrapper;
+    }
+
+    fs::path LunarMagicWrapper::transferredBaseline(Transfer transfer, const fs::path &clean_rom_path,
+                                                    const fs::path &directory_path) const {
+        const auto baseline_rom{ directory_path / "baseline" / clean_rom_path.filename() };
+        fs::create_directories(baseline_rom.parent_path());
+        fs::copy_file(clean_rom_path, baseline_rom);
+        (stagedWrapper().*transfer)(clean_rom_path, baseline_rom);
+
+        return baseline_rom;
+    }
+
+    Result LunarMagicWrapper::importThroughTransfer(Transfer transfer, const fs::path &rom_path,
+                                                    const fs::path &changes_path, const fs::path &clean_rom_path,
+                                                    const std::string &description) {
+        throwIfMissingInput(changes_path, description);
+        throwIfMissingInput(clean_rom_path, "Clean ROM");
+        const auto changes{ ROM::readChanges(changes_path) };
+
+        const TempDirectory temp_directory{ makeTempDirectory({ rom_path.parent_path() }) };
+        const auto staged_rom{ transferredBaseline(transfer, clean_rom_path, temp_directory.path()) };
+        ROM::applyChanges(staged_rom, changes);
+
+        return (this->*transfer)(staged_rom, rom_path);
+    }
+
+    fs::path LunarMagicWrapper::defaultTitleMovesPath(const fs::path &rom_path) {
+        return rom_path.parent_path() / (rom_path.stem().string() + ".zst");
+    }
+
+    void LunarMagicWrapper::throwIfInvalidTitleMoves(const fs::path &title_moves_path) {
+        throwIfMissingInput(title_moves_path, "Title moves file");
+
+        std::ifstream file(title_moves_path, std::ios::binary);
+        std::string signature(ZST_SIGNATURE.size(), '\0');
+        file.read(signature.data(), static_cast<std::streamsize>(signature.size()));
+
+        if (signature != ZST_SIGNATURE) {
+            throw InvalidTitleMovesException(fmt::format(
+                "Title moves file '{}' is not a ZSNES savestate", title_moves_path.string()
+            ), title_moves_path);
+        }
+
+        if (fs::file_size(title_moves_path) < ZST_MINIMUM_SIZE) {
+            throw InvalidTitleMovesException(fmt::format(
+                "Title moves file '{}' is only {} bytes, too small for a ZSNES savestate",
+                title_moves_path.string(), fs::file_size(title_moves_path)
+            ), title_moves_path);
+        }
+    }
+
     Result LunarMagicWrapper::exportTitleMoves(const fs::path &rom_path, const fs::path &title_moves_path) {
-        auto result{ call(fmt::format(R"(-ExportTitleMoves "{}" "{}")",
-                                         rom_path.string(), title_moves_path.string())) };
+        auto result{ call(Operation::EXPORT_TITLE_MOVES, fmt::format(R"("{}" "{}")",
+                                         inputPath(rom_path), outputPath(title_moves_path))) };
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -418,9 +3000,19 @@ namespace lunar_magic_wrapper {
         return result;
     }
 
+    Result LunarMagicWrapper::exportTitleMoves(const fs::path &rom_path) {
+        return exportTitleMoves(rom_path, defaultTitleMovesPath(rom_path));
+    }
+
     Result LunarMagicWrapper::importTitleMoves(const fs::path &rom_path, const fs::path &title_moves_path) {
-        auto result{ call(fmt::format(R"(-ImportTitleMoves "{}" "{}")",
-                                         rom_path.string(), title_moves_path.string())) };
+        throwIfInvalidTitleMoves(title_moves_path);
+
+        const auto rom_lock{ beforeWrite(rom_path) };
+
+        auto result{ call(Operation::IMPORT_TITLE_MOVES, fmt::format(R"("{}" "{}")",
+                                         inputPath(rom_path), inputPath(title_moves_path))) };
+
+        throwIfKnownFailure(rom_path, result);
 
         if (!result.succeeded) {
             throw LunarMagicWrapperException(fmt::format(
@@ -429,6 +3021,12 @@ namespace lunar_magic_wrapper {
             ), result);
         }
 
+        afterWrite(rom_path, result);
+
         return result;
     }
+
+    Result LunarMagicWrapper::importTitleMoves(const fs::path &rom_path) {
+        return importTitleMoves(rom_path, defaultTitleMovesPath(rom_path));
+    }
 } // lunar_magic_wrapper
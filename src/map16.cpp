@@ -0,0 +1,73 @@
+#include "../include/map16.h"
+
+#include <algorithm>
+#include <fstream>
+#include <iterator>
+
+#include "fmt/format.h"
+
+namespace lunar_magic_wrapper {
+    uint32_t readLong(const std::vector<uint8_t> &bytes, size_t offset) {
+        return bytes[offset] | (bytes[offset + 1] << 8) | (bytes[offset + 2] << 16)
+            | (static_cast<uint32_t>(bytes[offset + 3]) << 24);
+    }
+
+    size_t Map16SectionTable::end() const {
+        size_t last{ offset + size };
+        for (const auto& section : sections) {
+            last = std::max(last, section.offset + section.size);
+        }
+
+        return last;
+    }
+
+    Map16SectionTable Map16File::sectionTable(const std::vector<uint8_t> &bytes) {
+        if (bytes.size() < HEADER_SIZE || !std::equal(SIGNATURE.begin(), SIGNATURE.end(), bytes.begin())) {
+            throw Map16Exception("Not a map16 file, missing 'LM16' signature");
+        }
+
+        Map16SectionTable table{ readLong(bytes, 0x10), readLong(bytes, 0x14), {} };
+        if (table.size == 0 || table.size % 8 != 0 || table.offset + table.size > bytes.size()) {
+            throw Map16Exception(fmt::format(
+                "Map16 section table (0x{:X} bytes at 0x{:X}) is outside of the file of 0x{:X} bytes",
+                table.size, table.offset, bytes.size()
+            ));
+        }
+
+        for (size_t i{}; i < table.size / 8; ++i) {
+            table.sections.push_back({ readLong(bytes, table.offset + i * 8), readLong(bytes, table.offset + i * 8 + 4) });
+        }
+
+        return table;
+    }
+
+    void Map16File::validate(const std::vector<uint8_t> &bytes) {
+        const auto table{ sectionTable(bytes) };
+
+        for (size_t i{}; i < table.sections.size(); ++i) {
+            const auto [offset, size]{ table.sections[i] };
+
+            if (offset + size > bytes.size()) {
+                throw Map16Exception(fmt::format(
+                    "Map16 section {} (0x{:X} bytes at 0x{:X}) is outside of the file of 0x{:X} bytes, "
+                    "it may be truncated", i, size, offset, bytes.size()
+                ));
+            }
+
+            if (i == 0 && size % TILE_SIZE != 0) {
+                throw Map16Exception(fmt::format(
+                    "Map16 tile data is 0x{:X} bytes, not a multiple of the 0x{:X} bytes of a tile", size, TILE_SIZE
+                ));
+            }
+        }
+    }
+
+    void Map16File::validate(const fs::path &map16_path) {
+        std::ifstream map16_file(map16_path, std::ios::binary);
+        if (!map16_file) {
+            throw Map16Exception(fmt::format("Failed to open map16 file '{}'", map16_path.string()));
+        }
+
+        validate(std::vector<uint8_t>{ std::istreambuf_iterator<char>(map16_file), std::istreambuf_iterator<char>() });
+    }
+} // lunar_magic_wrapper
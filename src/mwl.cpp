@@ -0,0 +1,291 @@
+#include "../include/mwl.h"
+#include "../include/map16.h"
+
+#include <algorithm>
+#include <fstream>
+#include <iterator>
+
+#include "fmt/format.h"
+
+namespace lunar_magic_wrapper {
+    namespace {
+        // removes everything both lists have in common from both, matching each element at most once
+        template<typename T>
+        void removeCommon(std::vector<T>& before, std::vector<T>& after) {
+            for (auto it{ before.begin() }; it != before.end();) {
+                const auto match{ std::find(after.begin(), after.end(), *it) };
+                if (match != after.end()) {
+                    after.erase(match);
+                    it = before.erase(it);
+                } else {
+                    ++it;
+                }
+            }
+        }
+
+        // pairs up the remaining elements that are the same thing in a different place
+        template<typename T, typename Move, typename Same>
+        std::vector<Move> pairMoves(std::vector<T>& before, std::vector<T>& after, Same same) {
+            std::vector<Move> moves{};
+            for (auto it{ before.begin() }; it != before.end();) {
+                const auto match{ std::find_if(after.begin(), after.end(), [&](const T& other) {
+                    return same(*it, other);
+                }) };
+
+                if (match != after.end()) {
+                    moves.push_back({ *it, *match });
+                    after.erase(match);
+                    it = before.erase(it);
+                } else {
+                    ++it;
+                }
+            }
+
+            return moves;
+        }
+    }
+
+    MWL::MWL(std::vector<uint8_t> bytes) : data(std::move(bytes)) {
+        if (data.size() < 0x10 || data[0] != 'L' || data[1] != 'M') {
+            throw MWLException("Not an MWL file, missing 'LM' signature");
+        }
+
+        const auto table_offset{ readLong(data, 4) };
+        if (table_offset + SECTION_COUNT * 8 > data.size()) {
+            throw MWLException(fmt::format("MWL section table at 0x{:X} is outside of the file", table_offset));
+        }
+
+        for (size_t i{}; i < SECTION_COUNT; ++i) {
+            const auto offset{ readLong(data, table_offset + i * 8) };
+            const auto size{ readLong(data, table_offset + i * 8 + 4) };
+
+            if (static_cast<size_t>(offset) + size > data.size()) {
+                throw MWLException(fmt::format(
+                    "MWL section {} (0x{:X} bytes at 0x{:X}) is outside of the file", i, size, offset
+                ));
+            }
+
+            sections[i] = { offset, size };
+        }
+
+        parseLayer1();
+        parseSprites();
+    }
+
+    MWL MWL::read(const fs::path &mwl_path) {
+        std::ifstream mwl_file(mwl_path, std::ios::binary);
+        if (!mwl_file) {
+            throw MWLException(fmt::format("Failed to open MWL '{}'", mwl_path.string()));
+        }
+
+        std::vector<uint8_t> bytes{ std::istreambuf_iterator<char>(mwl_file), std::istreambuf_iterator<char>() };
+        return MWL(std::move(bytes));
+    }
+
+    void MWL::write(const fs::path &mwl_path) const {
+        std::ofstream mwl_file(mwl_path, std::ios::binary);
+        if (!mwl_file) {
+            throw MWLException(fmt::format("Failed to open '{}' for writing", mwl_path.string()));
+        }
+
+        mwl_file.write(reinterpret_cast<const char*>(data.data()), static_cast<std::streamsize>(data.size()));
+    }
+
+    uint16_t MWL::formatVersion() const {
+        return data[2] | (data[3] << 8);
+    }
+
+    std::string MWL::creator() const {
+        std::string text{};
+        for (size_t i{ 0x10 }; i < std::min<size_t>(0x40, data.size()) && data[i] != 0; ++i) {
+            text += static_cast<char>(data[i]);
+        }
+
+        while (!text.empty() && text.back() == ' ') {
+            text.pop_back();
+        }
+
+        return text;
+    }
+
+    std::vector<uint8_t> MWL::section(Section section) const {
+        const auto [offset, size]{ sections[static_cast<size_t>(section)] };
+        return { data.begin() + offset, data.begin() + offset + size };
+    }
+
+    uint16_t MWL::levelNumber() const {
+        const auto info{ section(Section::LEVEL_INFORMATION) };
+        if (info.size() < 2) {
+            throw MWLException("MWL level information section is too small to contain a level number");
+        }
+
+        return info[0] | (info[1] << 8);
+    }
+
+    std::array<uint8_t, MWL::PRIMARY_HEADER_SIZE> MWL::primaryHeader() const {
+        const auto layer1{ section(Section::LAYER1) };
+        if (layer1.size() < SECTION_HEADER_SIZE + PRIMARY_HEADER_SIZE) {
+            throw MWLException("MWL layer 1 section is too small to contain a level header");
+        }
+
+        std::array<uint8_t, PRIMARY_HEADER_SIZE> header{};
+        std::copy_n(layer1.begin() + SECTION_HEADER_SIZE, PRIMARY_HEADER_SIZE, header.begin());
+        return header;
+    }
+
+    size_t MWL::levelDataSize() const {
+        size_t size{};
+        for (const auto level_section : { Section::LAYER1, Section::LAYER2, Section::SPRITES }) {
+            const auto section_size{ sections[static_cast<size_t>(level_section)].second };
+            size += section_size > SECTION_HEADER_SIZE ? section_size - SECTION_HEADER_SIZE : 0;
+        }
+
+        return size;
+    }
+
+    void MWL::parseLayer1() {
+        const auto layer1{ section(Section::LAYER1) };
+        if (layer1.size() < SECTION_HEADER_SIZE + PRIMARY_HEADER_SIZE) {
+            return;
+        }
+
+        uint8_t screen{};
+        size_t i{ SECTION_HEADER_SIZE + PRIMARY_HEADER_SIZE };
+
+        while (i < layer1.size() && layer1[i] != 0xFF) {
+            if (i + 3 > layer1.size()) {
+                throw MWLException(fmt::format("MWL layer 1 data ends in the middle of an object at 0x{:X}", i));
+            }
+
+            const auto first{ layer1[i] };
+            const auto second{ layer1[i + 1] };
+            const auto third{ layer1[i + 2] };
+            const uint8_t number = ((first & 0x60) >> 1) | (second >> 4);
+
+            if (number == 0 && third == 0x00) {
+                if (i + 4 > layer1.size()) {
+                    throw MWLException(fmt::format("MWL layer 1 data ends in the middle of a screen exit at 0x{:X}", i));
+                }
+
+                screen_exits.push_back({
+                    static_cast<uint8_t>(first & 0x1F),
+                    static_cast<uint16_t>(layer1[i + 3] | ((second & 0x01) << 8)),
+                    (second & 0x02) != 0
+                });
+                i += 4;
+                continue;
+            }
+
+            if (number == 0 && third == 0x01) {
+                screen = first & 0x1F;
+                i += 3;
+                continue;
+            }
+
+            if (first & 0x80) {
+                ++screen;
+            }
+
+            objects.push_back({
+                screen, static_cast<uint8_t>(second & 0x0F), static_cast<uint8_t>(first & 0x1F), number, third
+            });
+            i += 3;
+        }
+    }
+
+    void MWL::parseSprites() {
+        const auto sprite_data{ section(Section::SPRITES) };
+
+        // section header, then a single sprite header byte
+        size_t i{ SECTION_HEADER_SIZE + 1 };
+
+        while (i < sprite_data.size() && sprite_data[i] != 0xFF) {
+            if (i + 3 > sprite_data.size()) {
+                throw MWLException(fmt::format("MWL sprite data ends in the middle of a sprite at 0x{:X}", i));
+            }
+
+            const auto first{ sprite_data[i] };
+            const auto second{ sprite_data[i + 1] };
+
+            sprites.push_back({
+                static_cast<uint8_t>(((first & 0x02) << 3) | (second & 0x0F)),
+                static_cast<uint8_t>(second >> 4),
+                static_cast<uint8_t>(((first & 0x01) << 4) | (first >> 4)),
+                static_cast<uint8_t>((first >> 2) & 0x03),
+                sprite_data[i + 2]
+            });
+            i += 3;
+        }
+    }
+
+    LevelSummary MWL::summary() const {
+        const auto header{ primaryHeader() };
+
+        return {
+            levelNumber(),
+            screenCount(),
+            static_cast<uint8_t>(header[1] & 0x1F),
+            static_cast<uint8_t>(header[4] & 0x0F),
+            static_cast<uint8_t>((header[2] >> 4) & 0x07),
+            objects.size(),
+            sprites.size(),
+            screen_exits
+        };
+    }
+
+    std::string LevelSummary::toString() const {
+        auto text{ fmt::format("Level: {:03X}\nScreens: {}\nLevel mode: {:02X}\nTileset: {:X}\nMusic: {}\n"
+                               "Objects: {}\nSprites: {}\nExits:\n", level_number, screen_count, level_mode,
+                               tileset, music, object_count, sprite_count) };
+
+        for (const auto& exit : screen_exits) {
+            text += fmt::format("  screen {:02X} -> {} {:03X}\n", exit.screen,
+                                exit.secondary ? "secondary exit" : "level", exit.destination);
+        }
+
+        return text;
+    }
+
+    std::string LevelSummary::toJSON() const {
+        std::string exits{};
+        for (const auto& exit : screen_exits) {
+            exits += fmt::format("{}\n    {{ \"screen\": {}, \"destination\": {}, \"secondary\": {} }}",
+                                 exits.empty() ? "" : ",", exit.screen, exit.destination,
+                                 exit.secondary ? "true" : "false");
+        }
+
+        return fmt::format("{{\n  \"level_number\": {},\n  \"screen_count\": {},\n  \"level_mode\": {},\n"
+                           "  \"tileset\": {},\n  \"music\": {},\n  \"object_count\": {},\n"
+                           "  \"sprite_count\": {},\n  \"screen_exits\": [{}]\n}}\n",
+                           level_number, screen_count, level_mode, tileset, music, object_count, sprite_count,
+                           exits.empty() ? "" : exits + "\n  ");
+    }
+
+    MWLDiff MWL::diff(const MWL &before, const MWL &after) {
+        MWLDiff result{};
+        result.level_number_changed = before.levelNumber() != after.levelNumber();
+        result.primary_header_changed = before.primaryHeader() != after.primaryHeader();
+
+        auto removed_objects{ before.objects };
+        auto added_objects{ after.objects };
+        removeCommon(removed_objects, added_objects);
+        result.moved_objects = pairMoves<LevelObject, ObjectMove>(removed_objects, added_objects,
+            [](const LevelObject& a, const LevelObject& b) {
+                return a.number == b.number && a.settings == b.settings;
+            });
+        result.removed_objects = std::move(removed_objects);
+        result.added_objects = std::move(added_objects);
+
+        auto removed_sprites{ before.sprites };
+        auto added_sprites{ after.sprites };
+        removeCommon(removed_sprites, added_sprites);
+        result.moved_sprites = pairMoves<Sprite, SpriteMove>(removed_sprites, added_sprites,
+            [](const Sprite& a, const Sprite& b) {
+                return a.number == b.number && a.extra_bits == b.extra_bits;
+            });
+        result.removed_sprites = std::move(removed_sprites);
+        result.added_sprites = std::move(added_sprites);
+
+        return result;
+    }
+} // lunar_magic_wrapper
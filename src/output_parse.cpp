@@ -0,0 +1,196 @@
+#include "../include/output_parse.h"
+#include "text.h"
+
+#include <algorithm>
+#include <array>
+#include <cctype>
+#include <charconv>
+
+namespace lunar_magic_wrapper::output_parse {
+    namespace {
+        // nullopt for numbers too long to fit instead of throwing like std::stoul
+        template<typename T>
+        std::optional<T> parseDecimal(const std::string& digits) {
+            T value{};
+            const auto [end, error]{ std::from_chars(digits.data(), digits.data() + digits.size(), value) };
+            if (error != std::errc{} || end != digits.data() + digits.size()) {
+                return std::nullopt;
+            }
+            return value;
+        }
+    }
+
+    std::optional<uint16_t> levelNumber(const std::string &line) {
+        const auto lower_line{ lower(line) };
+        const std::string word{ "level " };
+
+        for (auto position{ lower_line.find(word) }; position != std::string::npos;
+             position = lower_line.find(word, position + 1)) {
+            if (position != 0 && std::isalpha(static_cast<unsigned char>(lower_line[position - 1]))) {
+                continue;
+            }
+
+            const auto start{ position + word.size() };
+            auto end{ start };
+            while (end < lower_line.size() && end - start < 3
+                   && std::isxdigit(static_cast<unsigned char>(lower_line[end]))) {
+                ++end;
+            }
+
+            if (end == start
+                || (end < lower_line.size() && std::isalnum(static_cast<unsigned char>(lower_line[end])))) {
+                continue;
+            }
+
+            return static_cast<uint16_t>(std::stoul(lower_line.substr(start, end - start), nullptr, 16));
+        }
+
+        return std::nullopt;
+    }
+
+    std::optional<fs::path> filePath(const std::string &line) {
+        const auto lower_line{ lower(line) };
+
+        for (const std::string word : { " from ", " to " }) {
+            const auto position{ lower_line.find(word) };
+            if (position == std::string::npos) {
+                continue;
+            }
+
+            auto candidate{ line.substr(position + word.size()) };
+            while (!candidate.empty() && (candidate.back() == '.' || candidate.back() == ' ')) {
+                candidate.pop_back();
+            }
+            if (candidate.size() >= 2 && candidate.front() == '"' && candidate.back() == '"') {
+                candidate = candidate.substr(1, candidate.size() - 2);
+            }
+
+            if (fs::path(candidate).has_extension()) {
+                return candidate;
+            }
+        }
+
+        return std::nullopt;
+    }
+
+    std::optional<size_t> count(const std::string &line) {
+        size_t start{};
+        while (start < line.size() && line[start] == ' ') {
+            ++start;
+        }
+
+        auto end{ start };
+        while (end < line.size() && std::isdigit(static_cast<unsigned char>(line[end]))) {
+            ++end;
+        }
+
+        if (end == start || end + 1 >= line.size() || line[end] != ' '
+            || !std::isalpha(static_cast<unsigned char>(line[end + 1]))) {
+            return std::nullopt;
+        }
+
+        return parseDecimal<size_t>(line.substr(start, end - start));
+    }
+
+    std::optional<uint64_t> bytesWritten(const std::string &line) {
+        const auto lower_line{ lower(line) };
+        const std::string words{ " bytes written" };
+
+        const auto position{ lower_line.find(words) };
+        if (position == std::string::npos) {
+            return std::nullopt;
+        }
+
+        auto start{ position };
+        while (start > 0 && (std::isdigit(static_cast<unsigned char>(line[start - 1])) || line[start - 1] == ',')) {
+            --start;
+        }
+
+        std::string digits{};
+        for (auto i{ start }; i < position; ++i) {
+            if (line[i] != ',') {
+                digits += line[i];
+            }
+        }
+
+        // a number that's part of a word, like the 800 of 0x800, isn't decimal
+        if (digits.empty() || line[start] == ','
+            || (start > 0 && std::isalpha(static_cast<unsigned char>(line[start - 1])))) {
+            return std::nullopt;
+        }
+
+        return parseDecimal<uint64_t>(digits);
+    }
+
+    std::optional<uint64_t> totalBytesWritten(const std::vector<std::string> &lines) {
+        std::optional<uint64_t> total{};
+        for (const auto& line : lines) {
+            if (const auto bytes{ bytesWritten(line) }) {
+                total = total.value_or(0) + *bytes;
+            }
+        }
+
+        return total;
+    }
+
+    std::string joined(const std::vector<std::string> &lines) {
+        std::string text{};
+        for (const auto& line : lines) {
+            if (&line != &lines.front()) {
+                text += '\n';
+            }
+            text += line;
+        }
+
+        return text;
+    }
+
+    std::optional<std::string> findLine(const std::vector<std::string> &lines, const std::string &text) {
+        const auto match{ std::find_if(lines.begin(), lines.end(), [&](const std::string& line) {
+            return line.find(text) != std::string::npos;
+        }) };
+
+        return match == lines.end() ? std::nullopt : std::make_optional(*match);
+    }
+
+    bool containsLine(const std::vector<std::string> &lines, const std::string &text) {
+        return findLine(lines, text).has_value();
+    }
+
+    std::vector<uint16_t> levelNumbers(const std::vector<std::string> &lines) {
+        std::vector<uint16_t> numbers{};
+        for (const auto& line : lines) {
+            if (const auto number{ levelNumber(line) }) {
+                numbers.push_back(*number);
+            }
+        }
+
+        return numbers;
+    }
+
+    std::vector<ClassifiedLine> classify(const std::vector<std::string> &lines, const ClassifierPatterns &patterns) {
+        const std::array<std::pair<OutputLineKind, const std::vector<std::string>*>, 4> kinds{ {
+            { OutputLineKind::ERROR, &patterns.error }, { OutputLineKind::WARNING, &patterns.warning },
+            { OutputLineKind::PROGRESS, &patterns.progress }, { OutputLineKind::INFO, &patterns.info }
+        } };
+
+        std::vector<ClassifiedLine> classified{};
+        for (const auto& line : lines) {
+            const auto lower_line{ lower(line) };
+            auto kind{ OutputLineKind::UNKNOWN };
+
+            for (const auto& [candidate, phrases] : kinds) {
+                if (std::any_of(phrases->begin(), phrases->end(), [&](const std::string& phrase) {
+                    return !phrase.empty() && lower_line.find(lower(phrase)) != std::string::npos;
+                })) {
+                    kind = candidate;
+                    break;
+                }
+            }
+
+            classified.push_back({ kind, line });
+        }
+
+        return classified;
+    }
+} // lunar_magic_wrapper::output_parse
@@ -0,0 +1,68 @@
+#include "../include/output_testing.h"
+
+#include <algorithm>
+#include <optional>
+#include <regex>
+
+#include "fmt/format.h"
+
+namespace lunar_magic_wrapper::output_testing {
+    namespace {
+        const std::string REGEX_PREFIX{ "re:" };
+
+        bool globMatches(const std::string& line, const std::string& pattern) {
+            size_t l{};
+            size_t p{};
+            // where the last * was and how much of the line it had swallowed, to backtrack to on a mismatch
+            std::optional<size_t> star{};
+            size_t star_line{};
+
+            while (l < line.size()) {
+                if (p < pattern.size() && (pattern[p] == '?' || pattern[p] == line[l])) {
+                    ++l;
+                    ++p;
+                } else if (p < pattern.size() && pattern[p] == '*') {
+                    star = p++;
+                    star_line = l;
+                } else if (star) {
+                    p = *star + 1;
+                    l = ++star_line;
+                } else {
+                    return false;
+                }
+            }
+
+            return std::all_of(pattern.begin() + static_cast<std::ptrdiff_t>(p), pattern.end(),
+                               [](char c) { return c == '*'; });
+        }
+    }
+
+    bool lineMatches(const std::string &line, const std::string &pattern) {
+        if (pattern.starts_with(REGEX_PREFIX)) {
+            return std::regex_match(line, std::regex(pattern.substr(REGEX_PREFIX.size())));
+        }
+
+        return globMatches(line, pattern);
+    }
+
+    std::string outputDiff(const std::vector<std::string> &actual, const std::vector<std::string> &expected_patterns) {
+        std::string diff{};
+        for (size_t i{}; i < std::max(actual.size(), expected_patterns.size()); ++i) {
+            if (i >= actual.size()) {
+                diff += fmt::format("line {}: missing, expected '{}'\n", i + 1, expected_patterns[i]);
+            } else if (i >= expected_patterns.size()) {
+                diff += fmt::format("line {}: unexpected '{}'\n", i + 1, actual[i]);
+            } else if (!lineMatches(actual[i], expected_patterns[i])) {
+                diff += fmt::format("line {}: expected '{}', got '{}'\n", i + 1, expected_patterns[i], actual[i]);
+            }
+        }
+
+        return diff;
+    }
+
+    void assertOutputMatches(const std::vector<std::string> &actual, const std::vector<std::string> &expected_patterns) {
+        if (const auto diff{ outputDiff(actual, expected_patterns) }; !diff.empty()) {
+            throw OutputMismatchException(fmt::format("Output doesn't match the expected patterns:\n{}", diff));
+        }
+    }
+} // lunar_magic_wrapper::output_testing
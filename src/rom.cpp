@@ -0,0 +1,507 @@
+#include "../include/rom.h"
+#include "text.h"
+
+#include <fstream>
+#include <iterator>
+#include <algorithm>
+#include <cctype>
+#include <sstream>
+
+#include "fmt/format.h"
+
+namespace lunar_magic_wrapper {
+    std::string_view mappingModeToString(MappingMode mode) {
+        switch (mode) {
+            case MappingMode::LOROM:
+                return "LoROM";
+
+            case MappingMode::HIROM:
+                return "HiROM";
+
+            case MappingMode::EXLOROM:
+                return "ExLoROM";
+
+            case MappingMode::EXHIROM:
+                return "ExHiROM";
+
+            case MappingMode::SA1:
+                return "SA-1";
+
+            case MappingMode::UNKNOWN:
+                return "unknown";
+        }
+
+        return "unknown";
+    }
+
+    ROM::ROM(std::vector<uint8_t> bytes) : data(std::move(bytes)) {
+        if (data.size() % 0x8000 == COPIER_HEADER_SIZE) {
+            has_copier_header = true;
+            data.erase(data.begin(), data.begin() + COPIER_HEADER_SIZE);
+        }
+    }
+
+    ROM ROM::fromFile(const fs::path &rom_path) {
+        std::ifstream rom_file(rom_path, std::ios::binary);
+        if (!rom_file) {
+            throw ROMException(fmt::format("Failed to open ROM '{}'", rom_path.string()));
+        }
+
+        std::vector<uint8_t> bytes{ std::istreambuf_iterator<char>(rom_file), std::istreambuf_iterator<char>() };
+        return ROM(std::move(bytes));
+    }
+
+    bool ROM::hasROMExtension(const fs::path &rom_path) {
+        return std::any_of(EXTENSIONS.begin(), EXTENSIONS.end(), [&](std::string_view extension) {
+            return hasExtension(rom_path, extension);
+        });
+    }
+
+    size_t ROM::snesToPc(uint32_t snes_address) {
+        return ((snes_address & 0x7F0000) >> 1) | (snes_address & 0x7FFF);
+    }
+
+    MappingMode ROM::mappingMode() const {
+        const auto score{ [this](size_t header, bool hirom) {
+            if (data.size() < header + 0x40) {
+                return -1;
+            }
+
+            int points{};
+            const auto complement{ data[header + 0x1C] | (data[header + 0x1D] << 8) };
+            const auto checksum{ data[header + 0x1E] | (data[header + 0x1F] << 8) };
+            if ((checksum ^ complement) == 0xFFFF) {
+                points += 2;
+            }
+
+            // ignoring the FastROM bit, SA-1 and ExLoROM keep their header where LoROM does
+            const auto map_mode{ data[header + 0x15] & 0xEF };
+            const auto hirom_map_mode{ map_mode == 0x21 || map_mode == 0x25 };
+            if ((map_mode & 0xE0) == 0x20 && hirom_map_mode == hirom) {
+                points += 2;
+            }
+
+            const auto reset_vector{ data[header + 0x3C] | (data[header + 0x3D] << 8) };
+            if (reset_vector >= 0x8000) {
+                points += 1;
+            }
+
+            return points;
+        } };
+
+        const auto lorom_score{ score(LOROM_HEADER, false) };
+        const auto hirom_score{ score(HIROM_HEADER, true) };
+
+        if (std::max(lorom_score, hirom_score) <= 0) {
+            return MappingMode::UNKNOWN;
+        }
+
+        const auto header{ hirom_score > lorom_score ? HIROM_HEADER : LOROM_HEADER };
+        switch (data[header + 0x15] & 0x2F) {
+            case 0x20:
+                return MappingMode::LOROM;
+
+            case 0x21:
+                return MappingMode::HIROM;
+
+            case 0x22:
+                return MappingMode::EXLOROM;
+
+            case 0x23:
+                return MappingMode::SA1;
+
+            case 0x25:
+                return MappingMode::EXHIROM;
+
+            default:
+                return MappingMode::UNKNOWN;
+        }
+    }
+
+    size_t ROM::headerOffset() const {
+        const auto mode{ mappingMode() };
+        return mode == MappingMode::HIROM || mode == MappingMode::EXHIROM ? HIROM_HEADER : LOROM_HEADER;
+    }
+
+    ROMInfo ROM::info() const {
+        const auto header{ headerOffset() };
+        if (data.size() < header + 0x20) {
+            throw ROMException("ROM is too small to contain an internal header");
+        }
+
+        // ASCII, plus half-width katakana at A1-DF which map to U+FF61-U+FF9F
+        std::string title{};
+        for (size_t i{}; i < 21; ++i) {
+            const auto c{ data[header + i] };
+            if (c >= 0xA1 && c <= 0xDF) {
+                const auto code_point{ 0xFF61u + (c - 0xA1u) };
+                title += static_cast<char>(0xE0 | (code_point >> 12));
+                title += static_cast<char>(0x80 | ((code_point >> 6) & 0x3F));
+                title += static_cast<char>(0x80 | (code_point & 0x3F));
+            } else if (c >= 0x20 && c < 0x7F) {
+                title += static_cast<char>(c);
+            } else {
+                title += ' ';
+            }
+        }
+
+        while (!title.empty() && title.back() == ' ') {
+            title.pop_back();
+        }
+
+        return { title, data[header + 0x19], data[header + 0x1B], data[header + 0x17], mappingMode() };
+    }
+
+    uint16_t ROM::headerChecksum() const {
+        const auto header{ headerOffset() };
+        if (data.size() < header + 0x20) {
+            throw ROMException("ROM is too small to contain an internal header");
+        }
+
+        return data[header + 0x1E] | (data[header + 0x1F] << 8);
+    }
+
+    uint16_t ROM::computeChecksum() const {
+        const auto header{ headerOffset() };
+        if (data.size() < header + 0x20) {
+            throw ROMException("ROM is too small to contain an internal header");
+        }
+
+        size_t base{ 1 };
+        while (base * 2 <= data.size()) {
+            base *= 2;
+        }
+
+        const auto sum_range{ [this](size_t begin, size_t end) {
+            uint32_t sum{};
+            for (size_t i{ begin }; i < end; ++i) {
+                sum += data[i];
+            }
+            return sum;
+        } };
+
+        auto sum{ sum_range(0, base) };
+        const auto remainder{ data.size() - base };
+        if (remainder != 0) {
+            sum += sum_range(base, data.size()) * static_cast<uint32_t>(base / remainder);
+        }
+
+        // swap whatever is stored for 0000/FFFF
+        sum -= data[header + 0x1C] + data[header + 0x1D] + data[header + 0x1E] + data[header + 0x1F];
+        sum += 0xFF + 0xFF;
+
+        return static_cast<uint16_t>(sum & 0xFFFF);
+    }
+
+    uint16_t ROM::fixChecksum(const fs::path &rom_path) {
+        const auto rom{ fromFile(rom_path) };
+        const auto checksum{ rom.computeChecksum() };
+        const auto complement{ static_cast<uint16_t>(checksum ^ 0xFFFF) };
+
+        std::fstream rom_file(rom_path, std::ios::binary | std::ios::in | std::ios::out);
+        if (!rom_file) {
+            throw ROMException(fmt::format("Failed to open ROM '{}' for writing", rom_path.string()));
+        }
+
+        const std::array<char, 4> checksum_bytes{
+            static_cast<char>(complement & 0xFF), static_cast<char>(complement >> 8),
+            static_cast<char>(checksum & 0xFF), static_cast<char>(checksum >> 8)
+        };
+        rom_file.seekp(static_cast<std::streamoff>(
+            (rom.hasCopierHeader() ? COPIER_HEADER_SIZE : 0) + rom.headerOffset() + 0x1C
+        ));
+        rom_file.write(checksum_bytes.data(), checksum_bytes.size());
+
+        return checksum;
+    }
+
+    std::vector<ROMChange> ROM::changesFrom(const ROM &original) const {
+        std::vector<ROMChange> changes{};
+        for (size_t i{}; i < data.size(); ++i) {
+            if (i < original.data.size() && data[i] == original.data[i]) {
+                continue;
+            }
+
+            if (changes.empty() || changes.back().offset + changes.back().bytes.size() != i) {
+                changes.push_back({ i, {} });
+            }
+            changes.back().bytes.push_back(data[i]);
+        }
+
+        return changes;
+    }
+
+    void ROM::writeChanges(const fs::path &changes_path, const std::vector<ROMChange> &changes) {
+        constexpr size_t BYTES_PER_LINE{ 16 };
+
+        std::ofstream changes_file(changes_path);
+        if (!changes_file) {
+            throw ROMException(fmt::format("Failed to open '{}' for writing", changes_path.string()));
+        }
+
+        for (const auto& change : changes) {
+            for (size_t start{}; start < change.bytes.size(); start += BYTES_PER_LINE) {
+                changes_file << fmt::format("{:06X}:", change.offset + start);
+                for (size_t i{ start }; i < std::min(start + BYTES_PER_LINE, change.bytes.size()); ++i) {
+                    changes_file << fmt::format(" {:02X}", change.bytes[i]);
+                }
+                changes_file << '\n';
+            }
+        }
+    }
+
+    std::vector<ROMChange> ROM::readChanges(const fs::path &changes_path) {
+        std::ifstream changes_file(changes_path);
+        if (!changes_file) {
+            throw ROMException(fmt::format("Failed to open '{}'", changes_path.string()));
+        }
+
+        const auto parse_hex{ [](const std::string& text, size_t digits) -> std::optional<size_t> {
+            if (text.empty() || text.size() > digits
+                || !std::all_of(text.begin(), text.end(), [](unsigned char c) { return std::isxdigit(c); })) {
+                return std::nullopt;
+            }
+            return std::stoul(text, nullptr, 16);
+        } };
+
+        std::vector<ROMChange> changes{};
+        std::string line;
+        for (size_t line_number{ 1 }; std::getline(changes_file, line); ++line_number) {
+            if (!line.empty() && line.back() == '\r') {
+                line.pop_back();
+            }
+            if (line.empty() || line.starts_with('#')) {
+                continue;
+            }
+
+            const auto invalid{ [&] {
+                return ROMException(fmt::format(
+                    "Line {} of '{}' is not an \"<offset>: <bytes>\" line: {}", line_number, changes_path.string(), line
+                ));
+            } };
+
+            const auto colon{ line.find(':') };
+            if (colon == std::string::npos) {
+                throw invalid();
+            }
+
+            const auto offset{ parse_hex(line.substr(0, colon), 6) };
+            if (!offset) {
+                throw invalid();
+            }
+
+            ROMChange change{ *offset, {} };
+            std::istringstream byte_stream{ line.substr(colon + 1) };
+            std::string byte;
+            while (byte_stream >> byte) {
+                const auto value{ parse_hex(byte, 2) };
+                if (!value || byte.size() != 2) {
+                    throw invalid();
+                }
+                change.bytes.push_back(static_cast<uint8_t>(*value));
+            }
+            if (change.bytes.empty()) {
+                throw invalid();
+            }
+
+            // lines continuing the previous one are the same change, as writeChanges splits them
+            if (!changes.empty() && changes.back().offset + changes.back().bytes.size() == change.offset) {
+                changes.back().bytes.insert(changes.back().bytes.end(), change.bytes.begin(), change.bytes.end());
+            } else {
+                changes.push_back(std::move(change));
+            }
+        }
+
+        return changes;
+    }
+
+    void ROM::applyChanges(const fs::path &rom_path, const std::vector<ROMChange> &changes) {
+        std::vector<uint8_t> bytes{};
+        {
+            std::ifstream rom_file(rom_path, std::ios::binary);
+            if (!rom_file) {
+                throw ROMException(fmt::format("Failed to open ROM '{}'", rom_path.string()));
+            }
+            bytes.assign(std::istreambuf_iterator<char>(rom_file), std::istreambuf_iterator<char>());
+        }
+
+        const auto header_size{ ROM(bytes).hasCopierHeader() ? COPIER_HEADER_SIZE : 0 };
+        for (const auto& change : changes) {
+            const auto start{ header_size + change.offset };
+            if (bytes.size() < start + change.bytes.size()) {
+                bytes.resize(start + change.bytes.size());
+            }
+            std::copy(change.bytes.begin(), change.bytes.end(), bytes.begin() + static_cast<std::ptrdiff_t>(start));
+        }
+
+        std::ofstream rom_file(rom_path, std::ios::binary);
+        if (!rom_file) {
+            throw ROMException(fmt::format("Failed to open ROM '{}' for writing", rom_path.string()));
+        }
+        rom_file.write(reinterpret_cast<const char*>(bytes.data()), static_cast<std::streamsize>(bytes.size()));
+    }
+
+    bool ROM::containsSnes(uint32_t snes_address) const {
+        return (snes_address & 0x8000) != 0 && snesToPc(snes_address) < data.size();
+    }
+
+    uint8_t ROM::byteAt(uint32_t snes_address) const {
+        if (!containsSnes(snes_address)) {
+            throw ROMException(fmt::format("Address ${:06X} is outside of the ROM", snes_address));
+        }
+
+        return data[snesToPc(snes_address)];
+    }
+
+    uint32_t ROM::longAt(uint32_t snes_address) const {
+        return byteAt(snes_address) | (byteAt(snes_address + 1) << 8) | (byteAt(snes_address + 2) << 16);
+    }
+
+    size_t ROM::freeBytes() const {
+        size_t free{};
+        size_t run{};
+
+        for (size_t i{ ORIGINAL_ROM_SIZE }; i < data.size(); ++i) {
+            if (data[i] == 0x00) {
+                ++run;
+            } else {
+                free += run >= MIN_FREE_RUN ? run : 0;
+                run = 0;
+            }
+        }
+
+        return free + (run >= MIN_FREE_RUN ? run : 0);
+    }
+
+    std::vector<ExGFXSlot> ROM::usedExGFXSlots() const {
+        std::vector<ExGFXSlot> slots{};
+
+        // tables are contiguous in the file, so walk them by file offset rather than SNES address
+        const auto add_used{ [&](uint16_t first, uint16_t last, size_t table_offset) {
+            if (table_offset + (last - first + 1) * 3 > data.size()) {
+                return;
+            }
+
+            for (uint16_t number{ first }; number <= last; ++number) {
+                const auto entry{ table_offset + (number - first) * 3 };
+                const uint32_t pointer = data[entry] | (data[entry + 1] << 8) | (data[entry + 2] << 16);
+                if (pointer != 0x000000 && pointer != 0xFFFFFF && containsSnes(pointer)) {
+                    slots.push_back({ number, pointer });
+                }
+            }
+        } };
+
+        // Lunar Magic only installs the tables once it has expanded the ROM, before that this is SMW's own code
+        if (size() <= ORIGINAL_ROM_SIZE || !containsSnes(EXGFX_100_TABLE_POINTER + 2)) {
+            return slots;
+        }
+
+        add_used(0x80, 0xFF, snesToPc(EXGFX_80_TABLE));
+
+        const auto table{ longAt(EXGFX_100_TABLE_POINTER) };
+        if (table != 0x000000 && table != 0xFFFFFF && containsSnes(table)) {
+            add_used(0x100, 0xFFF, snesToPc(table));
+        }
+
+        return slots;
+    }
+
+    GraphicsUsage ROM::graphicsUsage() const {
+        GraphicsUsage usage{};
+
+        if (containsSnes(GFX_POINTER_TABLE_BANK + GFX_TABLE_ENTRIES - 1)) {
+            for (uint16_t number{}; number < GFX_TABLE_ENTRIES; ++number) {
+                const uint32_t pointer = byteAt(GFX_POINTER_TABLE_LOW + number)
+                    | (byteAt(GFX_POINTER_TABLE_HIGH + number) << 8)
+                    | (byteAt(GFX_POINTER_TABLE_BANK + number) << 16);
+                if (containsSnes(pointer)) {
+                    usage.used_gfx.push_back(number);
+                }
+            }
+            usage.used_gfx.push_back(0x32);
+            usage.used_gfx.push_back(0x33);
+        }
+
+        for (const auto& slot : usedExGFXSlots()) {
+            usage.used_exgfx.push_back(slot.number);
+        }
+
+        auto used{ usage.used_exgfx.begin() };
+        for (uint16_t number{ 0x80 }; number <= 0xFFF; ++number) {
+            if (used != usage.used_exgfx.end() && *used == number) {
+                ++used;
+            } else {
+                usage.free_exgfx.push_back(number);
+            }
+        }
+
+        return usage;
+    }
+
+    LevelPointers ROM::levelPointers(uint16_t level) const {
+        return {
+            longAt(LAYER1_POINTER_TABLE + level * 3),
+            longAt(LAYER2_POINTER_TABLE + level * 3),
+            static_cast<uint16_t>(byteAt(SPRITE_POINTER_TABLE + level * 2)
+                | (byteAt(SPRITE_POINTER_TABLE + level * 2 + 1) << 8))
+        };
+    }
+
+    uint8_t ROM::levelScreenCount(uint16_t level) const {
+        const auto layer1{ levelPointers(level).layer1 };
+        if (layer1 == 0x000000 || layer1 == 0xFFFFFF || !containsSnes(layer1)) {
+            throw ROMException(fmt::format(
+                "Level {:03X} doesn't exist, its layer 1 pointer ${:06X} is outside of the ROM", level, layer1
+            ));
+        }
+
+        return (byteAt(layer1) & 0x1F) + 1;
+    }
+
+    std::vector<SecondaryExit> ROM::secondaryExits() const {
+        std::vector<SecondaryExit> exits{};
+
+        if (!containsSnes(SECONDARY_EXIT_SETTINGS_TABLE + 0xFF)) {
+            return exits;
+        }
+
+        for (uint16_t level{}; level < LEVEL_COUNT; ++level) {
+            auto address{ longAt(LAYER1_POINTER_TABLE + level * 3) };
+            if (address == 0x000000 || address == 0xFFFFFF || !containsSnes(address)) {
+                continue;
+            }
+
+            // skip the primary header, objects follow until FF, see MWL::parseLayer1 for the format
+            address += 5;
+            while (containsSnes(address + 2) && byteAt(address) != 0xFF) {
+                const auto first{ byteAt(address) };
+                const auto second{ byteAt(address + 1) };
+                const auto third{ byteAt(address + 2) };
+                const uint8_t number = ((first & 0x60) >> 1) | (second >> 4);
+
+                if (number == 0 && third == 0x00) {
+                    if (!containsSnes(address + 3)) {
+                        break;
+                    }
+
+                    if (second & 0x02) {
+                        const uint16_t exit_number = byteAt(address + 3) | ((second & 0x01) << 8);
+                        std::optional<uint16_t> destination{};
+                        if (exit_number < 0x100) {
+                            destination = byteAt(SECONDARY_EXIT_LEVEL_TABLE + exit_number)
+                                | ((byteAt(SECONDARY_EXIT_SETTINGS_TABLE + exit_number) & 0x08) << 5);
+                        }
+
+                        exits.push_back({ level, static_cast<uint8_t>(first & 0x1F), exit_number, destination });
+                    }
+
+                    address += 4;
+                } else {
+                    address += 3;
+                }
+            }
+        }
+
+        return exits;
+    }
+} // lunar_magic_wrapper
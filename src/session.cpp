@@ -0,0 +1,275 @@
+#include "../include/session.h"
+
+#include <system_error>
+#include <utility>
+
+#include "fmt/format.h"
+
+#ifdef _WIN32
+#define WIN32_LEAN_AND_MEAN
+#define NOMINMAX
+#include <windows.h>
+#else
+#include <cerrno>
+#include <csignal>
+#include <thread>
+#include <fcntl.h>
+#include <sys/wait.h>
+#include <unistd.h>
+#endif
+
+namespace lunar_magic_wrapper {
+#ifdef _WIN32
+    LunarMagicSession::LunarMagicSession(const fs::path &lunar_magic_path, const fs::path &rom_path) {
+        auto command_line{ L"\"" + lunar_magic_path.wstring() + L"\" \"" + rom_path.wstring() + L"\"" };
+
+        STARTUPINFOW startup_info{};
+        startup_info.cb = sizeof(startup_info);
+        PROCESS_INFORMATION process_info{};
+
+        // started suspended so it's inside the job before it can spawn anything of its own
+        if (!CreateProcessW(nullptr, command_line.data(), nullptr, nullptr, FALSE, CREATE_SUSPENDED, nullptr,
+                            nullptr, &startup_info, &process_info)) {
+            throw LaunchException({ static_cast<int>(GetLastError()), std::system_category() },
+                                  "Failed to start Lunar Magic");
+        }
+
+        // the job lets kill() take down the whole process tree, without KILL_ON_JOB_CLOSE so that closing the
+        // handle still leaves the editor running; if the process can't be put in a job, only it gets killed
+        job_handle = CreateJobObjectW(nullptr, nullptr);
+        if (job_handle && !AssignProcessToJobObject(job_handle, process_info.hProcess)) {
+            CloseHandle(job_handle);
+            job_handle = nullptr;
+        }
+
+        ResumeThread(process_info.hThread);
+        CloseHandle(process_info.hThread);
+        process_handle = process_info.hProcess;
+    }
+
+    LunarMagicSession::LunarMagicSession(LunarMagicSession &&other) noexcept
+    : process_handle(std::exchange(other.process_handle, nullptr)),
+    job_handle(std::exchange(other.job_handle, nullptr)),
+    exit_code(std::exchange(other.exit_code, MOVED_FROM_EXIT_CODE)),
+    kill_on_destruction(other.kill_on_destruction) {}
+
+    LunarMagicSession &LunarMagicSession::operator=(LunarMagicSession &&other) noexcept {
+        if (this != &other) {
+            release();
+            process_handle = std::exchange(other.process_handle, nullptr);
+            job_handle = std::exchange(other.job_handle, nullptr);
+            exit_code = std::exchange(other.exit_code, MOVED_FROM_EXIT_CODE);
+            kill_on_destruction = other.kill_on_destruction;
+        }
+
+        return *this;
+    }
+
+    LunarMagicSession::~LunarMagicSession() {
+        release();
+    }
+
+    void LunarMagicSession::release() {
+        if (!process_handle) {
+            return;
+        }
+
+        if (kill_on_destruction && !exit_code) {
+            terminate();
+        }
+        if (job_handle) {
+            CloseHandle(job_handle);
+        }
+        CloseHandle(process_handle);
+        process_handle = nullptr;
+        job_handle = nullptr;
+    }
+
+    void LunarMagicSession::terminate() {
+        if (!job_handle || !TerminateJobObject(job_handle, 1)) {
+            TerminateProcess(process_handle, 1);
+        }
+    }
+
+    int LunarMagicSession::wait() {
+        if (!process_handle) {
+            return exit_code.value_or(MOVED_FROM_EXIT_CODE);
+        }
+
+        if (!exit_code) {
+            DWORD code{};
+            if (WaitForSingleObject(process_handle, INFINITE) == WAIT_FAILED
+                || !GetExitCodeProcess(process_handle, &code)) {
+                throw std::system_error({ static_cast<int>(GetLastError()), std::system_category() },
+                                        "Failed to wait for Lunar Magic");
+            }
+            exit_code = static_cast<int>(code);
+        }
+
+        return *exit_code;
+    }
+
+    std::optional<int> LunarMagicSession::tryWait() {
+        if (!process_handle) {
+            return exit_code.value_or(MOVED_FROM_EXIT_CODE);
+        }
+        if (exit_code) {
+            return exit_code;
+        }
+
+        const auto waited{ WaitForSingleObject(process_handle, 0) };
+        if (waited == WAIT_FAILED) {
+            throw std::system_error({ static_cast<int>(GetLastError()), std::system_category() },
+                                    "Failed to wait for Lunar Magic");
+        }
+
+        return waited == WAIT_OBJECT_0 ? std::make_optional(wait()) : std::nullopt;
+    }
+
+    void LunarMagicSession::kill() {
+        if (process_handle && !exit_code) {
+            terminate();
+            wait();
+        }
+    }
+#else
+    namespace {
+        int exitCode(int status) {
+            return WIFEXITED(status) ? WEXITSTATUS(status) : 128 + WTERMSIG(status);
+        }
+    }
+
+    LunarMagicSession::LunarMagicSession(const fs::path &lunar_magic_path, const fs::path &rom_path) {
+        // closed by a successful exec, otherwise the child reports its errno through it
+        int exec_error_pipe[2]{};
+        if (pipe(exec_error_pipe) == -1) {
+            throw LaunchException({ errno, std::generic_category() }, "Failed to start Lunar Magic");
+        }
+        fcntl(exec_error_pipe[0], F_SETFD, FD_CLOEXEC);
+        fcntl(exec_error_pipe[1], F_SETFD, FD_CLOEXEC);
+
+        pid = fork();
+
+        if (pid == -1) {
+            const auto fork_error{ errno };
+            close(exec_error_pipe[0]);
+            close(exec_error_pipe[1]);
+            throw LaunchException({ fork_error, std::generic_category() }, "Failed to start Lunar Magic");
+        }
+
+        // own process group, so kill() also reaches anything the editor started
+        if (pid == 0) {
+            close(exec_error_pipe[0]);
+            setpgid(0, 0);
+            execl(lunar_magic_path.c_str(), lunar_magic_path.c_str(), rom_path.c_str(), nullptr);
+
+            const auto exec_error{ errno };
+            [[maybe_unused]] const auto written{ write(exec_error_pipe[1], &exec_error, sizeof(exec_error)) };
+            _exit(127);
+        }
+
+        // also set from the parent, the child may not have gotten to it before kill() is called
+        setpgid(pid, pid);
+
+        close(exec_error_pipe[1]);
+        int exec_error{};
+        ssize_t read_size{};
+        while ((read_size = read(exec_error_pipe[0], &exec_error, sizeof(exec_error))) == -1 && errno == EINTR) {}
+        close(exec_error_pipe[0]);
+
+        if (read_size == sizeof(exec_error)) {
+            while (waitpid(pid, nullptr, 0) == -1 && errno == EINTR) {}
+            pid = -1;
+            throw LaunchException({ exec_error, std::generic_category() }, fmt::format(
+                "Failed to start Lunar Magic '{}'", lunar_magic_path.string()
+            ));
+        }
+    }
+
+    LunarMagicSession::LunarMagicSession(LunarMagicSession &&other) noexcept
+    : pid(std::exchange(other.pid, -1)), exit_code(std::exchange(other.exit_code, MOVED_FROM_EXIT_CODE)),
+    kill_on_destruction(other.kill_on_destruction) {}
+
+    LunarMagicSession &LunarMagicSession::operator=(LunarMagicSession &&other) noexcept {
+        if (this != &other) {
+            release();
+            pid = std::exchange(other.pid, -1);
+            exit_code = std::exchange(other.exit_code, MOVED_FROM_EXIT_CODE);
+            kill_on_destruction = other.kill_on_destruction;
+        }
+
+        return *this;
+    }
+
+    LunarMagicSession::~LunarMagicSession() {
+        release();
+    }
+
+    void LunarMagicSession::release() {
+        if (pid == -1) {
+            return;
+        }
+
+        try {
+            if (kill_on_destruction) {
+                kill();
+            } else if (!tryWait()) {
+                // still running, someone has to reap it once the editor is closed or it stays a zombie
+                std::thread([child{ pid }] {
+                    while (waitpid(child, nullptr, 0) == -1 && errno == EINTR) {}
+                }).detach();
+            }
+        } catch (const std::system_error&) {
+            // already reaped elsewhere, nothing left to release
+        }
+
+        pid = -1;
+    }
+
+    int LunarMagicSession::wait() {
+        if (pid == -1) {
+            return exit_code.value_or(MOVED_FROM_EXIT_CODE);
+        }
+
+        if (!exit_code) {
+            int status{};
+            pid_t waited{};
+            while ((waited = waitpid(pid, &status, 0)) == -1 && errno == EINTR) {}
+            if (waited == -1) {
+                throw std::system_error({ errno, std::generic_category() }, "Failed to wait for Lunar Magic");
+            }
+            exit_code = exitCode(status);
+        }
+
+        return *exit_code;
+    }
+
+    std::optional<int> LunarMagicSession::tryWait() {
+        if (pid == -1) {
+            return exit_code.value_or(MOVED_FROM_EXIT_CODE);
+        }
+
+        if (!exit_code) {
+            int status{};
+            pid_t waited{};
+            while ((waited = waitpid(pid, &status, WNOHANG)) == -1 && errno == EINTR) {}
+            if (waited == -1) {
+                throw std::system_error({ errno, std::generic_category() }, "Failed to wait for Lunar Magic");
+            }
+            if (waited == pid) {
+                exit_code = exitCode(status);
+            }
+        }
+
+        return exit_code;
+    }
+
+    void LunarMagicSession::kill() {
+        // -1 would signal every process the user owns
+        if (pid != -1 && !exit_code) {
+            ::kill(-pid, SIGKILL);
+            wait();
+        }
+    }
+#endif
+} // lunar_magic_wrapper
@@ -0,0 +1,24 @@
+#ifndef LUNAR_MAGIC_WRAPPER_TEXT_H
+#define LUNAR_MAGIC_WRAPPER_TEXT_H
+
+#include <algorithm>
+#include <cctype>
+#include <filesystem>
+#include <string>
+#include <string_view>
+
+namespace lunar_magic_wrapper {
+    // ASCII only, enough for file names and Lunar Magic's output
+    inline std::string lower(std::string text) {
+        std::transform(text.begin(), text.end(), text.begin(),
+                       [](unsigned char c) { return static_cast<char>(std::tolower(c)); });
+        return text;
+    }
+
+    // whether the path ends in the (lowercase) extension, ignoring case like Windows and Lunar Magic do
+    inline bool hasExtension(const std::filesystem::path& path, std::string_view extension) {
+        return lower(path.extension().string()) == extension;
+    }
+} // lunar_magic_wrapper
+
+#endif //LUNAR_MAGIC_WRAPPER_TEXT_H
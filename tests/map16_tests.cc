@@ -0,0 +1,64 @@
+#include <fstream>
+#include <iterator>
+
+#include <gtest/gtest.h>
+
+#include <map16.h>
+
+
+using namespace lunar_magic_wrapper;
+
+namespace {
+    fs::path FixturePath(const fs::path& rel_path) {
+        return fs::path{ __FILE__ }.parent_path() / rel_path;
+    }
+
+    std::vector<uint8_t> ReadBytes(const fs::path& path) {
+        std::ifstream file(path, std::ios::binary);
+        return { std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>() };
+    }
+}
+
+TEST(Map16Test, AcceptsExportedFiles) {
+    EXPECT_NO_THROW(Map16File::validate(FixturePath("all.map16")));
+    EXPECT_NO_THROW(Map16File::validate(FixturePath("partial.map16")));
+}
+
+TEST(Map16Test, RejectsTruncatedFiles) {
+    try {
+        Map16File::validate(FixturePath("truncated.map16"));
+        FAIL() << "Expected Map16Exception";
+    } catch (const Map16Exception& e) {
+        EXPECT_NE(std::string(e.what()).find("truncated"), std::string::npos);
+    }
+
+    auto bytes{ ReadBytes(FixturePath("partial.map16")) };
+    bytes.resize(0x20);
+    EXPECT_THROW(Map16File::validate(bytes), Map16Exception);
+}
+
+TEST(Map16Test, RejectsPartialTiles) {
+    auto bytes{ ReadBytes(FixturePath("partial.map16")) };
+    // size of the tile data, 0x480 -> 0x47C
+    bytes[0x74] = 0x7C;
+
+    EXPECT_THROW(Map16File::validate(bytes), Map16Exception);
+}
+
+TEST(Map16Test, RejectsOtherFiles) {
+    EXPECT_THROW(Map16File::validate(FixturePath("level.mwl")), Map16Exception);
+    EXPECT_THROW(Map16File::validate(FixturePath("missing.map16")), Map16Exception);
+}
+
+TEST(Map16Test, ReadsSectionTable) {
+    auto bytes{ ReadBytes(FixturePath("partial.map16")) };
+
+    const auto table{ Map16File::sectionTable(bytes) };
+    EXPECT_EQ(table.end(), bytes.size());
+    ASSERT_FALSE(table.sections.empty());
+    EXPECT_EQ(table.sections.front().size, 0x480u);
+
+    // the offset is all four bytes, the highest one puts the table far outside of the file
+    bytes[0x13] = 0x01;
+    EXPECT_THROW(Map16File::sectionTable(bytes), Map16Exception);
+}
@@ -0,0 +1,151 @@
+#include <fstream>
+#include <iterator>
+
+#include <gtest/gtest.h>
+#include <fmt/format.h>
+
+#include <mwl.h>
+
+
+using namespace lunar_magic_wrapper;
+
+namespace {
+    fs::path FixturePath(const fs::path& rel_path) {
+        return fs::path{ __FILE__ }.parent_path() / rel_path;
+    }
+
+    std::vector<uint8_t> ReadBytes(const fs::path& path) {
+        std::ifstream file(path, std::ios::binary);
+        return { std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>() };
+    }
+
+    // PC offset of the first byte after a section's 8 byte header
+    size_t SectionData(const std::vector<uint8_t>& bytes, MWL::Section section) {
+        const auto entry{ 0x40 + static_cast<size_t>(section) * 8 };
+        return (bytes[entry] | (bytes[entry + 1] << 8) | (bytes[entry + 2] << 16)) + MWL::SECTION_HEADER_SIZE;
+    }
+}
+
+TEST(MWLTest, ReadsHeader) {
+    const auto mwl{ MWL::read(FixturePath("level.mwl")) };
+
+    EXPECT_EQ(mwl.levelNumber(), 0x105);
+    EXPECT_EQ(mwl.creator().substr(0, 16), "Lunar Magic 3.40");
+    EXPECT_EQ(mwl.section(MWL::Section::EXGFX_BYPASS).size(), 0x20u);
+}
+
+TEST(MWLTest, ReadsLevelNumbersOfExportedLevels) {
+    for (uint16_t level_number{}; level_number <= 4; ++level_number) {
+        const auto mwl{ MWL::read(FixturePath(fmt::format("levels/level {:03X}.mwl", level_number))) };
+        EXPECT_EQ(mwl.levelNumber(), level_number);
+    }
+}
+
+TEST(MWLTest, ReadsObjects) {
+    const auto mwl{ MWL::read(FixturePath("levels/level 000.mwl")) };
+
+    EXPECT_EQ(mwl.screenCount(), 1);
+    ASSERT_EQ(mwl.getObjects().size(), 9u);
+    EXPECT_EQ(mwl.getObjects()[0], (LevelObject{ 0, 0x0, 0x0C, 0x3C, 0x1F }));
+    EXPECT_EQ(mwl.getObjects()[1], (LevelObject{ 0, 0x0, 0x0D, 0x00, 0x57 }));
+    EXPECT_TRUE(mwl.getObjects()[1].isExtended());
+}
+
+TEST(MWLTest, ReadsSprites) {
+    const auto mwl{ MWL::read(FixturePath("level.mwl")) };
+
+    EXPECT_EQ(mwl.screenCount(), 20);
+    ASSERT_EQ(mwl.getSprites().size(), 34u);
+    EXPECT_EQ(mwl.getSprites()[0], (Sprite{ 0x00, 0xD, 0x11, 0, 0xBD }));
+    EXPECT_EQ(mwl.getSprites()[1].number, 0x9F);
+}
+
+TEST(MWLTest, WritesFileBackUnchanged) {
+    const auto mwl{ MWL::read(FixturePath("level.mwl")) };
+
+    mwl.write("round_trip.mwl");
+
+    EXPECT_EQ(MWL::read("round_trip.mwl").bytes(), mwl.bytes());
+    fs::remove("round_trip.mwl");
+}
+
+TEST(MWLTest, RejectsNonMWLFiles) {
+    EXPECT_THROW(MWL(std::vector<uint8_t>(0x100, 0)), MWLException);
+    EXPECT_THROW(MWL::read(FixturePath("palette.pal")), MWLException);
+}
+
+TEST(MWLTest, DiffOfIdenticalLevelsIsEmpty) {
+    const auto mwl{ MWL::read(FixturePath("level.mwl")) };
+
+    EXPECT_TRUE(MWL::diff(mwl, mwl).empty());
+}
+
+TEST(MWLTest, DiffReportsMovedObjects) {
+    auto bytes{ ReadBytes(FixturePath("levels/level 000.mwl")) };
+    const MWL before{ bytes };
+
+    // x of the first object, 0 -> 3
+    bytes[SectionData(bytes, MWL::Section::LAYER1) + MWL::PRIMARY_HEADER_SIZE + 1] = 0xC3;
+    const MWL after{ bytes };
+
+    const auto diff{ MWL::diff(before, after) };
+    ASSERT_EQ(diff.moved_objects.size(), 1u);
+    EXPECT_EQ(diff.moved_objects[0].from, (LevelObject{ 0, 0x0, 0x0C, 0x3C, 0x1F }));
+    EXPECT_EQ(diff.moved_objects[0].to, (LevelObject{ 0, 0x3, 0x0C, 0x3C, 0x1F }));
+    EXPECT_TRUE(diff.added_objects.empty());
+    EXPECT_TRUE(diff.removed_objects.empty());
+    EXPECT_TRUE(diff.added_sprites.empty());
+    EXPECT_FALSE(diff.primary_header_changed);
+}
+
+TEST(MWLTest, DiffReportsReplacedSprites) {
+    auto bytes{ ReadBytes(FixturePath("level.mwl")) };
+    const MWL before{ bytes };
+
+    // number of the first sprite, BD -> 0F, after the sprite header byte
+    bytes[SectionData(bytes, MWL::Section::SPRITES) + 1 + 2] = 0x0F;
+    const MWL after{ bytes };
+
+    const auto diff{ MWL::diff(before, after) };
+    ASSERT_EQ(diff.removed_sprites.size(), 1u);
+    ASSERT_EQ(diff.added_sprites.size(), 1u);
+    EXPECT_EQ(diff.removed_sprites[0].number, 0xBD);
+    EXPECT_EQ(diff.added_sprites[0].number, 0x0F);
+    EXPECT_TRUE(diff.moved_sprites.empty());
+    EXPECT_TRUE(diff.moved_objects.empty());
+}
+
+TEST(MWLTest, DiffOfDifferentLevels) {
+    const auto diff{ MWL::diff(MWL::read(FixturePath("levels/level 000.mwl")),
+                               MWL::read(FixturePath("levels/level 001.mwl"))) };
+
+    EXPECT_FALSE(diff.empty());
+    EXPECT_TRUE(diff.level_number_changed);
+}
+
+TEST(MWLTest, LevelDataSizeExcludesSectionHeaders) {
+    EXPECT_EQ(MWL::read(FixturePath("level.mwl")).levelDataSize(), 0x118u + 0x800u + 0x68u);
+    EXPECT_EQ(MWL::read(FixturePath("levels/level 000.mwl")).levelDataSize(), 0x21u + 0x800u + 0x5u);
+}
+
+TEST(MWLTest, SummarizesLevel) {
+    const auto summary{ MWL::read(FixturePath("level.mwl")).summary() };
+
+    EXPECT_EQ(summary.level_number, 0x105);
+    EXPECT_EQ(summary.screen_count, 20);
+    EXPECT_EQ(summary.tileset, 7);
+    EXPECT_EQ(summary.object_count, 88u);
+    EXPECT_EQ(summary.sprite_count, 34u);
+    ASSERT_EQ(summary.screen_exits.size(), 1u);
+    EXPECT_EQ(summary.screen_exits[0], (ScreenExit{ 0x07, 0x1CB, false }));
+
+    EXPECT_NE(summary.toString().find("Screens: 20\n"), std::string::npos);
+    EXPECT_NE(summary.toString().find("  screen 07 -> level 1CB\n"), std::string::npos);
+    EXPECT_NE(summary.toJSON().find(R"("screen_exits": [
+    { "screen": 7, "destination": 459, "secondary": false }
+  ])"), std::string::npos);
+
+    const auto empty_exits{ MWL::read(FixturePath("levels/level 000.mwl")).summary() };
+    EXPECT_EQ(empty_exits.music, 7);
+    EXPECT_NE(empty_exits.toJSON().find(R"("screen_exits": [])"), std::string::npos);
+}
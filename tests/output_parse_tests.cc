@@ -0,0 +1,110 @@
+#include <fstream>
+
+#include <gtest/gtest.h>
+
+#include <output_parse.h>
+
+
+using namespace lunar_magic_wrapper;
+
+namespace {
+    fs::path FixturePath(const fs::path& rel_path) {
+        return fs::path{ __FILE__ }.parent_path() / rel_path;
+    }
+
+    std::vector<std::string> ReadLines(const fs::path& path) {
+        std::ifstream file(path);
+        std::vector<std::string> lines{};
+        std::string line;
+        while (std::getline(file, line)) {
+            lines.push_back(line);
+        }
+
+        return lines;
+    }
+}
+
+TEST(OutputParseTest, ParsesLevelImportLines) {
+    const auto lines{ ReadLines(FixturePath("logs/level_imported.log")) };
+
+    EXPECT_EQ(output_parse::levelNumber(lines[1]), 0x105);
+    EXPECT_EQ(output_parse::filePath(lines[1]), fs::path("level.mwl"));
+    EXPECT_EQ(output_parse::levelNumber(lines[2]), 0x105);
+    EXPECT_EQ(output_parse::filePath(lines[2]), std::nullopt);
+    EXPECT_EQ(output_parse::levelNumbers(lines), std::vector<uint16_t>({ 0x105, 0x105 }));
+}
+
+TEST(OutputParseTest, ParsesLevelExportLines) {
+    const auto lines{ ReadLines(FixturePath("logs/levels_exported.log")) };
+
+    EXPECT_EQ(output_parse::filePath(lines[1]), fs::path("level 105.mwl"));
+    EXPECT_EQ(output_parse::levelNumbers(lines), std::vector<uint16_t>({ 0x105, 0x106 }));
+    EXPECT_EQ(output_parse::count(lines[3]), 2u);
+    EXPECT_EQ(output_parse::count(lines[1]), std::nullopt);
+}
+
+TEST(OutputParseTest, ParsesBytesWrittenLines) {
+    const auto lines{ ReadLines(FixturePath("logs/bytes_written.log")) };
+
+    EXPECT_EQ(output_parse::bytesWritten(lines[2]), 32768u);
+    EXPECT_EQ(output_parse::bytesWritten(lines[4]), 2018u);
+    EXPECT_EQ(output_parse::bytesWritten(lines[1]), std::nullopt);
+    EXPECT_EQ(output_parse::totalBytesWritten(lines), 32768u + 2018u);
+    EXPECT_EQ(output_parse::totalBytesWritten({ lines[0], lines[1] }), std::nullopt);
+    EXPECT_EQ(output_parse::bytesWritten("Done, 0x800 bytes written"), std::nullopt);
+    EXPECT_EQ(output_parse::bytesWritten("Done, 99999999999999999999999 bytes written"), std::nullopt);
+    EXPECT_EQ(output_parse::count("99999999999999999999999 levels exported."), std::nullopt);
+}
+
+TEST(OutputParseTest, JoinsAndSearchesLines) {
+    const auto lines{ ReadLines(FixturePath("logs/levels_exported.log")) };
+
+    EXPECT_EQ(output_parse::joined(lines), "Lunar Magic 3.40\nExporting level 105 to level 105.mwl...\n"
+                                           "Exporting level 106 to level 106.mwl...\n2 levels exported.");
+    EXPECT_EQ(output_parse::joined({ "one" }), "one");
+    EXPECT_EQ(output_parse::joined({ "", "" }), "\n");
+
+    EXPECT_EQ(output_parse::findLine(lines, "level 106"), "Exporting level 106 to level 106.mwl...");
+    EXPECT_EQ(output_parse::findLine(lines, "Exporting"), lines[1]);
+    EXPECT_EQ(output_parse::findLine(lines, "LEVEL 106"), std::nullopt);
+    EXPECT_TRUE(output_parse::containsLine(lines, "exported."));
+    EXPECT_FALSE(output_parse::containsLine(lines, "imported"));
+
+    EXPECT_EQ(output_parse::joined({}), "");
+    EXPECT_EQ(output_parse::findLine({}, ""), std::nullopt);
+    EXPECT_FALSE(output_parse::containsLine({}, ""));
+}
+
+TEST(OutputParseTest, IgnoresLinesWithoutInformation) {
+    const auto lines{ ReadLines(FixturePath("logs/rom_too_small.log")) };
+
+    EXPECT_TRUE(output_parse::levelNumbers(lines).empty());
+    EXPECT_EQ(output_parse::filePath(lines[2]), std::nullopt);
+    EXPECT_EQ(output_parse::count(lines[0]), std::nullopt);
+    EXPECT_EQ(output_parse::levelNumber("Level 1234 imported."), std::nullopt);
+}
+
+TEST(OutputParseTest, ClassifiesLines) {
+    const auto lines{ ReadLines(FixturePath("logs/mixed.log")) };
+    const auto classified{ output_parse::classify(lines) };
+
+    ASSERT_EQ(classified.size(), lines.size());
+    std::vector<OutputLineKind> kinds{};
+    for (const auto& line : classified) {
+        kinds.push_back(line.kind);
+    }
+    EXPECT_EQ(kinds, std::vector<OutputLineKind>({
+        OutputLineKind::INFO, OutputLineKind::PROGRESS, OutputLineKind::PROGRESS, OutputLineKind::WARNING,
+        OutputLineKind::INFO, OutputLineKind::ERROR, OutputLineKind::UNKNOWN, OutputLineKind::INFO
+    }));
+    EXPECT_EQ(classified[5], (ClassifiedLine{ OutputLineKind::ERROR, "Error: Level 106 could not be inserted." }));
+
+    ClassifierPatterns patterns{};
+    patterns.error.push_back("could not");
+    patterns.info = { "checked" };
+    const auto custom{ output_parse::classify({ "Level 106 could not be inserted.", "ROM header checked", "Done." },
+                                              patterns) };
+    EXPECT_EQ(custom[0].kind, OutputLineKind::ERROR);
+    EXPECT_EQ(custom[1].kind, OutputLineKind::INFO);
+    EXPECT_EQ(custom[2].kind, OutputLineKind::UNKNOWN);
+}
@@ -0,0 +1,38 @@
+#include <gtest/gtest.h>
+
+#include <output_testing.h>
+
+
+using namespace lunar_magic_wrapper;
+
+TEST(OutputTestingTest, MatchesGlobsAndRegexes) {
+    const std::vector<std::string> output{
+        "Lunar Magic 3.40",
+        "Exporting level 105 to level 105.mwl...",
+        "12 levels exported."
+    };
+
+    EXPECT_EQ(output_testing::outputDiff(output, { "Lunar Magic ?.??", "Exporting level * to *.mwl...",
+                                                   R"(re:\d+ levels exported\.)" }), "");
+    EXPECT_NO_THROW(output_testing::assertOutputMatches(output, { "*", "*", "*" }));
+
+    EXPECT_TRUE(output_testing::lineMatches("", "*"));
+    EXPECT_FALSE(output_testing::lineMatches("level 105", "level ??"));
+    EXPECT_FALSE(output_testing::lineMatches("12 levels exported.", "re:levels"));
+}
+
+TEST(OutputTestingTest, ReportsMismatchedMissingAndUnexpectedLines) {
+    EXPECT_EQ(output_testing::outputDiff({ "Importing level 105", "Done" }, { "Importing level 106", "Done", "*" }),
+              "line 1: expected 'Importing level 106', got 'Importing level 105'\n"
+              "line 3: missing, expected '*'\n");
+    EXPECT_EQ(output_testing::outputDiff({ "Done", "Error: locked" }, { "Done" }),
+              "line 2: unexpected 'Error: locked'\n");
+
+    try {
+        output_testing::assertOutputMatches({ "Error" }, { "Done" });
+        FAIL() << "expected OutputMismatchException";
+    } catch (const OutputMismatchException& e) {
+        EXPECT_EQ(std::string(e.what()),
+                  "Output doesn't match the expected patterns:\nline 1: expected 'Done', got 'Error'\n");
+    }
+}
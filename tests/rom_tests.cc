@@ -0,0 +1,348 @@
+#include <fstream>
+
+#include <gtest/gtest.h>
+
+#include <rom.h>
+#include <mwl.h>
+
+
+using namespace lunar_magic_wrapper;
+
+namespace {
+    constexpr size_t ONE_MB{ 0x100000 };
+
+    fs::path FixturePath(const fs::path& rel_path) {
+        return fs::path{ __FILE__ }.parent_path() / rel_path;
+    }
+
+    void WriteLong(std::vector<uint8_t>& bytes, uint32_t snes_address, uint32_t value) {
+        const auto pc{ ROM::snesToPc(snes_address) };
+        bytes[pc] = value & 0xFF;
+        bytes[pc + 1] = (value >> 8) & 0xFF;
+        bytes[pc + 2] = (value >> 16) & 0xFF;
+    }
+
+    // a header that passes every check, checksum and complement don't have to match the contents
+    void WriteHeader(std::vector<uint8_t>& bytes, size_t header, uint8_t map_mode) {
+        bytes[header + 0x15] = map_mode;
+        bytes[header + 0x1C] = 0xCB;
+        bytes[header + 0x1D] = 0xED;
+        bytes[header + 0x1E] = 0x34;
+        bytes[header + 0x1F] = 0x12;
+        bytes[header + 0x3D] = 0x80;
+    }
+}
+
+TEST(ROMTest, AcceptsAllROMExtensions) {
+    for (const auto extension : ROM::EXTENSIONS) {
+        EXPECT_TRUE(ROM::hasROMExtension(fs::path("hack") += extension)) << extension;
+    }
+
+    EXPECT_TRUE(ROM::hasROMExtension("C:/hacks/My Hack.SFC"));
+    EXPECT_TRUE(ROM::hasROMExtension("rom.smc"));
+}
+
+TEST(ROMTest, RejectsNonROMExtensions) {
+    EXPECT_FALSE(ROM::hasROMExtension("level.mwl"));
+    EXPECT_FALSE(ROM::hasROMExtension("rom"));
+    EXPECT_FALSE(ROM::hasROMExtension("rom.smc.bak"));
+}
+
+TEST(ROMTest, ConvertsLoROMAddresses) {
+    EXPECT_EQ(ROM::snesToPc(0x008000), 0x000000u);
+    EXPECT_EQ(ROM::snesToPc(0x018000), 0x008000u);
+    EXPECT_EQ(ROM::snesToPc(0x0FF600), 0x07F600u);
+}
+
+TEST(ROMTest, StripsCopierHeader) {
+    std::vector<uint8_t> bytes(ONE_MB + ROM::COPIER_HEADER_SIZE, 0);
+    bytes[ROM::COPIER_HEADER_SIZE] = 0x42;
+
+    const ROM rom{ bytes };
+
+    EXPECT_TRUE(rom.hasCopierHeader());
+    EXPECT_EQ(rom.size(), ONE_MB);
+    EXPECT_EQ(rom.byteAt(0x008000), 0x42);
+}
+
+TEST(ROMTest, ListsUsedExGFXSlots) {
+    std::vector<uint8_t> bytes(ONE_MB, 0xFF);
+
+    WriteLong(bytes, ROM::EXGFX_80_TABLE + (0x85 - 0x80) * 3, 0x108000);
+    WriteLong(bytes, ROM::EXGFX_80_TABLE + (0x80 - 0x80) * 3, 0x118000);
+    WriteLong(bytes, ROM::EXGFX_100_TABLE_POINTER, 0x128000);
+    WriteLong(bytes, 0x128000 + (0x123 - 0x100) * 3, 0x138000);
+
+    const auto slots{ ROM(bytes).usedExGFXSlots() };
+
+    ASSERT_EQ(slots.size(), 3u);
+    EXPECT_EQ(slots[0].number, 0x80u);
+    EXPECT_EQ(slots[0].snes_address, 0x118000u);
+    EXPECT_EQ(slots[1].number, 0x85u);
+    EXPECT_EQ(slots[2].number, 0x123u);
+    EXPECT_EQ(slots[2].snes_address, 0x138000u);
+}
+
+TEST(ROMTest, ListsNoExGFXSlotsForUnmodifiedTables) {
+    const std::vector<uint8_t> bytes(ONE_MB, 0xFF);
+
+    EXPECT_TRUE(ROM(bytes).usedExGFXSlots().empty());
+}
+
+TEST(ROMTest, ListsNoExGFXSlotsForUnexpandedROM) {
+    // whatever is where the tables would be doesn't count before Lunar Magic has expanded the ROM
+    std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0xFF);
+    WriteLong(bytes, ROM::EXGFX_80_TABLE, 0x008000);
+    WriteLong(bytes, ROM::EXGFX_100_TABLE_POINTER, 0x018000);
+    WriteLong(bytes, 0x018000, 0x028000);
+
+    EXPECT_TRUE(ROM(bytes).usedExGFXSlots().empty());
+    EXPECT_EQ(ROM(bytes).graphicsUsage().free_exgfx.size(), 0x1000u - 0x80u);
+}
+
+TEST(ROMTest, CountsOnlyLongZeroRunsInExpandedAreaAsFree) {
+    std::vector<uint8_t> bytes(ONE_MB, 0x00);
+    std::fill(bytes.begin() + ROM::ORIGINAL_ROM_SIZE, bytes.begin() + ROM::ORIGINAL_ROM_SIZE + 0x1000, 0xAA);
+    bytes[ROM::ORIGINAL_ROM_SIZE + 0x1010] = 0xAA;
+
+    const ROM rom{ bytes };
+
+    EXPECT_EQ(rom.freeBytes(), ONE_MB - ROM::ORIGINAL_ROM_SIZE - 0x1000 - 0x10 - 1);
+    EXPECT_EQ(rom.usedBytes(), ROM::ORIGINAL_ROM_SIZE + 0x1000 + 0x10 + 1);
+}
+
+TEST(ROMTest, ReportsFreeSpace) {
+    std::vector<uint8_t> bytes(ONE_MB, 0x00);
+    std::fill(bytes.begin() + ROM::ORIGINAL_ROM_SIZE, bytes.begin() + ROM::ORIGINAL_ROM_SIZE + 0x40000, 0xAA);
+
+    const auto space{ ROM(bytes).freeSpace() };
+
+    EXPECT_EQ(space.total_bytes, ONE_MB);
+    EXPECT_EQ(space.used_bytes, ROM::ORIGINAL_ROM_SIZE + 0x40000);
+    EXPECT_EQ(space.free_bytes, ONE_MB - ROM::ORIGINAL_ROM_SIZE - 0x40000);
+    EXPECT_DOUBLE_EQ(space.percentUsed(), 75.0);
+
+    // nothing past the original size to use
+    EXPECT_DOUBLE_EQ(ROM(std::vector<uint8_t>(ROM::ORIGINAL_ROM_SIZE, 0x00)).freeSpace().percentUsed(), 100.0);
+}
+
+TEST(ROMTest, ReportsGraphicsUsage) {
+    std::vector<uint8_t> bytes(ONE_MB, 0xFF);
+
+    // GFX00 and GFX05 point into the ROM, everything else at $FFFFFF
+    for (const uint16_t number : { 0x05, 0x00 }) {
+        bytes[ROM::snesToPc(ROM::GFX_POINTER_TABLE_LOW + number)] = 0x00;
+        bytes[ROM::snesToPc(ROM::GFX_POINTER_TABLE_HIGH + number)] = 0x80;
+        bytes[ROM::snesToPc(ROM::GFX_POINTER_TABLE_BANK + number)] = 0x08;
+    }
+    WriteLong(bytes, ROM::EXGFX_80_TABLE + (0x81 - 0x80) * 3, 0x108000);
+
+    const auto usage{ ROM(bytes).graphicsUsage() };
+
+    EXPECT_EQ(usage.used_gfx, std::vector<uint16_t>({ 0x00, 0x05, 0x32, 0x33 }));
+    EXPECT_EQ(usage.used_exgfx, std::vector<uint16_t>({ 0x81 }));
+    EXPECT_EQ(usage.usedGFXCount(), 4u);
+    EXPECT_EQ(usage.usedExGFXCount(), 1u);
+    EXPECT_EQ(usage.free_exgfx.size(), 0xFFFu - 0x80u);
+    EXPECT_EQ(usage.free_exgfx.front(), 0x80u);
+    EXPECT_EQ(usage.free_exgfx[1], 0x82u);
+}
+
+TEST(ROMTest, DetectsLoROMHeader) {
+    std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x20);
+    EXPECT_EQ(ROM(bytes).mappingMode(), MappingMode::LOROM);
+
+    // FastROM bit and copier header don't matter
+    bytes[ROM::LOROM_HEADER + 0x15] = 0x30;
+    bytes.insert(bytes.begin(), ROM::COPIER_HEADER_SIZE, 0);
+    EXPECT_EQ(ROM(bytes).mappingMode(), MappingMode::LOROM);
+}
+
+TEST(ROMTest, DetectsHiROMHeader) {
+    std::vector<uint8_t> bytes(ONE_MB, 0);
+    WriteHeader(bytes, ROM::HIROM_HEADER, 0x21);
+    EXPECT_EQ(ROM(bytes).mappingMode(), MappingMode::HIROM);
+
+    bytes[ROM::HIROM_HEADER + 0x15] = 0x35;
+    EXPECT_EQ(ROM(bytes).mappingMode(), MappingMode::EXHIROM);
+}
+
+TEST(ROMTest, DetectsSA1Header) {
+    std::vector<uint8_t> bytes(ONE_MB, 0);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x23);
+    EXPECT_EQ(ROM(bytes).mappingMode(), MappingMode::SA1);
+}
+
+TEST(ROMTest, ReportsUnknownMappingWithoutValidHeader) {
+    EXPECT_EQ(ROM(std::vector<uint8_t>(ONE_MB, 0)).mappingMode(), MappingMode::UNKNOWN);
+    EXPECT_EQ(ROM(std::vector<uint8_t>(0x1000, 0)).mappingMode(), MappingMode::UNKNOWN);
+}
+
+TEST(ROMTest, ComputesChecksumIndependentOfStoredOne) {
+    std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x20);
+    const auto checksum{ ROM(bytes).computeChecksum() };
+
+    // map mode, reset vector and the 0000/FFFF checksum/complement
+    EXPECT_EQ(checksum, 0x20 + 0x80 + 0x1FE);
+
+    bytes[ROM::LOROM_HEADER + 0x1E] = 0xAA;
+    EXPECT_EQ(ROM(bytes).computeChecksum(), checksum);
+}
+
+TEST(ROMTest, MirrorsRemainderOfNonPowerOfTwoSizesInChecksum) {
+    std::vector<uint8_t> bytes(3 * ONE_MB, 0);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x20);
+    const auto checksum{ ROM(bytes).computeChecksum() };
+
+    bytes[0x10] = 1;
+    EXPECT_EQ(ROM(bytes).computeChecksum(), checksum + 1);
+
+    bytes[2 * ONE_MB + 0x10] = 1;
+    EXPECT_EQ(ROM(bytes).computeChecksum(), checksum + 3);
+}
+
+TEST(ROMTest, FixesChecksumInFile) {
+    std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0x11);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x20);
+    bytes.insert(bytes.begin(), ROM::COPIER_HEADER_SIZE, 0);
+    std::ofstream("checksum.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                          static_cast<std::streamsize>(bytes.size()));
+
+    const auto checksum{ ROM::fixChecksum("checksum.smc") };
+    const auto rom{ ROM::fromFile("checksum.smc") };
+
+    EXPECT_EQ(rom.size(), ROM::ORIGINAL_ROM_SIZE);
+    EXPECT_EQ(rom.headerChecksum(), checksum);
+    EXPECT_EQ(rom.computeChecksum(), checksum);
+    EXPECT_EQ(rom.mappingMode(), MappingMode::LOROM);
+    fs::remove("checksum.smc");
+}
+
+TEST(ROMTest, ReadsInfoFromLoROMHeader) {
+    std::vector<uint8_t> bytes(ONE_MB, 0);
+    WriteHeader(bytes, ROM::LOROM_HEADER, 0x20);
+    const std::string title{ "SUPER MARIOWORLD     " };
+    std::copy(title.begin(), title.end(), bytes.begin() + ROM::LOROM_HEADER);
+    bytes[ROM::LOROM_HEADER + 0x17] = 0x09;
+    bytes[ROM::LOROM_HEADER + 0x19] = 0x01;
+    bytes[ROM::LOROM_HEADER + 0x1B] = 0x00;
+
+    const auto info{ ROM(bytes).info() };
+
+    EXPECT_EQ(info.title, "SUPER MARIOWORLD");
+    EXPECT_EQ(info.region, 0x01);
+    EXPECT_EQ(info.version, 0x00);
+    EXPECT_EQ(info.rom_size, 0x09);
+    EXPECT_EQ(info.mapping_mode, MappingMode::LOROM);
+}
+
+TEST(ROMTest, ReadsInfoFromHiROMHeaderWithKatakanaTitle) {
+    std::vector<uint8_t> bytes(ONE_MB, 0);
+    WriteHeader(bytes, ROM::HIROM_HEADER, 0x21);
+    // "HACK " followed by half-width "ｶﾀ"
+    const std::vector<uint8_t> title{ 'H', 'A', 'C', 'K', ' ', 0xB6, 0xC0 };
+    std::copy(title.begin(), title.end(), bytes.begin() + ROM::HIROM_HEADER);
+    std::fill_n(bytes.begin() + ROM::HIROM_HEADER + title.size(), 21 - title.size(), ' ');
+    bytes[ROM::HIROM_HEADER + 0x17] = 0x0A;
+    bytes[ROM::HIROM_HEADER + 0x19] = 0x00;
+    bytes[ROM::HIROM_HEADER + 0x1B] = 0x02;
+
+    const auto info{ ROM(bytes).info() };
+
+    EXPECT_EQ(info.title, "HACK \xEF\xBD\xB6\xEF\xBE\x80");
+    EXPECT_EQ(info.region, 0x00);
+    EXPECT_EQ(info.version, 0x02);
+    EXPECT_EQ(info.rom_size, 0x0A);
+    EXPECT_EQ(info.mapping_mode, MappingMode::HIROM);
+}
+
+TEST(ROMTest, ListsSecondaryExits) {
+    std::vector<uint8_t> bytes(ONE_MB, 0xFF);
+
+    // secondary exit 10 leads to level 105
+    bytes[ROM::snesToPc(ROM::SECONDARY_EXIT_LEVEL_TABLE + 0x10)] = 0x05;
+    bytes[ROM::snesToPc(ROM::SECONDARY_EXIT_SETTINGS_TABLE + 0x10)] = 0x08;
+
+    // an object, a secondary exit to 10 on screen 2, a level exit to 20 on screen 3 and a secondary exit to 123
+    WriteLong(bytes, ROM::LAYER1_POINTER_TABLE + 0x105 * 3, 0x108000);
+    const std::vector<uint8_t> level_105{
+        0x00, 0x00, 0x00, 0x00, 0x00,
+        0x2C, 0x3C, 0x1F,
+        0x02, 0x02, 0x00, 0x10,
+        0x03, 0x00, 0x00, 0x20,
+        0x04, 0x03, 0x00, 0x23,
+        0xFF
+    };
+    std::copy(level_105.begin(), level_105.end(), bytes.begin() + ROM::snesToPc(0x108000));
+
+    WriteLong(bytes, ROM::LAYER1_POINTER_TABLE + 0x003 * 3, 0x118000);
+    const std::vector<uint8_t> level_003{ 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x10, 0xFF };
+    std::copy(level_003.begin(), level_003.end(), bytes.begin() + ROM::snesToPc(0x118000));
+
+    EXPECT_EQ(ROM(bytes).secondaryExits(), std::vector<SecondaryExit>({
+        { 0x003, 0, 0x10, 0x105 },
+        { 0x105, 2, 0x10, 0x105 },
+        { 0x105, 4, 0x123, std::nullopt }
+    }));
+}
+
+TEST(ROMTest, ReadsLevelScreenCount) {
+    std::vector<uint8_t> bytes(ONE_MB, 0xFF);
+
+    // level 105 with the primary header of the fixture level
+    const auto header{ MWL::read(FixturePath("level.mwl")).primaryHeader() };
+    WriteLong(bytes, ROM::LAYER1_POINTER_TABLE + 0x105 * 3, 0x108000);
+    std::copy(header.begin(), header.end(), bytes.begin() + ROM::snesToPc(0x108000));
+
+    const ROM rom{ bytes };
+
+    EXPECT_EQ(rom.levelScreenCount(0x105), 20);
+    EXPECT_THROW((void)rom.levelScreenCount(0x106), ROMException);
+}
+
+TEST(ROMTest, WritesAndAppliesChanges) {
+    std::vector<uint8_t> original(ONE_MB / 2, 0x00);
+    auto changed{ original };
+    std::fill(changed.begin() + 0x1000, changed.begin() + 0x1020, 0xAB);
+    changed[0x2000] = 0x01;
+    changed.resize(ONE_MB, 0x00);
+    changed.back() = 0xFF;
+
+    const auto changes{ ROM(changed).changesFrom(ROM(original)) };
+    ASSERT_EQ(changes.size(), 3u);
+    EXPECT_EQ(changes[0], (ROMChange{ 0x1000, std::vector<uint8_t>(0x20, 0xAB) }));
+    EXPECT_EQ(changes[1], (ROMChange{ 0x2000, { 0x01 } }));
+    EXPECT_EQ(changes[2].offset, 0x80000u);
+    EXPECT_EQ(changes[2].bytes.size(), 0x80000u);
+
+    ROM::writeChanges("rom_changes.txt", changes);
+    EXPECT_EQ(ROM::readChanges("rom_changes.txt"), changes);
+
+    // a copier header stays in front of the changes
+    std::vector<uint8_t> headered(ROM::COPIER_HEADER_SIZE, 0x11);
+    headered.insert(headered.end(), original.begin(), original.end());
+    std::ofstream("changed_rom.smc", std::ios::binary).write(reinterpret_cast<const char*>(headered.data()),
+                                                              static_cast<std::streamsize>(headered.size()));
+    ROM::applyChanges("changed_rom.smc", ROM::readChanges("rom_changes.txt"));
+
+    const auto applied{ ROM::fromFile("changed_rom.smc") };
+    EXPECT_TRUE(applied.hasCopierHeader());
+    EXPECT_EQ(applied.bytes(), changed);
+
+    fs::remove("rom_changes.txt");
+    fs::remove("changed_rom.smc");
+}
+
+TEST(ROMTest, RejectsMalformedChanges) {
+    std::ofstream("rom_changes.txt") << "# from the clean ROM\n\n001000: 43 52\n001002: 45\n";
+    EXPECT_EQ(ROM::readChanges("rom_changes.txt"), std::vector<ROMChange>({ { 0x1000, { 0x43, 0x52, 0x45 } } }));
+
+    for (const auto* line : { "001000 43 52", "001000:", "1000000: 43", "001000: 4", "001000: 432", "0010G0: 43" }) {
+        std::ofstream("rom_changes.txt") << line << '\n';
+        EXPECT_THROW(ROM::readChanges("rom_changes.txt"), ROMException) << line;
+    }
+
+    fs::remove("rom_changes.txt");
+}
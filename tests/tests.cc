@@ -1,4 +1,13 @@
 #include <filesystem>
+#include <thread>
+#include <sstream>
+#include <chrono>
+#include <regex>
+
+#ifndef _WIN32
+#include <csignal>
+#include <sys/resource.h>
+#endif
 
 #include <gtest/gtest.h>
 
@@ -9,6 +18,45 @@ namespace fs = std::filesystem;
 
 using namespace lunar_magic_wrapper;
 
+namespace {
+    fs::path FixturePath(const fs::path& rel_path) {
+        return fs::path{ __FILE__ }.parent_path() / rel_path;
+    }
+
+    std::vector<std::string> ReadLines(const fs::path& path) {
+        std::ifstream file(path);
+        std::vector<std::string> lines{};
+        std::string line;
+        while (std::getline(file, line)) {
+            lines.push_back(line);
+        }
+
+        return lines;
+    }
+
+    class TestableWrapper : public LunarMagicWrapper {
+    public:
+        using LunarMagicWrapper::LunarMagicWrapper;
+        using LunarMagicWrapper::throwIfGraphicsFolderMissing;
+        using LunarMagicWrapper::throwIfKnownFailure;
+        using LunarMagicWrapper::teeLog;
+        using LunarMagicWrapper::buildCommand;
+        using LunarMagicWrapper::directArguments;
+        using LunarMagicWrapper::findGraphicsFolder;
+        using LunarMagicWrapper::extractArchive;
+        using LunarMagicWrapper::createArchive;
+        using LunarMagicWrapper::readFileVersion;
+        using LunarMagicWrapper::inputPath;
+        using LunarMagicWrapper::outputPath;
+        using LunarMagicWrapper::placeExportedLevels;
+        using LunarMagicWrapper::makeTempDirectory;
+        using LunarMagicWrapper::runDirectly;
+#ifdef _WIN32
+        using LunarMagicWrapper::priorityCreationFlags;
+#endif
+    };
+}
+
 class LunarMagicTest : public testing::Test {
 private:
     constexpr static auto LM_PATH{ "lunar_magic.exe" };
@@ -108,6 +156,26 @@ TEST_F(LunarMagicTest, ImportingAllGraphicsWorks) {
     EXPECT_NO_THROW(lm->importAllGraphics(rom));
 }
 
+TEST_F(LunarMagicTest, InsertingExGFXWorks) {
+    EXPECT_NO_THROW(lm->insertExGFX(rom, MakePath("Graphics/GFX00.bin"), 0x80));
+}
+
+TEST_F(LunarMagicTest, ExportingProjectArchiveWorks) {
+    fs::remove_all("project_test");
+
+    const auto results{ lm->exportProjectArchive(rom, "project.zip") };
+    TestableWrapper::extractArchive("project.zip", "project_test");
+
+    EXPECT_EQ(results.size(), 5u);
+    EXPECT_TRUE(fs::exists("project_test/manifest.json"));
+    EXPECT_TRUE(fs::exists("project_test/Graphics/GFX00.bin"));
+    EXPECT_TRUE(fs::exists("project_test/Levels/level 105.mwl"));
+    EXPECT_TRUE(fs::exists("project_test/all.map16"));
+
+    fs::remove_all("project_test");
+    fs::remove("project.zip");
+}
+
 TEST_F(LunarMagicTest, ExportingLevelWorks) {
     EXPECT_NO_THROW(lm->exportLevel(rom, out_level, 0x105));
 }
@@ -120,6 +188,14 @@ TEST_F(LunarMagicTest, ImportingLevelSpecifiedWorks) {
     EXPECT_NO_THROW(lm->importLevel(rom, in_level, 0x106));
 }
 
+TEST_F(LunarMagicTest, ImportingLevelWithOutcomeWorks) {
+    const auto outcome{ lm->importLevelWithOutcome(rom, in_level, MakePath("rom.smc"), 0x106) };
+
+    EXPECT_TRUE(outcome.result.succeeded);
+    EXPECT_EQ(outcome.status, LevelImportStatus::CREATED);
+    EXPECT_EQ(outcome.level_number, 0x106);
+}
+
 TEST_F(LunarMagicTest, ImportingPartialMap16Works) {
     EXPECT_NO_THROW(lm->importMap16(rom, in_partial_map16, 0x105));
 }
@@ -156,6 +232,13 @@ TEST_F(LunarMagicTest, ExportingMultipleLevelsWithOptionsWorks) {
     EXPECT_NO_THROW(lm->exportMultipleLevels(rom, out_levels, "level ", LevelExportOption::MODIFIED_ONLY));
 }
 
+TEST_F(LunarMagicTest, ExportingMultipleLevelsWithTemplateWorks) {
+    EXPECT_NO_THROW(lm->exportMultipleLevels(rom, [&](uint16_t level_number) {
+        return out_levels / fmt::format("0x{:03X}.mwl", level_number);
+    }));
+    EXPECT_TRUE(fs::exists(out_levels / "0x105.mwl"));
+}
+
 TEST_F(LunarMagicTest, ImportingMultipleLevelsWorks) {
     EXPECT_NO_THROW(lm->importMultipleLevels(rom, in_levels));
 }
@@ -178,6 +261,18 @@ TEST_F(LunarMagicTest, ChangingCompressionWorks) {
     }
 }
 
+TEST_F(LunarMagicTest, ChangingCompressionWithReportWorks) {
+    lm->expandROM(rom, ROMSize::_2MB);
+
+    const auto report{ lm->changeCompressionWithReport(rom, CompressionOption::LC_LZ3) };
+
+    EXPECT_TRUE(report.result.succeeded);
+    EXPECT_EQ(report.format, CompressionOption::LC_LZ3);
+    EXPECT_GT(report.before_bytes, 0u);
+    EXPECT_GT(report.after_bytes, 0u);
+    EXPECT_EQ(report.delta(), static_cast<std::ptrdiff_t>(report.after_bytes - report.before_bytes));
+}
+
 TEST_F(LunarMagicTest, TransferingGlobalEaxanimationWorks) {
     EXPECT_NO_THROW(lm->transferGlobalExanimation(rom, out_rom));
 }
@@ -193,3 +288,2542 @@ TEST_F(LunarMagicTest, TransferingTitleScreenWorks) {
 TEST_F(LunarMagicTest, TransferingCreditsWorks) {
     EXPECT_NO_THROW(lm->transferCredits(rom, out_rom));
 }
+
+TEST(LunarMagicWrapperTest, WithROMBytesReturnsModifiedBytes) {
+    fs::path used_path{};
+
+    const auto bytes{ LunarMagicWrapper::withROMBytes({ 0x01, 0x02 }, [&](const fs::path& rom_path) {
+        used_path = rom_path;
+        std::ofstream rom_file(rom_path, std::ios::binary | std::ios::app);
+        rom_file.put(0x03);
+    }) };
+
+    EXPECT_EQ(bytes, std::vector<uint8_t>({ 0x01, 0x02, 0x03 }));
+    EXPECT_FALSE(fs::exists(used_path.parent_path()));
+}
+
+TEST(LunarMagicWrapperTest, WithROMBytesCleansUpOnFailure) {
+    fs::path used_path{};
+
+    EXPECT_THROW(LunarMagicWrapper::withROMBytes({ 0x01 }, [&](const fs::path& rom_path) {
+        used_path = rom_path;
+        throw std::runtime_error("operation failed");
+    }), std::runtime_error);
+
+    EXPECT_FALSE(fs::exists(used_path.parent_path()));
+}
+
+#ifndef _WIN32
+// stands in for the editor, since the session doesn't care what it's running
+TEST(LunarMagicSessionTest, WaitsForAndKillsProcess) {
+    const LunarMagicWrapper sleeper{ "/bin/sleep" };
+
+    auto short_session{ sleeper.open("0") };
+    EXPECT_EQ(short_session.wait(), 0);
+    EXPECT_EQ(short_session.tryWait(), 0);
+
+    auto long_session{ sleeper.open("10") };
+    EXPECT_EQ(long_session.tryWait(), std::nullopt);
+    long_session.kill();
+    EXPECT_TRUE(long_session.tryWait().has_value());
+}
+
+TEST(LunarMagicSessionTest, MovedFromSessionOwnsNothing) {
+    const LunarMagicWrapper sleeper{ "/bin/sleep" };
+
+    auto session{ sleeper.open("10") };
+    auto moved{ std::move(session) };
+
+    // would be kill(-1)/waitpid(-1) without the guard
+    session.kill();
+    EXPECT_EQ(session.wait(), LunarMagicSession::MOVED_FROM_EXIT_CODE);
+    EXPECT_EQ(session.tryWait(), LunarMagicSession::MOVED_FROM_EXIT_CODE);
+    EXPECT_EQ(moved.tryWait(), std::nullopt);
+
+    // the replaced session is killed like it would be on destruction
+    moved.killOnDestruction(true);
+    moved = sleeper.open("0");
+    EXPECT_EQ(moved.wait(), 0);
+}
+
+TEST(LunarMagicSessionTest, ReportsFailureToStart) {
+    std::ofstream("not_executable.sh") << "#!/bin/sh\n";
+    fs::permissions("not_executable.sh", fs::perms::owner_read | fs::perms::owner_write);
+
+    const LunarMagicWrapper missing{ fs::absolute("missing_lunar_magic") };
+    try {
+        const auto session{ missing.open("rom.smc") };
+        FAIL() << "expected LaunchException";
+    } catch (const LaunchException& e) {
+        EXPECT_EQ(e.code(), std::errc::no_such_file_or_directory);
+    }
+
+    const LunarMagicWrapper not_executable{ fs::absolute("not_executable.sh") };
+    EXPECT_THROW(static_cast<void>(not_executable.open("rom.smc")), LaunchException);
+
+    fs::remove("not_executable.sh");
+}
+
+// the child only touches the marker if it outlives the kill
+TEST(LunarMagicSessionTest, KillTakesDownChildProcesses) {
+    std::ofstream("spawner.sh") << "#!/bin/sh\n(sleep 1; touch \"$1\") &\nsleep 10\n";
+    fs::permissions("spawner.sh", fs::perms::owner_all);
+    fs::remove("orphan_marker");
+
+    const LunarMagicWrapper spawner{ fs::absolute("spawner.sh") };
+    auto session{ spawner.open("orphan_marker") };
+    std::this_thread::sleep_for(std::chrono::milliseconds(200));
+    session.kill();
+    std::this_thread::sleep_for(std::chrono::milliseconds(1500));
+
+    EXPECT_FALSE(fs::exists("orphan_marker"));
+    fs::remove("spawner.sh");
+    fs::remove("orphan_marker");
+}
+#else
+TEST(LunarMagicSessionTest, KillTakesDownChildProcesses) {
+    std::ofstream("spawner.bat")
+        << "@echo off\r\n"
+        << "start \"\" /b cmd /c \"ping -n 2 127.0.0.1 > nul & echo orphaned> %~1\"\r\n"
+        << "ping -n 10 127.0.0.1 > nul\r\n";
+    fs::remove("orphan_marker");
+
+    const LunarMagicWrapper spawner{ fs::absolute("spawner.bat") };
+    auto session{ spawner.open("orphan_marker") };
+    std::this_thread::sleep_for(std::chrono::milliseconds(200));
+    session.kill();
+    std::this_thread::sleep_for(std::chrono::milliseconds(2500));
+
+    EXPECT_FALSE(fs::exists("orphan_marker"));
+    fs::remove("spawner.bat");
+    fs::remove("orphan_marker");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, DetectsMissingGraphicsFolder) {
+    const TestableWrapper lm{ "lunar_magic.exe" };
+    const Result result{ 1, ReadLines(FixturePath("logs/missing_graphics_folder.log")), {} };
+
+    try {
+        lm.throwIfGraphicsFolderMissing(fs::path("hacks") / "rom.smc", result);
+        FAIL() << "Expected MissingGraphicsFolderException";
+    } catch (const MissingGraphicsFolderException& e) {
+        EXPECT_EQ(e.getExpectedPath(), fs::path("hacks") / "Graphics");
+        EXPECT_FALSE(e.getResult().succeeded);
+    }
+}
+
+TEST(LunarMagicWrapperTest, MissingGraphicsFolderMarkerIsConfigurable) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setOutputMarkers({ .missing_graphics_folder = "no such folder" });
+
+    const Result default_phrase{ 1, ReadLines(FixturePath("logs/missing_graphics_folder.log")), {} };
+    const Result custom_phrase{ 1, {}, { "NO SUCH FOLDER: Graphics" } };
+
+    EXPECT_NO_THROW(lm.throwIfGraphicsFolderMissing("rom.smc", default_phrase));
+    EXPECT_THROW(lm.throwIfGraphicsFolderMissing("rom.smc", custom_phrase), MissingGraphicsFolderException);
+}
+
+TEST(LunarMagicWrapperTest, ClassifiesOutputWithConfiguredPatterns) {
+    LunarMagicWrapper lm{ "lunar_magic.exe" };
+    const std::vector<std::string> lines{ "Lunar Magic 3.40", "Achtung: Level 105 nicht gefunden" };
+
+    EXPECT_EQ(lm.classifyOutput(lines)[1].kind, OutputLineKind::UNKNOWN);
+
+    lm.setClassifierPatterns({ .error = { "nicht gefunden" } });
+    EXPECT_EQ(lm.classifyOutput(lines)[1].kind, OutputLineKind::ERROR);
+    // the other kinds keep their default phrases
+    EXPECT_EQ(lm.classifyOutput(lines)[0].kind, OutputLineKind::INFO);
+}
+
+TEST(LunarMagicWrapperTest, BuildsCommandWithoutPrefixOrSuffix) {
+    const TestableWrapper lm{ "lunar_magic.exe" };
+
+    EXPECT_EQ(lm.buildCommand(R"(-ExportGFX "rom.smc")", "out.log", "err.log"),
+              R"(""lunar_magic.exe" -ExportGFX "rom.smc" > "out.log" 2> "err.log"")");
+}
+
+TEST(LunarMagicWrapperTest, BuildsCommandWithPrefixAndSuffix) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setCommandPrefix({ "sandbox.exe", "--isolated" }).setCommandSuffix({ "extra arg" });
+
+    EXPECT_EQ(lm.buildCommand(R"(-ExportGFX "rom.smc")", "out.log", "err.log"),
+              R"(""sandbox.exe" "--isolated" "lunar_magic.exe" -ExportGFX "rom.smc" "extra arg" > "out.log" 2> "err.log"")");
+}
+
+TEST(LunarMagicWrapperTest, SetsCodePageOnlyOnWindows) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setCodePage(65001);
+
+#ifdef _WIN32
+    EXPECT_EQ(lm.buildCommand(R"(-ExportGFX "rom.smc")", "out.log", "err.log"),
+              R"("chcp 65001 >nul && "lunar_magic.exe" -ExportGFX "rom.smc" > "out.log" 2> "err.log"")");
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "rom.smc")"), std::nullopt);
+#else
+    EXPECT_EQ(lm.buildCommand(R"(-ExportGFX "rom.smc")", "out.log", "err.log"),
+              R"(""lunar_magic.exe" -ExportGFX "rom.smc" > "out.log" 2> "err.log"")");
+#endif
+
+    lm.setCodePage(std::nullopt);
+    EXPECT_EQ(lm.buildCommand(R"(-ExportGFX "rom.smc")", "out.log", "err.log"),
+              R"(""lunar_magic.exe" -ExportGFX "rom.smc" > "out.log" 2> "err.log"")");
+
+    EXPECT_THROW(lm.setCodePage(0), std::invalid_argument);
+}
+
+TEST(LunarMagicWrapperTest, RunsLunarMagicAtConfiguredPriority) {
+#ifdef _WIN32
+    // IDLE_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS
+    EXPECT_EQ(TestableWrapper::priorityCreationFlags(ProcessPriority::IDLE), 0x40u);
+    EXPECT_EQ(TestableWrapper::priorityCreationFlags(ProcessPriority::BELOW_NORMAL), 0x4000u);
+    EXPECT_EQ(TestableWrapper::priorityCreationFlags(ProcessPriority::NORMAL), 0x20u);
+#else
+    std::ofstream("nice_lm.sh") << "#!/bin/sh\nnice\n";
+    fs::permissions("nice_lm.sh", fs::perms::owner_all);
+    const auto own_nice_value{ getpriority(PRIO_PROCESS, 0) };
+
+    LunarMagicWrapper lm{ fs::absolute("nice_lm.sh") };
+    EXPECT_EQ(lm.getProcessPriority(), ProcessPriority::NORMAL);
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, std::vector<std::string>({ std::to_string(own_nice_value) }));
+
+    lm.setProcessPriority(ProcessPriority::BELOW_NORMAL);
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, std::vector<std::string>({ std::to_string(std::max(own_nice_value, 10)) }));
+
+    fs::remove("nice_lm.sh");
+#endif
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, ProcessThatCantBeWaitedOnFails) {
+    // with SIGCHLD ignored, children are reaped right away and waitpid fails with ECHILD
+    const auto previous{ std::signal(SIGCHLD, SIG_IGN) };
+    const auto exit_code{ TestableWrapper::runDirectly({ "true" }, "wait_out.log", "wait_err.log", false,
+                                                       ProcessPriority::NORMAL) };
+    std::signal(SIGCHLD, previous);
+
+    EXPECT_EQ(exit_code, -1);
+
+    fs::remove("wait_out.log");
+    fs::remove("wait_err.log");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, CancellingLeavesNoOrphanedProcesses) {
+    fs::remove("orphan_survived.log");
+    const auto survived_path{ fs::absolute("orphan_survived.log").string() };
+
+    // starts something that outlives the wait for cancellation unless it's killed too
+#ifdef _WIN32
+    const fs::path script{ "orphan_lm.bat" };
+    std::ofstream(script) << "@echo off\r\n"
+                          << "start \"\" /b cmd /c \"ping -n 3 127.0.0.1 >nul && echo survived> \"" << survived_path
+                          << "\"\"\r\n"
+                          << "ping -n 30 127.0.0.1 >nul\r\n";
+
+    LunarMagicWrapper lm{ fs::absolute(script) };
+    lm.setCommandPrefix({ "cmd", "/c" });
+#else
+    const fs::path script{ "orphan_lm.sh" };
+    std::ofstream(script) << "#!/bin/sh\n(sleep 1; echo survived > \"" << survived_path << "\") &\nsleep 10\n";
+    fs::permissions(script, fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute(script) };
+#endif
+
+    std::stop_source cancel{};
+    auto exported{ lm.exportGFXAsync("rom.smc", cancel.get_token()) };
+    std::this_thread::sleep_for(std::chrono::milliseconds(300));
+    cancel.request_stop();
+    EXPECT_THROW(exported.get(), CancelledException);
+
+    std::this_thread::sleep_for(std::chrono::seconds(3));
+    EXPECT_FALSE(fs::exists("orphan_survived.log"));
+
+    fs::remove("orphan_survived.log");
+    fs::remove(script);
+}
+
+TEST(LunarMagicWrapperTest, LocatesGraphicsFoldersNextToROM) {
+    EXPECT_EQ(LunarMagicWrapper::graphicsDirectory("hacks/my hack/hack.smc"), fs::path("hacks/my hack/Graphics"));
+    EXPECT_EQ(LunarMagicWrapper::exGraphicsDirectory("hacks/my hack/hack.smc"), fs::path("hacks/my hack/ExGraphics"));
+    EXPECT_EQ(LunarMagicWrapper::graphicsDirectory("hack.smc"), fs::path("Graphics"));
+
+    fs::remove_all("graphics_rom");
+    fs::create_directories("graphics_rom/Graphics");
+    EXPECT_TRUE(LunarMagicWrapper::graphicsDirectoryExists("graphics_rom/hack.smc"));
+    EXPECT_FALSE(LunarMagicWrapper::exGraphicsDirectoryExists("graphics_rom/hack.smc"));
+    fs::remove_all("graphics_rom");
+}
+
+TEST(LunarMagicWrapperTest, PassesPathsThroughUnlessCanonicalizing) {
+    const TestableWrapper lm{ "lunar_magic.exe" };
+
+    EXPECT_EQ(lm.inputPath("./missing/../rom.smc"), fs::path("./missing/../rom.smc").string());
+}
+
+TEST(LunarMagicWrapperTest, CanonicalizesRelativeAndDottedPaths) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setCanonicalizePaths(true);
+
+    fs::create_directories("canonicalize_test/sub");
+    std::ofstream("canonicalize_test/rom.smc").close();
+
+    const auto expected{ fs::canonical("canonicalize_test/rom.smc").string() };
+    EXPECT_EQ(lm.inputPath("canonicalize_test/rom.smc"), expected);
+    EXPECT_EQ(lm.inputPath("./canonicalize_test/sub/../rom.smc"), expected);
+    EXPECT_EQ(lm.outputPath("canonicalize_test/sub/../level.mwl"),
+              (fs::canonical("canonicalize_test") / "level.mwl").string());
+
+    fs::remove_all("canonicalize_test");
+}
+
+TEST(LunarMagicWrapperTest, CanonicalizingMissingInputThrows) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setCanonicalizePaths(true);
+
+    try {
+        static_cast<void>(lm.inputPath("does_not_exist/rom.smc"));
+        FAIL() << "Expected InvalidPathException";
+    } catch (const InvalidPathException& e) {
+        EXPECT_EQ(e.getPath(), fs::path("does_not_exist/rom.smc"));
+    }
+}
+
+TEST(LunarMagicWrapperTest, NamesOperationsAfterTheirCommandLineFunction) {
+    EXPECT_EQ(LunarMagicWrapper::operationToString(Operation::IMPORT_GFX), "ImportGFX");
+    EXPECT_EQ(LunarMagicWrapper::operationToString(Operation::EXPORT_MULTIPLE_LEVELS), "ExportMultLevels");
+    EXPECT_EQ(LunarMagicWrapper::operationToString(Operation::TRANSFER_GLOBAL_EXANIMATION),
+              "TransferLevelGlobalExAnim");
+}
+
+TEST(LunarMagicWrapperTest, ExceptionsExposeTheResultsOperation) {
+    const LunarMagicWrapperException with_operation{ "failed", { 1, {}, {}, Operation::EXPAND_ROM } };
+    const LunarMagicWrapperException without_operation{ "failed", { 1, {}, {} } };
+
+    EXPECT_EQ(with_operation.getOperation(), Operation::EXPAND_ROM);
+    EXPECT_EQ(without_operation.getOperation(), std::nullopt);
+}
+
+TEST(LunarMagicWrapperTest, ForEachROMCollectsResultsInOrder) {
+    const std::vector<fs::path> roms{ "a.smc", "b.smc", "c.smc", "d.smc" };
+
+    const auto results{ LunarMagicWrapper::forEachROM(roms, 3, [](const fs::path& rom_path) {
+        if (rom_path == "c.smc") {
+            throw std::runtime_error("failed on c");
+        }
+        return Result{ 0, { rom_path.string() }, {} };
+    }) };
+
+    ASSERT_EQ(results.size(), roms.size());
+    for (size_t i{}; i < roms.size(); ++i) {
+        EXPECT_EQ(results[i].rom_path, roms[i]);
+    }
+
+    EXPECT_TRUE(results[0].succeeded());
+    EXPECT_EQ(results[1].result->out, std::vector<std::string>({ "b.smc" }));
+    EXPECT_FALSE(results[2].succeeded());
+    EXPECT_THROW(std::rethrow_exception(results[2].error), std::runtime_error);
+    EXPECT_TRUE(results[3].succeeded());
+}
+
+TEST(LunarMagicWrapperTest, HooksRunAroundEachCall) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    std::vector<std::string> calls{};
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back("run " + call_string);
+        return call_string.find("-ImportGFX") != std::string::npos ? Result{ 1, {}, { "Error" } } : Result{ 0, {}, {} };
+    });
+    lm.beforeEach([&](const std::string& call_string) { calls.push_back("before " + call_string); })
+        .beforeEach([&](const std::string&) { calls.push_back("second before"); })
+        .afterEach([&](const std::string& call_string, const Result& result) {
+            calls.push_back((result.succeeded ? "after " : "failed ") + call_string);
+        });
+
+    lm.exportGFX("rom.smc");
+    EXPECT_THROW(lm.importGFX("rom.smc"), LunarMagicWrapperException);
+
+    EXPECT_EQ(calls, std::vector<std::string>({
+        R"(before -ExportGFX "rom.smc")", "second before", R"(run -ExportGFX "rom.smc")",
+        R"(after -ExportGFX "rom.smc")",
+        R"(before -ImportGFX "rom.smc")", "second before", R"(run -ImportGFX "rom.smc")",
+        R"(failed -ImportGFX "rom.smc")"
+    }));
+}
+
+TEST(LunarMagicWrapperTest, TeeLogContainsOutputOfSpawnedCalls) {
+    fs::remove("tee.log");
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string&) { return Result{ 0, { "Lunar Magic 3.40", "GFX exported." }, {} }; });
+    lm.setTeeLogPath("tee.log");
+
+    lm.exportGFX("rom.smc");
+
+    EXPECT_EQ(ReadLines("tee.log"), std::vector<std::string>({
+        R"(=== -ExportGFX "rom.smc" (exit code 0) ===)", "Lunar Magic 3.40", "GFX exported."
+    }));
+
+    fs::remove("tee.log");
+}
+
+TEST(LunarMagicWrapperTest, FailingOperationsReportTheirOperation) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string&) { return Result{ 1, {}, { "Error" } }; });
+
+    try {
+        lm.importLevel("rom.smc", FixturePath("level.mwl"));
+        FAIL() << "Expected LunarMagicWrapperException";
+    } catch (const LunarMagicWrapperException& e) {
+        EXPECT_EQ(e.getOperation(), Operation::IMPORT_LEVEL);
+    }
+
+    try {
+        lm.importAllMap16("rom.smc", FixturePath("all.map16"));
+        FAIL() << "Expected LunarMagicWrapperException";
+    } catch (const LunarMagicWrapperException& e) {
+        EXPECT_EQ(e.getOperation(), Operation::IMPORT_ALL_MAP16);
+    }
+}
+
+TEST(LunarMagicWrapperTest, ImportsGFXIntoManyROMsInOrder) {
+    std::mutex calls_mutex{};
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        {
+            std::lock_guard lock{ calls_mutex };
+            calls.push_back(call_string);
+        }
+        return call_string.find("b.smc") != std::string::npos ? Result{ 1, {}, { "Error" } }
+                                                              : Result{ 0, { call_string }, {} };
+    });
+
+    const std::vector<fs::path> roms{ "a.smc", "b.smc", "c.smc" };
+    const auto results{ lm.importGFXMany(roms, 2) };
+
+    ASSERT_EQ(results.size(), roms.size());
+    for (size_t i{}; i < roms.size(); ++i) {
+        EXPECT_EQ(results[i].rom_path, roms[i]);
+    }
+    EXPECT_EQ(results[0].result->out, std::vector<std::string>({ R"(-ImportGFX "a.smc")" }));
+    EXPECT_FALSE(results[1].succeeded());
+    EXPECT_EQ(results[2].result->out, std::vector<std::string>({ R"(-ImportGFX "c.smc")" }));
+    EXPECT_EQ(calls.size(), 3u);
+}
+
+TEST(LunarMagicWrapperTest, ImportingLevelFixesChecksumWhenEnabled) {
+    const std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0);
+    std::ofstream("checksum.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                          static_cast<std::streamsize>(bytes.size()));
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string&) {
+        std::fstream rom("checksum.smc", std::ios::in | std::ios::out | std::ios::binary);
+        rom.seekp(0x1000);
+        rom.write("\x12\x34", 2);
+        return Result{ 0, {}, {} };
+    });
+    lm.setAutoFixChecksum(true);
+    const auto before{ ROM::fromFile("checksum.smc").headerChecksum() };
+
+    lm.importLevel("checksum.smc", FixturePath("level.mwl"));
+
+    const auto imported{ ROM::fromFile("checksum.smc") };
+    EXPECT_NE(imported.headerChecksum(), before);
+    EXPECT_EQ(imported.headerChecksum(), imported.computeChecksum());
+
+    fs::remove("checksum.smc");
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, FindsLunarMagicOnPath) {
+    const std::string original_path{ std::getenv("PATH") ? std::getenv("PATH") : "" };
+    fs::create_directories("find_test/empty");
+    fs::create_directories("find_test/bin");
+    std::ofstream("find_test/bin/lunar_magic.exe").close();
+
+    const auto bin{ fs::absolute("find_test/bin") };
+    setenv("PATH", (fs::absolute("find_test/empty").string() + ":" + bin.string()).c_str(), 1);
+
+    const auto found{ LunarMagicWrapper::findLunarMagic() };
+    const auto wrapper{ LunarMagicWrapper::fromDefaultLocation() };
+
+    setenv("PATH", original_path.c_str(), 1);
+    fs::remove_all("find_test");
+
+    EXPECT_EQ(found, bin / "lunar_magic.exe");
+    EXPECT_TRUE(wrapper.has_value());
+}
+#endif
+
+namespace {
+    void MakeExportedLevels(const fs::path& directory, std::initializer_list<const char*> file_names) {
+        fs::remove_all(directory);
+        fs::create_directories(directory);
+        for (const auto* file_name : file_names) {
+            std::ofstream(directory / file_name) << file_name;
+        }
+    }
+}
+
+TEST(LunarMagicWrapperTest, PlacesExportedLevelsUsingTemplate) {
+    MakeExportedLevels("template_test/export", { "level 000.mwl", "level 105.mwl", "level 1FF.MWL", "other.txt" });
+
+    TestableWrapper::placeExportedLevels("template_test/export", "level ", [](uint16_t level_number) {
+        return fs::path("template_test/Levels") / fmt::format("0x{:03X}.mwl", level_number);
+    });
+
+    EXPECT_TRUE(fs::exists("template_test/Levels/0x000.mwl"));
+    EXPECT_TRUE(fs::exists("template_test/Levels/0x105.mwl"));
+    EXPECT_TRUE(fs::exists("template_test/Levels/0x1FF.mwl"));
+    EXPECT_EQ(ReadLines("template_test/Levels/0x105.mwl"), std::vector<std::string>({ "level 105.mwl" }));
+    EXPECT_TRUE(fs::exists("template_test/export/other.txt"));
+
+    fs::remove_all("template_test");
+}
+
+TEST(LunarMagicWrapperTest, RejectsTemplatesProducingDuplicatePaths) {
+    MakeExportedLevels("template_test/export", { "level 105.mwl", "level 106.mwl" });
+
+    EXPECT_THROW(TestableWrapper::placeExportedLevels("template_test/export", "level ", [](uint16_t level_number) {
+        return fs::path("template_test") / (level_number < 0x100 ? "low.mwl" : "high.mwl");
+    }), std::invalid_argument);
+
+    EXPECT_FALSE(fs::exists("template_test/high.mwl"));
+    EXPECT_TRUE(fs::exists("template_test/export/level 105.mwl"));
+
+    fs::remove_all("template_test");
+}
+
+TEST(LunarMagicWrapperTest, DetectsROMNeedingExpansion) {
+    const TestableWrapper lm{ "lunar_magic.exe" };
+    std::ofstream("too_small.smc", std::ios::binary) << std::string(0x100000, '\0');
+    const Result result{ 1, ReadLines(FixturePath("logs/rom_too_small.log")), {} };
+
+    try {
+        lm.throwIfKnownFailure("too_small.smc", result);
+        FAIL() << "Expected NeedsExpansionException";
+    } catch (const NeedsExpansionException& e) {
+        EXPECT_EQ(e.getCurrentSize(), 0x100000u);
+        EXPECT_EQ(e.getSuggestedSize(), ROMSize::_2MB);
+    }
+
+    fs::remove("too_small.smc");
+}
+
+TEST(LunarMagicWrapperTest, SuggestsNextSizeWhenLunarMagicDoesNot) {
+    const TestableWrapper lm{ "lunar_magic.exe" };
+    std::ofstream("too_small.smc", std::ios::binary) << std::string(0x200200, '\0');
+    const Result result{ 1, { "ROM is too small." }, {} };
+
+    try {
+        lm.throwIfKnownFailure("too_small.smc", result);
+        FAIL() << "Expected NeedsExpansionException";
+    } catch (const NeedsExpansionException& e) {
+        EXPECT_EQ(e.getCurrentSize(), 0x200000u);
+        EXPECT_EQ(e.getSuggestedSize(), ROMSize::_3MB);
+    }
+
+    fs::remove("too_small.smc");
+}
+
+TEST(LunarMagicWrapperTest, ImportsSharedPaletteIntoManyROMs) {
+    std::mutex calls_mutex{};
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        std::lock_guard lock{ calls_mutex };
+        calls.push_back(call_string);
+        return call_string.find("second.smc") != std::string::npos
+            ? Result{ 1, {}, { "Error" } } : Result{ 0, { "Shared palette imported." }, {} };
+    });
+    const auto palette{ FixturePath("shared_palette.pal") };
+
+    const auto results{ lm.importSharedPaletteMany({ "first.smc", "second.smc" }, palette, 2) };
+
+    ASSERT_EQ(results.size(), 2u);
+    EXPECT_EQ(results[0].rom_path, fs::path("first.smc"));
+    EXPECT_TRUE(results[0].succeeded());
+    EXPECT_EQ(results[0].result->out, std::vector<std::string>({ "Shared palette imported." }));
+    EXPECT_FALSE(results[1].succeeded());
+    EXPECT_THROW(std::rethrow_exception(results[1].error), LunarMagicWrapperException);
+    EXPECT_EQ(calls.size(), 2u);
+
+    calls.clear();
+    EXPECT_THROW(lm.importSharedPaletteMany({ "first.smc" }, "missing.pal", 1), MissingInputFileException);
+    EXPECT_THROW(lm.importSharedPaletteMany({ "first.smc" }, FixturePath("palette.pal"), 1), InvalidPathException);
+    EXPECT_TRUE(calls.empty());
+}
+
+TEST(LunarMagicWrapperTest, CreatesMissingOutputDirectories) {
+    fs::remove_all("fresh_project");
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string&) {
+        return Result{ 0, {}, {} };
+    });
+
+    lm.exportLevel("rom.smc", fs::path("fresh_project") / "levels" / "level 105.mwl", 0x105);
+    lm.exportMultipleLevels("rom.smc", fs::path("fresh_project") / "all_levels", "level ");
+    EXPECT_TRUE(fs::is_directory(fs::path("fresh_project") / "levels"));
+    EXPECT_TRUE(fs::is_directory(fs::path("fresh_project") / "all_levels"));
+
+    lm.setCreateOutputDirectories(false);
+    lm.exportAllMap16("rom.smc", fs::path("fresh_project") / "map16" / "all.map16");
+    EXPECT_FALSE(fs::exists(fs::path("fresh_project") / "map16"));
+
+    fs::remove_all("fresh_project");
+}
+
+TEST(LunarMagicWrapperTest, DetectsROMFromNewerLunarMagic) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    const auto lines{ ReadLines(FixturePath("logs/newer_rom_format.log")) };
+    const Result result{ 0, lines, {} };
+
+    try {
+        lm.throwIfKnownFailure("rom.smc", result);
+        FAIL() << "Expected NewerROMFormatException";
+    } catch (const NewerROMFormatException& e) {
+        EXPECT_EQ(e.getROMPath(), fs::path("rom.smc"));
+        EXPECT_EQ(e.getWarning(), lines[1]);
+        EXPECT_TRUE(e.getResult().succeeded);
+    }
+
+    lm.setOutputMarkers({ .newer_rom_format = "made with a later version" });
+    EXPECT_NO_THROW(lm.throwIfKnownFailure("rom.smc", result));
+    EXPECT_THROW(lm.throwIfKnownFailure("rom.smc", { 0, {}, { "ROM made with a later version" } }),
+                 NewerROMFormatException);
+
+    LunarMagicWrapper spawned{ "missing_lunar_magic.exe" };
+    spawned.setSpawner([&](const std::string&) {
+        return Result{ 0, lines, {} };
+    });
+    EXPECT_THROW(spawned.expandROM("rom.smc", ROMSize::_4MB), NewerROMFormatException);
+    EXPECT_THROW(spawned.changeCompression("rom.smc", CompressionOption::LC_LZ2_Speed), NewerROMFormatException);
+}
+
+TEST(LunarMagicWrapperTest, TeeLogAppendsEveryCall) {
+    fs::remove("tee_test.log");
+    TestableWrapper lm{ "lunar_magic.exe" };
+
+    lm.teeLog("-ExportGFX \"rom.smc\"", { 0, { "exported" }, {} });
+    lm.setTeeLogPath("tee_test.log");
+    lm.teeLog("-ExportGFX \"rom.smc\"", { 0, { "exported" }, {} });
+    lm.teeLog("-ImportGFX \"rom.smc\"", { 3, { "importing" }, { "failed" } });
+
+    EXPECT_EQ(ReadLines("tee_test.log"), std::vector<std::string>({
+        R"(=== -ExportGFX "rom.smc" (exit code 0) ===)", "exported",
+        R"(=== -ImportGFX "rom.smc" (exit code 3) ===)", "importing", "failed"
+    }));
+
+    fs::remove("tee_test.log");
+}
+
+TEST(LunarMagicWrapperTest, SecondaryExitPolicyMapsToFlagBits) {
+    EXPECT_EQ(withSecondaryExitPolicy(LevelImportOption::NONE, SecondaryExitPolicy::KEEP), LevelImportOption::NONE);
+    EXPECT_EQ(withSecondaryExitPolicy(LevelImportOption::NONE, SecondaryExitPolicy::CLEAR),
+              LevelImportOption::CLEAR_SECONDARY_EXITS);
+    EXPECT_EQ(withSecondaryExitPolicy(LevelImportOption::CLEAR_SECONDARY_EXITS, SecondaryExitPolicy::KEEP),
+              LevelImportOption::NONE);
+    EXPECT_EQ(withSecondaryExitPolicy(LevelImportOption::CLEAR_SECONDARY_EXITS, SecondaryExitPolicy::CLEAR),
+              LevelImportOption::CLEAR_SECONDARY_EXITS);
+}
+
+TEST(LunarMagicWrapperTest, SplitsCallForDirectSpawn) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+    lm.setCommandPrefix({ "wine" });
+
+    EXPECT_EQ(lm.directArguments(R"(-ImportMultLevels "my hack.smc" "levels" 1)"), std::vector<std::string>({
+        "wine", "lunar_magic.exe", "-ImportMultLevels", "my hack.smc", "levels", "1"
+    }));
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "")"), std::vector<std::string>({
+        "wine", "lunar_magic.exe", "-ExportGFX", ""
+    }));
+}
+
+TEST(LunarMagicWrapperTest, LeavesCallsNeedingTheShellToIt) {
+    TestableWrapper lm{ "lunar_magic.exe" };
+
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "rock & roll.smc")"), std::nullopt);
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "%HACK%.smc")"), std::nullopt);
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "unterminated.smc)"), std::nullopt);
+
+    lm.setCommandSuffix({ "|", "tee" });
+    EXPECT_EQ(lm.directArguments(R"(-ExportGFX "rom.smc")"), std::nullopt);
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, CapturesOutputOfDirectSpawn) {
+    std::ofstream("fake_lm.sh") << "#!/bin/sh\necho \"$1\"\necho \"$2\"\necho warning >&2\n";
+    fs::permissions("fake_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("fake_lm.sh") };
+    const auto result{ lm.exportGFX("my hack.smc") };
+
+    EXPECT_TRUE(result.succeeded);
+    EXPECT_EQ(result.out, std::vector<std::string>({ "-ExportGFX", "my hack.smc" }));
+    EXPECT_EQ(result.err, std::vector<std::string>({ "warning" }));
+    fs::remove("fake_lm.sh");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, ExposesConfiguration) {
+    LunarMagicWrapper lm{ "tools/lunar_magic.exe" };
+
+    EXPECT_EQ(lm.getLunarMagicPath(), fs::path("tools/lunar_magic.exe"));
+    EXPECT_TRUE(lm.getCommandPrefix().empty());
+    EXPECT_FALSE(lm.getCanonicalizePaths());
+    EXPECT_EQ(lm.getTeeLogPath(), std::nullopt);
+    EXPECT_TRUE(lm.getHideWindow());
+
+    lm.setCommandPrefix({ "wine" }).setCommandSuffix({ "--quiet" }).setCanonicalizePaths(true).setTeeLogPath("lm.log");
+    lm.setHideWindow(false);
+
+    EXPECT_EQ(lm.getCommandPrefix(), std::vector<std::string>({ "wine" }));
+    EXPECT_EQ(lm.getCommandSuffix(), std::vector<std::string>({ "--quiet" }));
+    EXPECT_TRUE(lm.getCanonicalizePaths());
+    EXPECT_EQ(lm.getTeeLogPath(), fs::path("lm.log"));
+    EXPECT_FALSE(lm.getHideWindow());
+}
+
+TEST(LunarMagicWrapperTest, ParsesSupportedOperationsFromUsage) {
+    const std::set<Operation> expected{
+        Operation::EXPORT_GFX, Operation::IMPORT_GFX, Operation::EXPORT_LEVEL, Operation::IMPORT_LEVEL,
+        Operation::EXPAND_ROM
+    };
+
+    EXPECT_EQ(LunarMagicWrapper::parseSupportedOperations(ReadLines(FixturePath("logs/help.log"))), expected);
+    EXPECT_EQ(LunarMagicWrapper::supportedOperations(FixturePath("logs/help.log")), expected);
+
+    EXPECT_EQ(LunarMagicWrapper::supportedOperations().size(), ALL_OPERATIONS.size());
+    EXPECT_EQ(LunarMagicWrapper::supportedOperations(FixturePath("logs/level_imported.log")).size(),
+              ALL_OPERATIONS.size());
+}
+
+TEST(LunarMagicWrapperTest, FindsGraphicsFolderInArchiveLayouts) {
+    fs::remove_all("archive_test");
+    fs::create_directories("archive_test/nested/pack/GRAPHICS");
+    fs::create_directories("archive_test/flat");
+    fs::create_directories("archive_test/empty/ExGraphics");
+    std::ofstream("archive_test/flat/GFX00.bin").close();
+
+    EXPECT_EQ(TestableWrapper::findGraphicsFolder("archive_test/nested"), fs::path("archive_test/nested/pack/GRAPHICS"));
+    EXPECT_EQ(TestableWrapper::findGraphicsFolder("archive_test/flat"), fs::path("archive_test/flat"));
+    EXPECT_EQ(TestableWrapper::findGraphicsFolder("archive_test/empty"), std::nullopt);
+
+    fs::remove_all("archive_test");
+}
+
+TEST(LunarMagicWrapperTest, ImportsGFXFromArchiveOnlyOnSuccess) {
+    fs::remove_all("archive_test");
+    fs::create_directories("archive_test/pack/Graphics");
+    std::ofstream("archive_test/pack/Graphics/GFX00.bin") << "gfx";
+    const std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE, 0);
+    std::ofstream("archive_test/rom.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                                  static_cast<std::streamsize>(bytes.size()));
+
+    // writes into the staged ROM it's given, like Lunar Magic would before failing or succeeding
+    int exit_code{ 1 };
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        const auto staged_rom{ call_string.substr(call_string.find('"') + 1,
+                                                  call_string.rfind('"') - call_string.find('"') - 1) };
+        std::fstream rom(staged_rom, std::ios::in | std::ios::out | std::ios::binary);
+        rom.seekp(0x1000);
+        rom.write("\x12\x34", 2);
+        return Result{ exit_code, {}, {} };
+    });
+    lm.setAutoFixChecksum(true);
+
+    EXPECT_THROW(lm.importGFXFromArchive("archive_test/rom.smc", "archive_test/pack"), LunarMagicWrapperException);
+    EXPECT_EQ(ROM::fromFile("archive_test/rom.smc").bytes(), bytes);
+
+    exit_code = 0;
+    lm.importGFXFromArchive("archive_test/rom.smc", "archive_test/pack");
+    const auto imported{ ROM::fromFile("archive_test/rom.smc") };
+    EXPECT_EQ(imported.bytes()[0x1000], 0x12);
+    EXPECT_EQ(imported.headerChecksum(), imported.computeChecksum());
+
+    fs::remove_all("archive_test");
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, CreatesArchivesThatExtractAgain) {
+    fs::remove_all("archive_test");
+    fs::create_directories("archive_test/project/Levels");
+    std::ofstream("archive_test/project/Levels/level 105.mwl") << "level";
+    std::ofstream("archive_test/project/manifest.json") << "{}";
+
+    for (const auto* archive : { "archive_test/project.zip", "archive_test/project.tar.gz" }) {
+        TestableWrapper::createArchive("archive_test/project", archive);
+        TestableWrapper::extractArchive(archive, "archive_test/extracted");
+
+        EXPECT_EQ(ReadLines("archive_test/extracted/Levels/level 105.mwl"), std::vector<std::string>({ "level" }))
+            << archive;
+        EXPECT_TRUE(fs::exists("archive_test/extracted/manifest.json")) << archive;
+        fs::remove_all("archive_test/extracted");
+    }
+
+    fs::remove_all("archive_test");
+}
+
+TEST(LunarMagicWrapperTest, ExtractsArchives) {
+    fs::remove_all("archive_test");
+    fs::create_directories("archive_test/Graphics");
+    std::ofstream("archive_test/Graphics/GFX00.bin") << "gfx";
+    ASSERT_EQ(std::system("tar -cf archive_test.tar -C archive_test Graphics"), 0);
+
+    TestableWrapper::extractArchive("archive_test.tar", "archive_test/extracted");
+    EXPECT_EQ(TestableWrapper::findGraphicsFolder("archive_test/extracted"), fs::path("archive_test/extracted/Graphics"));
+    EXPECT_THROW(TestableWrapper::extractArchive("missing.zip", "archive_test/missing"), InvalidPathException);
+
+    fs::remove_all("archive_test");
+    fs::remove("archive_test.tar");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, WrapsSystemErrors) {
+    const fs::filesystem_error io_error{ "Failed to create", "missing/temp",
+                                         std::make_error_code(std::errc::permission_denied) };
+    const TempDirectoryException temp_error{ io_error };
+    EXPECT_EQ(temp_error.getErrorCode(), std::errc::permission_denied);
+    EXPECT_EQ(std::string(temp_error.what()), io_error.what());
+
+    const LaunchException launch_error{ std::system_error(std::make_error_code(std::errc::no_such_file_or_directory),
+                                                          "Failed to start Lunar Magic") };
+    EXPECT_EQ(launch_error.code(), std::errc::no_such_file_or_directory);
+    EXPECT_THROW(throw launch_error, std::system_error);
+
+    EXPECT_FALSE(TempDirectoryException("no temp directory").getErrorCode());
+}
+
+TEST(ROMFileLockTest, ExcludesOtherHandles) {
+    std::ofstream("locked.smc") << "rom";
+
+    auto first{ ROMFileLock::tryAcquire("locked.smc", std::chrono::milliseconds(0)) };
+    ASSERT_TRUE(first.has_value());
+    EXPECT_TRUE(first->ownsLock());
+
+    const auto start{ std::chrono::steady_clock::now() };
+    EXPECT_FALSE(ROMFileLock::tryAcquire("locked.smc", std::chrono::milliseconds(50)).has_value());
+    EXPECT_GE(std::chrono::steady_clock::now() - start, std::chrono::milliseconds(50));
+
+    // the file itself is still readable while locked
+    std::ifstream rom_file("locked.smc");
+    std::string contents{};
+    rom_file >> contents;
+    rom_file.close();
+    EXPECT_EQ(contents, "rom");
+
+    first->release();
+    EXPECT_FALSE(first->ownsLock());
+    auto second{ ROMFileLock::tryAcquire("locked.smc", std::chrono::milliseconds(0)) };
+    EXPECT_TRUE(second.has_value());
+    second.reset();
+
+    EXPECT_THROW(ROMFileLock::tryAcquire("missing_locked.smc", std::chrono::milliseconds(0)), fs::filesystem_error);
+
+    fs::remove("locked.smc");
+}
+
+TEST(LunarMagicWrapperTest, LocksROMWhileWriting) {
+    std::ofstream("locked.smc") << "rom";
+
+    std::optional<bool> locked_during_call{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string&) {
+        locked_during_call = !ROMFileLock::tryAcquire("locked.smc", std::chrono::milliseconds(0)).has_value();
+        return Result{ 0, {}, {} };
+    });
+    lm.setFileLock(std::chrono::milliseconds(50));
+
+    EXPECT_NO_THROW(lm.importGFX("locked.smc"));
+    EXPECT_EQ(locked_during_call, true);
+
+    // released once the operation is done
+    auto external{ ROMFileLock::tryAcquire("locked.smc", std::chrono::milliseconds(0)) };
+    ASSERT_TRUE(external.has_value());
+
+    locked_during_call.reset();
+    try {
+        lm.importGFX("locked.smc");
+        FAIL() << "expected ROMLockedException";
+    } catch (const ROMLockedException& e) {
+        EXPECT_EQ(e.getROMPath(), fs::path("locked.smc"));
+    }
+    EXPECT_FALSE(locked_during_call.has_value());
+
+    external.reset();
+    fs::remove("locked.smc");
+}
+
+TEST(LunarMagicWrapperTest, ReadsFreeSpaceOfROMFile) {
+    // copier header, the original 512KB and 256KB of expanded area of which half is used
+    std::vector<uint8_t> bytes(ROM::COPIER_HEADER_SIZE + 0xC0000, 0);
+    std::fill_n(bytes.begin() + ROM::COPIER_HEADER_SIZE + ROM::ORIGINAL_ROM_SIZE, 0x20000, 0xAA);
+    std::ofstream("space.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                       static_cast<std::streamsize>(bytes.size()));
+
+    const auto space{ LunarMagicWrapper::freeSpace("space.smc") };
+
+    EXPECT_EQ(space.total_bytes, 0xC0000u);
+    EXPECT_EQ(space.free_bytes, 0x20000u);
+    EXPECT_NEAR(space.percentUsed(), 83.33, 0.01);
+
+    fs::remove("space.smc");
+}
+
+TEST(LunarMagicWrapperTest, RejectsUnexpectedMappingBeforeRunning) {
+    std::vector<uint8_t> bytes(0x100000, 0);
+    bytes[ROM::HIROM_HEADER + 0x15] = 0x21;
+    bytes[ROM::HIROM_HEADER + 0x1C] = 0xFF;
+    bytes[ROM::HIROM_HEADER + 0x1D] = 0xFF;
+    bytes[ROM::HIROM_HEADER + 0x3D] = 0x80;
+    std::ofstream("hirom.sfc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                       static_cast<std::streamsize>(bytes.size()));
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setExpectedMappingMode(MappingMode::LOROM);
+
+    EXPECT_EQ(LunarMagicWrapper::mappingMode("hirom.sfc"), MappingMode::HIROM);
+    try {
+        lm.importGFX("hirom.sfc");
+        FAIL() << "expected UnexpectedMappingException";
+    } catch (const UnexpectedMappingException& e) {
+        EXPECT_EQ(e.getActual(), MappingMode::HIROM);
+        EXPECT_EQ(e.getROMPath(), fs::path("hirom.sfc"));
+    }
+
+    fs::remove("hirom.sfc");
+}
+
+TEST(LunarMagicWrapperTest, ReadsVersionFromVersionResource) {
+    // VS_FIXEDFILEINFO for 3.40.0.1 somewhere in the file
+    const std::vector<uint8_t> executable{
+        'M', 'Z', 0x00, 0x00, 0xBD, 0x04, 0xEF, 0xFE, 0x00, 0x00, 0x01, 0x00,
+        0x28, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00
+    };
+    std::ofstream("versioned.exe", std::ios::binary).write(reinterpret_cast<const char*>(executable.data()),
+                                                           static_cast<std::streamsize>(executable.size()));
+    std::ofstream("unversioned.exe") << "MZ";
+
+    EXPECT_EQ(TestableWrapper::readFileVersion("versioned.exe"), "3.40.0.1");
+    EXPECT_EQ(TestableWrapper::readFileVersion("unversioned.exe"), std::nullopt);
+    EXPECT_EQ(TestableWrapper::readFileVersion("missing.exe"), std::nullopt);
+
+    fs::remove("versioned.exe");
+    fs::remove("unversioned.exe");
+}
+
+TEST(LunarMagicWrapperTest, DiagnosticsDescribeSetup) {
+    fs::create_directories("diagnostics_test");
+    LunarMagicWrapper lm{ "diagnostics_test" };
+    lm.setCommandPrefix({ "wine" }).setExpectedMappingMode(MappingMode::LOROM);
+
+    const auto diagnostics{ lm.diagnostics() };
+    const auto report{ diagnostics.toString() };
+
+    EXPECT_TRUE(diagnostics.lunar_magic_exists);
+    EXPECT_TRUE(diagnostics.lunar_magic_is_directory);
+    EXPECT_EQ(diagnostics.lunar_magic_version, std::nullopt);
+    EXPECT_NE(report.find("Lunar Magic path: diagnostics_test\n"), std::string::npos);
+    EXPECT_NE(report.find("(directory, not the executable)"), std::string::npos);
+    EXPECT_NE(report.find("Command prefix: wine\n"), std::string::npos);
+    EXPECT_NE(report.find("Expected mapping mode: LoROM\n"), std::string::npos);
+
+    fs::remove_all("diagnostics_test");
+}
+
+TEST(LunarMagicWrapperTest, UsesExecutableInsideConfiguredDirectory) {
+    fs::create_directories("lm_directory");
+    TestableWrapper lm{ "lm_directory" };
+
+    EXPECT_THROW(lm.exportGFX("rom.smc"), LunarMagicMissingException);
+    try {
+        (void)lm.buildCommand("-ExportGFX \"rom.smc\"", "out.log", "err.log");
+        FAIL() << "expected LunarMagicMissingException";
+    } catch (const LunarMagicMissingException& e) {
+        EXPECT_NE(std::string(e.what()).find("is a directory"), std::string::npos);
+        EXPECT_EQ(e.getPath(), fs::path("lm_directory"));
+    }
+
+    std::ofstream("lm_directory/lunar_magic.exe").close();
+    EXPECT_EQ(lm.directArguments("-ExportGFX \"rom.smc\""), std::vector<std::string>({
+        (fs::path("lm_directory") / "lunar_magic.exe").string(), "-ExportGFX", "rom.smc"
+    }));
+
+    fs::remove_all("lm_directory");
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, BacksUpROMBeforeWriting) {
+    fs::remove_all("backup_test");
+    fs::create_directories("backup_test/Graphics");
+    std::ofstream("backup_test/rom.smc") << "original";
+    // stands in for Lunar Magic and modifies the ROM it's given
+    std::ofstream("backup_test/fake_lm.sh") << "#!/bin/sh\necho modified > \"$2\"\n";
+    fs::permissions("backup_test/fake_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("backup_test/fake_lm.sh") };
+    lm.setBackupBeforeWrite(true).setBackupDirectory("backup_test/backups");
+
+    std::vector<fs::path> backups{};
+    lm.afterBackup([&](const fs::path& rom_path, const fs::path& backup_path) {
+        EXPECT_EQ(rom_path, fs::path("backup_test/rom.smc"));
+        backups.push_back(backup_path);
+    });
+
+    lm.importGFX("backup_test/rom.smc");
+    lm.importGFX("backup_test/rom.smc");
+    lm.exportGFX("backup_test/rom.smc");
+
+    ASSERT_EQ(backups.size(), 2u);
+    EXPECT_NE(backups[0], backups[1]);
+    EXPECT_EQ(backups[0].parent_path(), fs::path("backup_test/backups"));
+    EXPECT_EQ(backups[0].extension(), ".bak");
+    EXPECT_EQ(ReadLines(backups[0]), std::vector<std::string>({ "original" }));
+    EXPECT_EQ(ReadLines(backups[1]), std::vector<std::string>({ "modified" }));
+    EXPECT_EQ(ReadLines("backup_test/rom.smc"), std::vector<std::string>({ "modified" }));
+
+    fs::remove_all("backup_test");
+}
+#endif
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, SuccessPolicyDecidesWhetherCallSucceeded) {
+    // exits with 3 even though it worked, like some builds do
+    std::ofstream("quirky_lm.sh") << "#!/bin/sh\necho done\nexit 3\n";
+    fs::permissions("quirky_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("quirky_lm.sh") };
+    EXPECT_THROW(lm.exportGFX("rom.smc"), LunarMagicWrapperException);
+
+    std::vector<Operation> seen{};
+    lm.setSuccessPolicy([&](Operation operation, int exit_code, const std::vector<std::string>& out,
+                            const std::vector<std::string>&) {
+        seen.push_back(operation);
+        return exit_code == 0 || out == std::vector<std::string>({ "done" });
+    });
+
+    const auto result{ lm.exportGFX("rom.smc") };
+    EXPECT_TRUE(result.succeeded);
+    EXPECT_TRUE(result.error_code.has_value());
+    EXPECT_EQ(seen, std::vector<Operation>({ Operation::EXPORT_GFX }));
+
+    fs::remove("quirky_lm.sh");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, SpawnerReplacesRunningLunarMagic) {
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return call_string.starts_with("-ExportGFX") ? Result{ 0, { "exported" }, {} } : Result{ 1, {}, { "failed" } };
+    });
+
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, std::vector<std::string>({ "exported" }));
+    EXPECT_EQ(calls, std::vector<std::string>({ R"(-ExportGFX "rom.smc")" }));
+
+    try {
+        lm.exportLevel("rom.smc", "level 105.mwl", 0x105);
+        FAIL() << "expected LunarMagicWrapperException";
+    } catch (const LunarMagicWrapperException& e) {
+        EXPECT_EQ(e.getResult().error_code, 1);
+        EXPECT_EQ(e.getResult().err, std::vector<std::string>({ "failed" }));
+        EXPECT_EQ(e.getOperation(), Operation::EXPORT_LEVEL);
+    }
+    EXPECT_EQ(calls.back(), R"(-ExportLevel "rom.smc" "level 105.mwl" 105)");
+}
+
+TEST(LunarMagicWrapperTest, DetectsTrailingDataAfterExport) {
+    EXPECT_EQ(LunarMagicWrapper::expectedExportSize(FixturePath("level.mwl")), fs::file_size(FixturePath("level.mwl")));
+    EXPECT_EQ(LunarMagicWrapper::expectedExportSize(FixturePath("all.map16")), fs::file_size(FixturePath("all.map16")));
+    EXPECT_EQ(LunarMagicWrapper::expectedExportSize(FixturePath("partial.map16")),
+              fs::file_size(FixturePath("partial.map16")));
+    EXPECT_EQ(LunarMagicWrapper::expectedExportSize(FixturePath("palette.pal")), std::nullopt);
+
+    // the spawner stands in for an export that pads the file
+    const auto export_padded{ [](const std::string&) {
+        fs::copy_file(FixturePath("level.mwl"), "padded.mwl", fs::copy_options::overwrite_existing);
+        std::ofstream("padded.mwl", std::ios::binary | std::ios::app) << std::string(0x10, '\0');
+        return Result{ 0, {}, {} };
+    } };
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner(export_padded);
+
+    EXPECT_NO_THROW(lm.exportLevel("rom.smc", "padded.mwl", 0x105));
+
+    lm.setTrailingDataPolicy(TrailingDataPolicy::ERROR);
+    try {
+        lm.exportLevel("rom.smc", "padded.mwl", 0x105);
+        FAIL() << "expected MalformedExportException";
+    } catch (const MalformedExportException& e) {
+        EXPECT_EQ(e.getPath(), fs::path("padded.mwl"));
+        EXPECT_EQ(e.getExpectedSize(), fs::file_size(FixturePath("level.mwl")));
+        EXPECT_EQ(e.getActualSize(), fs::file_size(FixturePath("level.mwl")) + 0x10);
+    }
+
+    lm.setTrailingDataPolicy(TrailingDataPolicy::TRUNCATE);
+    lm.exportLevel("rom.smc", "padded.mwl", 0x105);
+    EXPECT_EQ(MWL::read("padded.mwl").bytes(), MWL::read(FixturePath("level.mwl")).bytes());
+
+    fs::remove("padded.mwl");
+}
+
+TEST(LunarMagicWrapperTest, ChecksOnlyLevelsExportedByTheCall) {
+    fs::remove_all("stale_exports");
+    fs::create_directories("stale_exports");
+    fs::copy_file(FixturePath("level.mwl"), "stale_exports/level 106.mwl");
+    std::ofstream("stale_exports/level 106.mwl", std::ios::binary | std::ios::app) << std::string(0x10, '\0');
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setTrailingDataPolicy(TrailingDataPolicy::ERROR);
+    bool pad{};
+    lm.setSpawner([&](const std::string&) {
+        fs::copy_file(FixturePath("level.mwl"), "stale_exports/level 105.mwl", fs::copy_options::overwrite_existing);
+        if (pad) {
+            std::ofstream("stale_exports/level 105.mwl", std::ios::binary | std::ios::app) << std::string(0x10, '\0');
+        }
+        return Result{ 0, {}, {} };
+    });
+
+    EXPECT_NO_THROW(lm.exportMultipleLevels("rom.smc", "stale_exports", "level "));
+    EXPECT_EQ(fs::file_size("stale_exports/level 106.mwl"), fs::file_size(FixturePath("level.mwl")) + 0x10);
+
+    pad = true;
+    EXPECT_THROW(lm.exportMultipleLevels("rom.smc", "stale_exports", "level ", LevelExportOption::MODIFIED_ONLY),
+                 MalformedExportException);
+
+    fs::remove_all("stale_exports");
+}
+
+TEST(LunarMagicWrapperTest, ResetsLevelsFromCleanROM) {
+    std::ofstream("clean.smc", std::ios::binary) << std::string(0x80000, '\0');
+    std::ofstream("hack.smc", std::ios::binary) << std::string(0x100000, '\0');
+    fs::remove_all("reset_imported");
+
+    // exports the fixture levels as the clean ROM's, keeps whatever is imported in reset_imported
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setDefaultLevelImportOptions(LevelImportOption::CLEAR_SECONDARY_EXITS);
+    bool import_fails{};
+    lm.setSpawner([&](const std::string& call_string) {
+        std::vector<std::string> arguments{};
+        for (auto start{ call_string.find('"') }; start != std::string::npos;
+             start = call_string.find('"', call_string.find('"', start + 1) + 1)) {
+            arguments.push_back(call_string.substr(start + 1, call_string.find('"', start + 1) - start - 1));
+        }
+
+        if (call_string.starts_with("-ExportMultLevels")) {
+            EXPECT_EQ(arguments[0], "clean.smc");
+            for (const auto& entry : fs::directory_iterator(FixturePath("levels"))) {
+                fs::copy_file(entry.path(), arguments[1] + entry.path().filename().string().substr(6));
+            }
+        } else if (call_string.starts_with("-ImportMultLevels")) {
+            EXPECT_EQ(arguments[0], "hack.smc");
+            EXPECT_TRUE(call_string.ends_with("\" 0")) << call_string;
+            if (import_fails) {
+                return Result{ 1, {}, { "Error" } };
+            }
+            fs::copy(arguments[1], "reset_imported");
+        }
+        return Result{ 0, {}, {} };
+    });
+
+    EXPECT_EQ(lm.resetLevels("hack.smc", "clean.smc", 0x001, 0x003), std::vector<uint16_t>({ 0x001, 0x002, 0x003 }));
+
+    std::vector<fs::path> imported{};
+    for (const auto& entry : fs::directory_iterator("reset_imported")) {
+        imported.push_back(entry.path().filename());
+    }
+    std::sort(imported.begin(), imported.end());
+    EXPECT_EQ(imported, std::vector<fs::path>({ "level 001.mwl", "level 002.mwl", "level 003.mwl" }));
+    EXPECT_EQ(MWL::read("reset_imported/level 002.mwl").bytes(),
+              MWL::read(FixturePath("levels/level 002.mwl")).bytes());
+
+    EXPECT_THROW(lm.resetLevels("hack.smc", "clean.smc", 0x003, 0x001), std::invalid_argument);
+    EXPECT_THROW(lm.resetLevels("clean.smc", "hack.smc", 0x000, 0x1FF), std::invalid_argument);
+
+    import_fails = true;
+    EXPECT_THROW(lm.resetLevels("hack.smc", "clean.smc", 0x001, 0x003), LunarMagicWrapperException);
+
+    fs::remove_all("reset_imported");
+    fs::remove("clean.smc");
+    fs::remove("hack.smc");
+}
+
+TEST(LunarMagicWrapperTest, PlansProjectBuild) {
+    fs::remove_all("plan_project");
+    fs::create_directories("plan_project/Graphics");
+    fs::create_directories("plan_project/Levels");
+    fs::copy_file(FixturePath("levels/level 000.mwl"), "plan_project/Levels/level 000.mwl");
+    fs::copy_file(FixturePath("level.mwl"), "plan_project/Levels/level 105.mwl");
+    fs::copy_file(FixturePath("all.map16"), "plan_project/all.map16");
+    std::ofstream("plan_project/Levels/notes.txt") << "not a level";
+
+    const auto plan{ LunarMagicWrapper::buildProjectPlan("plan_project") };
+
+    ASSERT_EQ(plan.steps.size(), 4u);
+    EXPECT_EQ(plan.steps[0].operation, Operation::IMPORT_GFX);
+    EXPECT_EQ(plan.steps[1].operation, Operation::IMPORT_LEVEL);
+    EXPECT_EQ(plan.steps[1].level_number, 0x000);
+    EXPECT_EQ(plan.steps[2].level_number, 0x105);
+    EXPECT_EQ(plan.steps[3].operation, Operation::IMPORT_ALL_MAP16);
+
+    std::ostringstream text{};
+    text << plan;
+    EXPECT_EQ(text.str(), "1. ImportGFX Graphics\n"
+                          "2. ImportLevel Levels/level 000.mwl as level 000\n"
+                          "3. ImportLevel Levels/level 105.mwl as level 105\n"
+                          "4. ImportAllMap16 all.map16\n");
+
+    fs::remove_all("plan_project");
+}
+
+TEST(LunarMagicWrapperTest, FormatsMap16CoordinatesWithSeparator) {
+    const auto map16_path{ FixturePath("partial.map16") };
+
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return Result{ 0, {}, {} };
+    });
+
+    lm.importMap16("rom.smc", map16_path, 0x105, std::make_pair(0x10, 0x2B));
+    EXPECT_EQ(calls.back(), fmt::format(R"(-ImportMap16 "rom.smc" "{}" 105 10,2B)", map16_path.string()));
+
+    lm.setCoordinateSeparator(" ");
+    lm.importMap16("rom.smc", map16_path, 0x105, std::make_pair(0x10, 0x2B));
+    EXPECT_EQ(calls.back(), fmt::format(R"(-ImportMap16 "rom.smc" "{}" 105 10 2B)", map16_path.string()));
+
+    lm.importMap16("rom.smc", map16_path, 0x105);
+    EXPECT_EQ(calls.back(), fmt::format(R"(-ImportMap16 "rom.smc" "{}" 105)", map16_path.string()));
+}
+
+TEST(LunarMagicWrapperTest, ReportsMixedBuildResults) {
+    fs::remove_all("report_project");
+    fs::create_directories("report_project/Levels");
+    fs::copy_file(FixturePath("level.mwl"), "report_project/Levels/level 105.mwl");
+    fs::copy_file(FixturePath("all.map16"), "report_project/all.map16");
+    std::ofstream("report.smc", std::ios::binary) << std::string(0x80000, '\0');
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string& call_string) {
+        return call_string.starts_with("-ImportLevel")
+            ? Result{ 0, { "Level 105 imported." }, {} }
+            : Result{ 1, {}, { "Error: map16 is locked" } };
+    });
+
+    const auto report{ lm.buildProject("report.smc", "report_project") };
+
+    EXPECT_FALSE(report.succeeded());
+    ASSERT_EQ(report.steps.size(), 2u);
+    EXPECT_TRUE(report.steps[0].succeeded);
+    EXPECT_EQ(report.steps[0].out, std::vector<std::string>({ "Level 105 imported." }));
+    ASSERT_EQ(report.failures().size(), 1u);
+    EXPECT_EQ(report.failures()[0].name, "ImportAllMap16 all.map16");
+    ASSERT_EQ(report.failures()[0].err.size(), 2u);
+    EXPECT_EQ(report.failures()[0].err[0], "Error: map16 is locked");
+
+    // durations vary between runs
+    const auto summary{ std::regex_replace(report.toString(), std::regex(R"(\(\d+ms\))"), "(Xms)") };
+    EXPECT_EQ(summary, fmt::format("Build failed, 1 of 2 step(s) failed\n"
+                                   "  ok     ImportLevel Levels/level 105.mwl as level 105 (Xms)\n"
+                                   "  FAILED ImportAllMap16 all.map16 (Xms)\n"
+                                   "    Error: map16 is locked\n"
+                                   "    {}\n", report.failures()[0].err[1]));
+
+    fs::remove_all("report_project");
+    fs::remove("report.smc");
+}
+
+TEST(LunarMagicWrapperTest, AttributesBuildLogLinesToOperations) {
+    fs::remove_all("merged_log_project");
+    fs::create_directories("merged_log_project/Levels");
+    fs::copy_file(FixturePath("level.mwl"), "merged_log_project/Levels/level 105.mwl");
+    fs::copy_file(FixturePath("all.map16"), "merged_log_project/all.map16");
+    std::ofstream("merged_log.smc", std::ios::binary) << std::string(0x80000, '\0');
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string& call_string) {
+        return call_string.starts_with("-ImportLevel")
+            ? Result{ 0, { "Lunar Magic 3.40", "Level 105 imported." }, {} }
+            : Result{ 0, { "Lunar Magic 3.40" }, { "Warning: page 3 is empty" } };
+    });
+
+    const auto report{ lm.buildProject("merged_log.smc", "merged_log_project") };
+
+    EXPECT_EQ(report.mergedLog(), std::vector<std::string>({
+        "[ImportLevel] Lunar Magic 3.40", "[ImportLevel] Level 105 imported.",
+        "[ImportAllMap16] Lunar Magic 3.40", "[ImportAllMap16] Warning: page 3 is empty"
+    }));
+    EXPECT_EQ(report.mergedLog(false), std::vector<std::string>({
+        "Lunar Magic 3.40", "Level 105 imported.", "Lunar Magic 3.40", "Warning: page 3 is empty"
+    }));
+
+    fs::remove_all("merged_log_project");
+    fs::remove("merged_log.smc");
+}
+
+TEST(LunarMagicWrapperTest, ValidatesLevelNumbers) {
+    size_t calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string&) {
+        ++calls;
+        return Result{ 0, {}, {} };
+    });
+
+    EXPECT_NO_THROW(lm.exportLevel("rom.smc", "level 000.mwl", 0x000));
+    EXPECT_NO_THROW(lm.exportLevel("rom.smc", "level 1FF.mwl", 0x1FF));
+    EXPECT_NO_THROW(lm.importLevel("rom.smc", FixturePath("level.mwl"), 0x1FF));
+    EXPECT_EQ(calls, 3u);
+
+    try {
+        lm.exportLevel("rom.smc", "level 200.mwl", 0x200);
+        FAIL() << "expected InvalidLevelException";
+    } catch (const InvalidLevelException& e) {
+        EXPECT_EQ(e.getLevels(), std::vector<uint16_t>({ 0x200 }));
+        EXPECT_EQ(std::string(e.what()), "Level 200 is outside of the valid range 000-1FF");
+    }
+    EXPECT_THROW(lm.importLevel("rom.smc", FixturePath("level.mwl"), 0x200), InvalidLevelException);
+    EXPECT_THROW(lm.importMap16("rom.smc", FixturePath("partial.map16"), 0x200), InvalidLevelException);
+    EXPECT_THROW(lm.importCustomPalette("rom.smc", FixturePath("palette.pal"), 0x200), InvalidLevelException);
+
+    // ranges report both ends at once
+    try {
+        lm.resetLevels("rom.smc", "clean.smc", 0x200, 0x3FF);
+        FAIL() << "expected InvalidLevelException";
+    } catch (const InvalidLevelException& e) {
+        EXPECT_EQ(e.getLevels(), std::vector<uint16_t>({ 0x200, 0x3FF }));
+        EXPECT_EQ(std::string(e.what()), "Levels 200, 3FF are outside of the valid range 000-1FF");
+    }
+
+    EXPECT_EQ(calls, 3u);
+}
+
+TEST(LunarMagicWrapperTest, ImportsCustomPalettesFromDirectory) {
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return call_string.ends_with(" 105") ? Result{ 1, {}, { "Error: palette is locked" } } : Result{ 0, {}, {} };
+    });
+
+    const auto outcomes{ lm.importCustomPalettesFromDirectory("rom.smc", FixturePath("palettes")) };
+
+    ASSERT_EQ(outcomes.size(), 2u);
+    EXPECT_EQ(outcomes[0].level_number, 0x01C);
+    EXPECT_EQ(outcomes[0].palette_path.filename(), "1C.pal");
+    EXPECT_TRUE(outcomes[0].succeeded());
+    EXPECT_EQ(outcomes[1].level_number, 0x105);
+    EXPECT_FALSE(outcomes[1].succeeded());
+    EXPECT_THROW(std::rethrow_exception(outcomes[1].error), LunarMagicWrapperException);
+
+    ASSERT_EQ(calls.size(), 2u);
+    EXPECT_TRUE(calls[0].starts_with("-ImportCustomPalette ")) << calls[0];
+    EXPECT_TRUE(calls[0].ends_with("1C.pal\" 1C")) << calls[0];
+
+    // names are checked before anything is imported
+    fs::create_directories("bad_palettes");
+    fs::copy_file(FixturePath("palette.pal"), "bad_palettes/105.pal");
+    fs::copy_file(FixturePath("palette.pal"), "bad_palettes/castle.pal");
+    calls.clear();
+    EXPECT_THROW(lm.importCustomPalettesFromDirectory("rom.smc", "bad_palettes"), InvalidPathException);
+
+    fs::rename("bad_palettes/castle.pal", "bad_palettes/0105.pal");
+    EXPECT_THROW(lm.importCustomPalettesFromDirectory("rom.smc", "bad_palettes"), InvalidPathException);
+
+    // the same level twice
+    fs::rename("bad_palettes/0105.pal", "bad_palettes/5.pal");
+    fs::copy_file(FixturePath("palette.pal"), "bad_palettes/005.pal");
+    EXPECT_THROW(lm.importCustomPalettesFromDirectory("rom.smc", "bad_palettes"), InvalidPathException);
+
+    fs::remove("bad_palettes/5.pal");
+    fs::remove("bad_palettes/005.pal");
+    fs::copy_file(FixturePath("palette.pal"), "bad_palettes/200.pal");
+    EXPECT_THROW(lm.importCustomPalettesFromDirectory("rom.smc", "bad_palettes"), InvalidLevelException);
+    EXPECT_TRUE(calls.empty());
+
+    fs::remove_all("bad_palettes");
+}
+
+TEST(LunarMagicWrapperTest, AppliesDefaultLevelOptions) {
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return Result{ 0, {}, {} };
+    });
+    lm.setDefaultLevelImportOptions(LevelImportOption::CLEAR_SECONDARY_EXITS)
+        .setDefaultLevelExportOptions(LevelExportOption::MODIFIED_ONLY);
+
+    fs::create_directories("default_levels");
+    lm.importMultipleLevels("rom.smc", "default_levels");
+    lm.importMultipleLevels("rom.smc", "default_levels", LevelImportOption::NONE);
+    lm.importMultipleLevels("rom.smc", "default_levels", SecondaryExitPolicy::KEEP);
+    lm.exportMultipleLevels("rom.smc", "default_levels", "level ");
+
+    lm.setDefaultLevelImportOptions(std::nullopt);
+    lm.importMultipleLevels("rom.smc", "default_levels");
+
+    EXPECT_EQ(calls, std::vector<std::string>({
+        R"(-ImportMultLevels "rom.smc" "default_levels" 1)",
+        R"(-ImportMultLevels "rom.smc" "default_levels" 0)",
+        R"(-ImportMultLevels "rom.smc" "default_levels" 0)",
+        R"(-ExportMultLevels "rom.smc" "default_levels/level " 1)",
+        R"(-ImportMultLevels "rom.smc" "default_levels")"
+    }));
+
+    fs::remove_all("default_levels");
+}
+
+TEST(LunarMagicWrapperTest, ChecksForExportedGraphics) {
+    size_t calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string&) {
+        ++calls;
+        return Result{ 0, {}, {} };
+    });
+
+    fs::create_directories("exported_gfx");
+    EXPECT_FALSE(LunarMagicWrapper::hasExportedGraphics("exported_gfx/rom.smc"));
+
+    fs::create_directories("exported_gfx/Graphics");
+    for (int number{}; number <= 0x33; ++number) {
+        std::ofstream(fmt::format("exported_gfx/Graphics/GFX{:02X}.bin", number)) << "gfx";
+    }
+    EXPECT_TRUE(LunarMagicWrapper::hasExportedGraphics("exported_gfx/rom.smc"));
+
+    fs::remove("exported_gfx/Graphics/GFX33.bin");
+    EXPECT_FALSE(LunarMagicWrapper::hasExportedGraphics("exported_gfx/rom.smc"));
+
+    // only checked before importing when enabled
+    EXPECT_NO_THROW(lm.importGFX("exported_gfx/rom.smc"));
+    EXPECT_EQ(calls, 1u);
+
+    lm.setCheckExportedGraphics(true);
+    try {
+        lm.importGFX("exported_gfx/rom.smc");
+        FAIL() << "expected NoExportedGraphicsException";
+    } catch (const NoExportedGraphicsException& e) {
+        EXPECT_EQ(e.getMissingFiles(), std::vector<std::string>({ "GFX33.bin" }));
+        EXPECT_EQ(e.getPath(), fs::path("exported_gfx/Graphics"));
+    }
+    EXPECT_THROW(lm.importAllGraphics("exported_gfx/rom.smc"), NoExportedGraphicsException);
+    EXPECT_EQ(calls, 1u);
+
+    std::ofstream("exported_gfx/Graphics/gfx33.BIN") << "gfx";
+    EXPECT_NO_THROW(lm.importGFX("exported_gfx/rom.smc"));
+    EXPECT_EQ(calls, 2u);
+
+    fs::remove_all("exported_gfx");
+}
+
+TEST(LunarMagicWrapperTest, StopsOnceTimeBudgetIsSpent) {
+    size_t calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string&) {
+        ++calls;
+        std::this_thread::sleep_for(std::chrono::milliseconds(30));
+        return Result{ 0, {}, {} };
+    });
+    lm.setTimeBudget(std::chrono::milliseconds(50));
+
+    EXPECT_NO_THROW(lm.exportGFX("rom.smc"));
+    // the call that goes over the budget still finishes
+    EXPECT_NO_THROW(lm.exportGFX("rom.smc"));
+    EXPECT_GE(lm.getTimeSpent(), std::chrono::milliseconds(60));
+
+    try {
+        lm.exportGFX("rom.smc");
+        FAIL() << "expected BudgetExceededException";
+    } catch (const BudgetExceededException& e) {
+        EXPECT_EQ(e.getBudget(), std::chrono::milliseconds(50));
+        EXPECT_GE(e.getSpent(), std::chrono::milliseconds(60));
+    }
+    EXPECT_EQ(calls, 2u);
+
+    lm.resetTimeBudget();
+    EXPECT_NO_THROW(lm.exportGFX("rom.smc"));
+    EXPECT_EQ(calls, 3u);
+
+    lm.setTimeBudget(std::nullopt);
+    EXPECT_NO_THROW(lm.exportGFX("rom.smc"));
+    EXPECT_NO_THROW(lm.exportGFX("rom.smc"));
+    EXPECT_EQ(calls, 5u);
+}
+
+TEST(LunarMagicWrapperTest, ImportsGFXAndExGFXSeparately) {
+    std::vector<std::string> calls{};
+    bool fail_exgfx{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        if (call_string.starts_with("-ImportExGFX")) {
+            return Result{ fail_exgfx ? 1 : 0, { "ExGFX imported" }, {} };
+        }
+        return Result{ 0, { "GFX imported" }, {} };
+    });
+
+    const auto result{ lm.importGFXAndExGFX("gfx rom.smc") };
+
+    EXPECT_EQ(calls, std::vector<std::string>({ R"(-ImportGFX "gfx rom.smc")", R"(-ImportExGFX "gfx rom.smc")" }));
+    EXPECT_TRUE(result.succeeded);
+    EXPECT_EQ(result.out, std::vector<std::string>({ "GFX imported", "ExGFX imported" }));
+    EXPECT_FALSE(result.operation.has_value());
+
+    fail_exgfx = true;
+    try {
+        lm.importGFXAndExGFX("gfx rom.smc");
+        FAIL() << "expected LunarMagicWrapperException";
+    } catch (const LunarMagicWrapperException& e) {
+        EXPECT_EQ(e.getOperation(), Operation::IMPORT_EXGFX);
+    }
+}
+
+TEST(LunarMagicWrapperTest, DetectsModifiedLevelSlots) {
+    std::vector<uint8_t> clean(0x80000, 0);
+    // level 105's layer 1 data at 06:E000 and level 106's at 06:E100
+    clean[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x105 * 3) + 1] = 0xE0;
+    clean[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x105 * 3) + 2] = 0x06;
+    clean[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x106 * 3) + 1] = 0xE1;
+    clean[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x106 * 3) + 2] = 0x06;
+
+    // a hack where level 106 was saved into the expanded area
+    auto hack{ clean };
+    hack.resize(0x100000);
+    hack[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x106 * 3) + 1] = 0x80;
+    hack[ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + 0x106 * 3) + 2] = 0x10;
+
+    std::ofstream("empty_clean.smc", std::ios::binary).write(reinterpret_cast<const char*>(clean.data()),
+                                                             static_cast<std::streamsize>(clean.size()));
+    std::ofstream("empty_hack.smc", std::ios::binary).write(reinterpret_cast<const char*>(hack.data()),
+                                                            static_cast<std::streamsize>(hack.size()));
+
+    EXPECT_TRUE(LunarMagicWrapper::isLevelEmpty("empty_hack.smc", "empty_clean.smc", 0x105));
+    EXPECT_FALSE(LunarMagicWrapper::isLevelEmpty("empty_hack.smc", "empty_clean.smc", 0x106));
+    EXPECT_THROW(LunarMagicWrapper::isLevelEmpty("empty_hack.smc", "empty_clean.smc", 0x200), InvalidLevelException);
+
+    fs::remove("empty_clean.smc");
+    fs::remove("empty_hack.smc");
+}
+
+TEST(LunarMagicWrapperTest, RejectsSA1SizesForNonSA1ROMs) {
+    // a 4MB ROM with an internal header that passes every check, with the given map mode
+    const auto write_rom{ [](const fs::path& path, uint8_t map_mode) {
+        std::vector<char> bytes(0x400000, 0);
+        const auto header{ static_cast<size_t>(ROM::LOROM_HEADER) };
+        bytes[header + 0x15] = static_cast<char>(map_mode);
+        bytes[header + 0x1C] = static_cast<char>(0xCB);
+        bytes[header + 0x1D] = static_cast<char>(0xED);
+        bytes[header + 0x1E] = 0x34;
+        bytes[header + 0x1F] = 0x12;
+        bytes[header + 0x3D] = static_cast<char>(0x80);
+        std::ofstream(path, std::ios::binary).write(bytes.data(), static_cast<std::streamsize>(bytes.size()));
+    } };
+    write_rom("lorom.smc", 0x20);
+    write_rom("sa1.smc", 0x23);
+
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return Result{ 0, {}, {} };
+    });
+
+    try {
+        lm.expandROM("lorom.smc", ROMSize::_8MB_SA1);
+        FAIL() << "expected IncompatibleExpansionException";
+    } catch (const IncompatibleExpansionException& e) {
+        EXPECT_EQ(e.getFrom(), MappingMode::LOROM);
+        EXPECT_EQ(e.getTo(), ROMSize::_8MB_SA1);
+    }
+    EXPECT_TRUE(calls.empty());
+
+    lm.expandROM("sa1.smc", ROMSize::_6MB_SA1);
+    lm.expandROM("lorom.smc", ROMSize::_4MB);
+    EXPECT_EQ(calls, std::vector<std::string>({ R"(-ExpandROM "sa1.smc" 6MB_SA1)", R"(-ExpandROM "lorom.smc" 4MB)" }));
+
+    fs::remove("lorom.smc");
+    fs::remove("sa1.smc");
+}
+
+TEST(LunarMagicWrapperTest, ChainsOperationsOnOneROM) {
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return call_string.starts_with("-ImportMultLevels") ? Result{ 1, {}, { "failed" } } : Result{ 0, {}, {} };
+    });
+
+    auto operations{ lm.onROM("hack.smc") };
+    operations.importGFX().importAllMap16(FixturePath("all.map16"));
+
+    EXPECT_EQ(operations.getLog().size(), 2u);
+    EXPECT_EQ(operations.getLog()[1].operation, Operation::IMPORT_ALL_MAP16);
+
+    // the first failure ends the chain
+    EXPECT_THROW(operations.importMultipleLevels("levels").exportGFX(), LunarMagicWrapperException);
+    EXPECT_EQ(operations.getLog().size(), 2u);
+    ASSERT_EQ(calls.size(), 3u);
+    EXPECT_EQ(calls[0], R"(-ImportGFX "hack.smc")");
+    EXPECT_TRUE(calls[1].starts_with(R"(-ImportAllMap16 "hack.smc" )"));
+    EXPECT_EQ(calls[2], R"(-ImportMultLevels "hack.smc" "levels")");
+}
+
+TEST(LunarMagicWrapperTest, WritesOutputIntoStream) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string&) {
+        return Result{ 2, { "Lunar Magic 3.40", "Exporting graphics..." }, { "Error: no ROM" } };
+    });
+
+    std::ostringstream output{};
+
+    EXPECT_EQ(lm.exportGFXInto("rom.smc", output), 2);
+    EXPECT_EQ(output.str(), "Lunar Magic 3.40\nExporting graphics...\nError: no ROM\n");
+}
+
+TEST(LunarMagicWrapperTest, EnsureSizeOnlyExpandsSmallerROMs) {
+    std::ofstream("small.smc", std::ios::binary) << std::string(0x100000, '\0');
+
+    // stands in for expanding, grows the ROM to 2MB
+    size_t expansions{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        EXPECT_EQ(call_string, R"(-ExpandROM "small.smc" 2MB)");
+        ++expansions;
+        fs::resize_file("small.smc", 0x200000);
+        return Result{ 0, {}, {} };
+    });
+
+    EXPECT_TRUE(lm.ensureSize("small.smc", ROMSize::_2MB));
+    EXPECT_EQ(fs::file_size("small.smc"), 0x200000u);
+
+    EXPECT_FALSE(lm.ensureSize("small.smc", ROMSize::_2MB));
+    EXPECT_EQ(expansions, 1u);
+
+    fs::remove("small.smc");
+}
+
+TEST(LunarMagicWrapperTest, ClassifiesLevelImportsAgainstCleanROM) {
+    const auto write_layer1_pointer{ [](const fs::path& rom_path, uint16_t level, const char* pointer) {
+        std::fstream rom(rom_path, std::ios::in | std::ios::out | std::ios::binary);
+        rom.seekp(static_cast<std::streamoff>(ROM::snesToPc(ROM::LAYER1_POINTER_TABLE + level * 3)));
+        rom.write(pointer, 3);
+    } };
+
+    // like in SMW, every level of the clean ROM has data, 106 has been saved by Lunar Magic before
+    const std::vector<uint8_t> bytes(0x100000, 0);
+    std::ofstream("outcome_clean.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                               static_cast<std::streamsize>(bytes.size()));
+    for (uint16_t level{}; level <= LunarMagicWrapper::MAX_LEVEL; ++level) {
+        write_layer1_pointer("outcome_clean.smc", level, "\x00\x80\x06");
+    }
+    fs::copy_file("outcome_clean.smc", "outcome.smc", fs::copy_options::overwrite_existing);
+    write_layer1_pointer("outcome.smc", 0x106, "\x00\x80\x10");
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    std::optional<uint16_t> imported_level{};
+    lm.setSpawner([&](const std::string&) {
+        if (imported_level) {
+            write_layer1_pointer("outcome.smc", *imported_level, "\x00\x90\x10");
+        }
+        return Result{ 0, {}, {} };
+    });
+
+    // without a level number, the MWL's own, 105, which is still the clean ROM's
+    imported_level = 0x105;
+    const auto created{ lm.importLevelWithOutcome("outcome.smc", FixturePath("level.mwl"), "outcome_clean.smc") };
+    EXPECT_EQ(created.status, LevelImportStatus::CREATED);
+    EXPECT_EQ(created.level_number, 0x105);
+
+    imported_level = 0x106;
+    EXPECT_EQ(lm.importLevelWithOutcome("outcome.smc", FixturePath("level.mwl"), "outcome_clean.smc", 0x106).status,
+              LevelImportStatus::OVERWRITTEN);
+
+    imported_level.reset();
+    EXPECT_EQ(lm.importLevelWithOutcome("outcome.smc", FixturePath("level.mwl"), "outcome_clean.smc", 0x107).status,
+              LevelImportStatus::UNKNOWN);
+
+    EXPECT_THROW(lm.importLevelWithOutcome("outcome.smc", FixturePath("level.mwl"), "missing_clean.smc"),
+                 MissingInputFileException);
+
+    fs::remove("outcome.smc");
+    fs::remove("outcome_clean.smc");
+}
+
+TEST(LunarMagicWrapperTest, CollapsesRepeatedOutputLines) {
+    const auto lines{ ReadLines(FixturePath("logs/repeated_progress.log")) };
+    const std::vector<std::string> collapsed{
+        "Lunar Magic 3.40", "Importing levels...", "Compressing... (x3)", "Level 105 imported.",
+        "Compressing... (x2)", "Done."
+    };
+
+    EXPECT_EQ(LunarMagicWrapper::collapseRepeatedLines(lines), collapsed);
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string&) { return Result{ 0, lines, {} }; });
+
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, lines);
+    lm.setCollapseRepeatedLines(true);
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, collapsed);
+
+    EXPECT_EQ(LunarMagicWrapper::collapseRepeatedLines({
+        "2026-01-31T12:00:00.250Z Compressing...", "2026-01-31T12:00:00.500Z Compressing...",
+        "2026-01-31T12:00:01.000Z Done."
+    }), std::vector<std::string>({ "2026-01-31T12:00:00.250Z Compressing... (x2)", "2026-01-31T12:00:01.000Z Done." }));
+}
+
+TEST(LunarMagicWrapperTest, ParsesLevelNumberFromFileName) {
+    EXPECT_EQ(LunarMagicWrapper::levelNumberFromFileName("levels/level 105.mwl"), 0x105);
+    EXPECT_EQ(LunarMagicWrapper::levelNumberFromFileName("levels/level_01C.mwl"), 0x1C);
+    EXPECT_EQ(LunarMagicWrapper::levelNumberFromFileName("levels/level_1C.mwl"), std::nullopt);
+    EXPECT_EQ(LunarMagicWrapper::levelNumberFromFileName("levels/level 0105.mwl"), 0x105);
+    EXPECT_EQ(LunarMagicWrapper::levelNumberFromFileName("levels/castle.mwl"), std::nullopt);
+}
+
+TEST(LunarMagicWrapperTest, FindsMWLsNamedAfterOtherLevels) {
+    fs::remove_all("mismatch_test");
+    fs::copy(FixturePath("levels"), "mismatch_test");
+    fs::rename("mismatch_test/level 003.mwl", "mismatch_test/level 007.mwl");
+    std::ofstream("mismatch_test/level 008.mwl") << "not an MWL";
+
+    const auto mismatches{ LunarMagicWrapper::checkLevelFileNames("mismatch_test") };
+
+    ASSERT_EQ(mismatches.size(), 1u);
+    EXPECT_EQ(mismatches[0].path.filename(), "level 007.mwl");
+    EXPECT_EQ(mismatches[0].file_name_level, 0x007);
+    EXPECT_EQ(mismatches[0].embedded_level, 0x003);
+
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setCheckLevelFileNames(true);
+    EXPECT_THROW(lm.importMultipleLevels("rom.smc", "mismatch_test"), LevelFileNameMismatchException);
+
+    fs::remove_all("mismatch_test");
+}
+
+TEST(LunarMagicWrapperTest, OptionsDefaultToNone) {
+    struct Config {
+        LevelExportOption export_options{};
+        LevelImportOption import_options{};
+    };
+
+    constexpr Config config{};
+    static_assert(config.export_options == LevelExportOption::NONE);
+    static_assert(config.import_options == LevelImportOption::NONE);
+
+    EXPECT_EQ(LevelImportOption{} | LevelImportOption::CLEAR_SECONDARY_EXITS, LevelImportOption::CLEAR_SECONDARY_EXITS);
+    EXPECT_EQ(LevelExportOption::MODIFIED_ONLY & LevelExportOption{}, LevelExportOption::NONE);
+    EXPECT_EQ(LevelExportOption::MODIFIED_ONLY & LevelExportOption::MODIFIED_ONLY, LevelExportOption::MODIFIED_ONLY);
+}
+
+TEST(LunarMagicWrapperTest, InsertingExGFXOutOfRangeThrows) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    bool called{};
+    lm.setSpawner([&](const std::string&) {
+        called = true;
+        return Result{ 0, {}, {} };
+    });
+
+    EXPECT_THROW(lm.insertExGFX("rom.smc", "ExGFX7F.bin", 0x7F), std::invalid_argument);
+    EXPECT_THROW(lm.insertExGFX("rom.smc", "ExGFX1000.bin", 0x1000), std::invalid_argument);
+    EXPECT_FALSE(called);
+}
+
+TEST(LunarMagicWrapperTest, WritingToNonROMPathThrows) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    bool called{};
+    lm.setSpawner([&](const std::string&) {
+        called = true;
+        return Result{ 0, {}, {} };
+    });
+
+    std::ofstream("level 105.mwl") << "level";
+    EXPECT_THROW(lm.importLevel("level 105.mwl", "level 105.mwl"), InvalidPathException);
+    EXPECT_FALSE(called);
+
+    fs::remove("level 105.mwl");
+}
+
+#ifndef _WIN32
+TEST(LunarMagicWrapperTest, FreeSpaceGuardRejectsFullROMs) {
+    std::ofstream("noop_lm.sh") << "#!/bin/sh\n";
+    fs::permissions("noop_lm.sh", fs::perms::owner_all);
+
+    std::vector<uint8_t> bytes(0x100000, 0x11);
+    std::ofstream("full.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                      static_cast<std::streamsize>(bytes.size()));
+    std::fill(bytes.begin() + ROM::ORIGINAL_ROM_SIZE, bytes.end(), 0);
+    std::ofstream("empty.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                       static_cast<std::streamsize>(bytes.size()));
+
+    LunarMagicWrapper lm{ fs::absolute("noop_lm.sh") };
+    lm.setFreeSpaceGuard(0x1000);
+
+    EXPECT_NO_THROW(lm.importGFX("empty.smc"));
+    try {
+        lm.importGFX("full.smc");
+        FAIL() << "expected OutOfSpaceException";
+    } catch (const OutOfSpaceException& e) {
+        EXPECT_EQ(e.getUsed(), 0x100000u);
+        EXPECT_EQ(e.getCapacity(), 0x100000u);
+        EXPECT_EQ(e.getOperation(), Operation::IMPORT_GFX);
+    }
+
+    fs::remove("noop_lm.sh");
+    fs::remove("full.smc");
+    fs::remove("empty.smc");
+}
+
+TEST(LunarMagicWrapperTest, BenchmarksCompressionFormatsOnCopies) {
+    // fills a different amount of the expanded area per format
+    std::ofstream("compression_lm.sh") << "#!/bin/sh\n"
+                                          "case \"$3\" in\n"
+                                          "LC_LZ2_Orig) size=12288 ;;\n"
+                                          "LC_LZ2_Speed) size=16384 ;;\n"
+                                          "LC_LZ3) size=4096 ;;\n"
+                                          "esac\n"
+                                          "printf '%*s' $size | dd of=\"$2\" bs=1 seek=524288 conv=notrunc 2>/dev/null\n";
+    fs::permissions("compression_lm.sh", fs::perms::owner_all);
+
+    std::vector<uint8_t> bytes(0x100000, 0);
+    std::fill(bytes.begin(), bytes.begin() + ROM::ORIGINAL_ROM_SIZE, 0x11);
+    std::ofstream("benchmark.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                           static_cast<std::streamsize>(bytes.size()));
+
+    LunarMagicWrapper lm{ fs::absolute("compression_lm.sh") };
+    const auto benchmarks{ lm.benchmarkCompression("benchmark.smc", {
+        CompressionOption::LC_LZ2_Orig, CompressionOption::LC_LZ2_Speed, CompressionOption::LC_LZ3
+    }) };
+
+    ASSERT_EQ(benchmarks.size(), 3u);
+    EXPECT_EQ(benchmarks[0].report.format, CompressionOption::LC_LZ3);
+    EXPECT_EQ(benchmarks[1].report.format, CompressionOption::LC_LZ2_Orig);
+    EXPECT_EQ(benchmarks[2].report.format, CompressionOption::LC_LZ2_Speed);
+    EXPECT_EQ(benchmarks[0].report.after_bytes, ROM::ORIGINAL_ROM_SIZE + 0x1000);
+    EXPECT_EQ(benchmarks[0].report.before_bytes, ROM::ORIGINAL_ROM_SIZE);
+    EXPECT_GT(benchmarks[0].duration.count(), 0);
+    EXPECT_EQ(ROM::fromFile("benchmark.smc").usedBytes(), ROM::ORIGINAL_ROM_SIZE);
+
+    fs::remove("compression_lm.sh");
+    fs::remove("benchmark.smc");
+}
+
+TEST(LunarMagicWrapperTest, ListsSpritesUsedByExportedLevels) {
+    // stands in for exporting, copies the fixture levels to where Lunar Magic would put them
+    std::ofstream("sprites_lm.sh") << "#!/bin/sh\n"
+                                      "cp \"" << FixturePath("levels").string() << "\"/*.mwl \"$(dirname \"$3\")\"\n";
+    fs::permissions("sprites_lm.sh", fs::perms::owner_all);
+
+    const std::set<uint16_t> expected{
+        0x09, 0x0B, 0x13, 0x41, 0x42, 0x43, 0x4B, 0x4F, 0x7B, 0x82, 0x8C, 0x92, 0xB9, 0xC3, 0xCD, 0xCF, 0xD0,
+        0xD9, 0xE1
+    };
+
+    EXPECT_EQ(LunarMagicWrapper::usedSprites(FixturePath("levels")), expected);
+
+    // extensions are compared ignoring case, like Windows does
+    fs::create_directories("upper_case_levels");
+    for (const auto& entry : fs::directory_iterator(FixturePath("levels"))) {
+        fs::copy_file(entry.path(), fs::path("upper_case_levels") / (entry.path().stem().string() + ".MWL"),
+                      fs::copy_options::overwrite_existing);
+    }
+    EXPECT_EQ(LunarMagicWrapper::usedSprites("upper_case_levels"), expected);
+    fs::remove_all("upper_case_levels");
+
+    LunarMagicWrapper lm{ fs::absolute("sprites_lm.sh") };
+    EXPECT_EQ(lm.listUsedSprites("rom.smc"), expected);
+
+    fs::remove("sprites_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, ReportsActivityWhileRunning) {
+    std::ofstream("slow_lm.sh") << "#!/bin/sh\nsleep 0.5\necho done\n";
+    fs::permissions("slow_lm.sh", fs::perms::owner_all);
+
+    size_t activity{};
+    LunarMagicWrapper lm{ fs::absolute("slow_lm.sh") };
+    lm.onActivity([&] { ++activity; }).setActivityInterval(std::chrono::milliseconds(20));
+
+    EXPECT_EQ(lm.exportGFX("rom.smc").out, std::vector<std::string>({ "done" }));
+    EXPECT_GE(activity, 5u);
+
+    fs::remove("slow_lm.sh");
+}
+
+// everything that's handed between threads has to be copyable, exceptions also for std::exception_ptr
+static_assert(std::is_copy_constructible_v<LunarMagicWrapper> && std::is_move_constructible_v<LunarMagicWrapper>);
+static_assert(std::is_copy_constructible_v<Result> && std::is_copy_constructible_v<BatchResult>);
+static_assert(std::is_copy_constructible_v<LunarMagicWrapperException>
+              && std::is_copy_constructible_v<InvalidPathException>
+              && std::is_copy_constructible_v<UnexpectedMappingException>);
+
+TEST(LunarMagicWrapperTest, RunsOperationsFromOtherThreads) {
+    std::ofstream("echo_lm.sh") << "#!/bin/sh\necho \"$2\"\n";
+    fs::permissions("echo_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("echo_lm.sh") };
+
+    std::thread([moved = std::move(lm)]() mutable {
+        EXPECT_EQ(moved.exportGFX("moved.smc").out, std::vector<std::string>({ "moved.smc" }));
+    }).join();
+
+    LunarMagicWrapper shared{ fs::absolute("echo_lm.sh") };
+    std::vector<std::thread> threads{};
+    for (size_t i{}; i < 4; ++i) {
+        threads.emplace_back([&shared, i] {
+            const auto rom{ fmt::format("rom{}.smc", i) };
+            EXPECT_EQ(shared.exportGFX(rom).out, std::vector<std::string>({ rom }));
+        });
+    }
+    for (auto& thread : threads) {
+        thread.join();
+    }
+
+    fs::remove("echo_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, ImportsExGFXFromSeveralDirectories) {
+    fs::remove_all("exgfx_test");
+    fs::create_directories("exgfx_test/enemies");
+    fs::create_directories("exgfx_test/tilesets");
+    fs::create_directories("exgfx_test/more_tilesets");
+    std::ofstream("exgfx_test/enemies/ExGFX80.bin") << "enemies";
+    std::ofstream("exgfx_test/tilesets/ExGFX81.bin") << "tiles";
+    std::ofstream("exgfx_test/more_tilesets/exgfx81.bin") << "more tiles";
+    std::ofstream("exgfx_test/rom.smc") << "rom";
+
+    // records what's in the ExGraphics folder next to the ROM it's given
+    std::ofstream("exgfx_lm.sh") << "#!/bin/sh\nls \"$(dirname \"$2\")/ExGraphics\" > \""
+                                 << fs::absolute("exgfx_test/imported.log").string() << "\"\n";
+    fs::permissions("exgfx_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("exgfx_lm.sh") };
+
+    EXPECT_EQ(lm.importExGFXFromDirectories("exgfx_test/rom.smc", { "exgfx_test/enemies", "exgfx_test/tilesets" }),
+              std::vector<fs::path>({ "exgfx_test/enemies/ExGFX80.bin", "exgfx_test/tilesets/ExGFX81.bin" }));
+    EXPECT_EQ(ReadLines("exgfx_test/imported.log"), std::vector<std::string>({ "ExGFX80.bin", "ExGFX81.bin" }));
+
+    fs::remove("exgfx_test/imported.log");
+    try {
+        lm.importExGFXFromDirectories("exgfx_test/rom.smc", { "exgfx_test/tilesets", "exgfx_test/more_tilesets" });
+        FAIL() << "expected InvalidPathException";
+    } catch (const InvalidPathException& e) {
+        EXPECT_NE(std::string(e.what()).find("exgfx_test/tilesets"), std::string::npos);
+        EXPECT_EQ(e.getPath(), fs::path("exgfx_test/more_tilesets/exgfx81.bin"));
+    }
+    EXPECT_FALSE(fs::exists("exgfx_test/imported.log"));
+
+    fs::remove_all("exgfx_test");
+    fs::remove("exgfx_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, InsertsOneExGFXFile) {
+    fs::remove_all("insert_exgfx");
+    fs::create_directories("insert_exgfx/ExGraphics");
+    std::ofstream("insert_exgfx/ExGraphics/ExGFX90.bin") << "project";
+    std::ofstream("insert_exgfx/tiles.bin") << "tiles";
+    std::ofstream("insert_exgfx/rom.smc") << "rom";
+
+    // records what's in the ExGraphics folder next to the ROM it's given
+    std::ofstream("exgfx_lm.sh") << "#!/bin/sh\nls \"$(dirname \"$2\")/ExGraphics\" > \""
+                                 << fs::absolute("insert_exgfx/imported.log").string() << "\"\n";
+    fs::permissions("exgfx_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("exgfx_lm.sh") };
+
+    lm.insertExGFX("insert_exgfx/rom.smc", "insert_exgfx/tiles.bin", 0x80);
+    EXPECT_EQ(ReadLines("insert_exgfx/imported.log"), std::vector<std::string>({ "ExGFX80.bin" }));
+    EXPECT_EQ(ReadLines("insert_exgfx/ExGraphics/ExGFX90.bin"), std::vector<std::string>({ "project" }));
+    EXPECT_FALSE(fs::exists("insert_exgfx/ExGraphics/ExGFX80.bin"));
+
+    fs::remove("insert_exgfx/imported.log");
+    EXPECT_THROW(lm.insertExGFX("insert_exgfx/rom.smc", "insert_exgfx/missing.bin", 0x80), MissingInputFileException);
+    EXPECT_FALSE(fs::exists("insert_exgfx/imported.log"));
+
+    fs::remove_all("insert_exgfx");
+    fs::remove("exgfx_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, ExportsAndImportsCredits) {
+    fs::remove_all("credits_test");
+    fs::create_directories("credits_test");
+    for (const auto* rom : { "credits_test/clean.smc", "credits_test/rom.smc", "credits_test/other.smc" }) {
+        const std::vector<char> bytes(0x80000);
+        std::ofstream(rom, std::ios::binary).write(bytes.data(), static_cast<std::streamsize>(bytes.size()));
+    }
+
+    // -TransferCredits "destination" "source", with the credits being 32 bytes at 0x1000, marking the destination
+    // at 0x7FDC like the hacks and checksum Lunar Magic writes on every transfer
+    std::ofstream("credits_lm.sh") << "#!/bin/sh\n"
+                                   << "dd if=\"$3\" of=\"$2\" bs=1 skip=4096 seek=4096 count=32 conv=notrunc 2>/dev/null\n"
+                                   << "printf LM | dd of=\"$2\" bs=1 seek=32732 conv=notrunc 2>/dev/null\n";
+    fs::permissions("credits_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("credits_lm.sh") };
+
+    lm.importCredits("credits_test/rom.smc", FixturePath("credits.txt"), "credits_test/clean.smc");
+    const auto rom{ ROM::fromFile("credits_test/rom.smc") };
+    EXPECT_EQ(std::string(rom.bytes().begin() + 0x1000, rom.bytes().begin() + 0x1020),
+              "THANKS FOR PLAYING, TEST SUITE!!");
+
+    EXPECT_EQ(std::string(rom.bytes().begin() + 0x7FDC, rom.bytes().begin() + 0x7FDE), "LM");
+
+    // the mark is written by every transfer, so it isn't part of the credits
+    lm.exportCredits("credits_test/rom.smc", "credits_test/exported/credits.txt", "credits_test/clean.smc");
+    EXPECT_EQ(ReadLines("credits_test/exported/credits.txt"), ReadLines(FixturePath("credits.txt")));
+
+    // only ever copies of the clean ROM are written to
+    EXPECT_EQ(ROM::fromFile("credits_test/clean.smc").bytes(), std::vector<uint8_t>(0x80000));
+
+    lm.importCredits("credits_test/other.smc", "credits_test/exported/credits.txt", "credits_test/clean.smc");
+    EXPECT_EQ(ROM::fromFile("credits_test/other.smc").bytes(), ROM::fromFile("credits_test/rom.smc").bytes());
+
+    std::ofstream("credits_test/broken.txt") << "THANKS FOR PLAYING\n";
+    EXPECT_THROW(lm.importCredits("credits_test/rom.smc", "credits_test/broken.txt", "credits_test/clean.smc"),
+                 ROMException);
+    EXPECT_THROW(lm.importCredits("credits_test/rom.smc", "credits_test/missing.txt", "credits_test/clean.smc"),
+                 MissingInputFileException);
+
+    fs::remove_all("credits_test");
+    fs::remove("credits_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, ExportsAndImportsGlobalExanimation) {
+    fs::remove_all("exanimation_test");
+    fs::create_directories("exanimation_test");
+    for (const auto* rom : { "exanimation_test/clean.smc", "exanimation_test/rom.smc", "exanimation_test/other.smc" }) {
+        const std::vector<char> bytes(0x80000);
+        std::ofstream(rom, std::ios::binary).write(bytes.data(), static_cast<std::streamsize>(bytes.size()));
+    }
+    {
+        // some animation data at 0x2000 and the pointer to it at 0x100
+        std::fstream rom("exanimation_test/rom.smc", std::ios::binary | std::ios::in | std::ios::out);
+        rom.seekp(0x100);
+        rom.write("\x00\xA0\x00", 3);
+        rom.seekp(0x2000);
+        rom.write("ANIMATION", 9);
+    }
+
+    // -TransferLevelGlobalExAnim "destination" "source", copying both places
+    std::ofstream("exanimation_lm.sh") << "#!/bin/sh\n"
+                                       << "dd if=\"$3\" of=\"$2\" bs=1 skip=256 seek=256 count=3 conv=notrunc 2>/dev/null\n"
+                                       << "dd if=\"$3\" of=\"$2\" bs=1 skip=8192 seek=8192 count=16 conv=notrunc 2>/dev/null\n";
+    fs::permissions("exanimation_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("exanimation_lm.sh") };
+
+    lm.exportGlobalExanimation("exanimation_test/rom.smc", "exanimation_test/exanimation.txt",
+                               "exanimation_test/clean.smc");
+    // only the bytes that differ from the clean ROM, the pointer's low byte was 00 already
+    EXPECT_EQ(ReadLines("exanimation_test/exanimation.txt"), std::vector<std::string>({
+        "000101: A0",
+        "002000: 41 4E 49 4D 41 54 49 4F 4E"
+    }));
+
+    lm.importGlobalExanimation("exanimation_test/other.smc", "exanimation_test/exanimation.txt",
+                               "exanimation_test/clean.smc");
+    EXPECT_EQ(ROM::fromFile("exanimation_test/other.smc").bytes(), ROM::fromFile("exanimation_test/rom.smc").bytes());
+
+    // into a ROM that has been worked on, right next to the animation too, only the transferred bytes change
+    std::vector<uint8_t> modified(0x100000, 0x00);
+    std::fill_n(modified.begin() + 0x1F00, 0x200, 0xEE);
+    std::fill_n(modified.begin() + 0x80000, 0x1000, 0xAB);
+    std::ofstream("exanimation_test/modified.smc", std::ios::binary)
+        .write(reinterpret_cast<const char*>(modified.data()), static_cast<std::streamsize>(modified.size()));
+
+    lm.importGlobalExanimation("exanimation_test/modified.smc", "exanimation_test/exanimation.txt",
+                               "exanimation_test/clean.smc");
+
+    auto expected{ modified };
+    std::copy_n("\x00\xA0\x00", 3, expected.begin() + 0x100);
+    std::fill_n(expected.begin() + 0x2000, 0x10, 0x00);
+    std::copy_n("ANIMATION", 9, expected.begin() + 0x2000);
+    EXPECT_EQ(ROM::fromFile("exanimation_test/modified.smc").bytes(), expected);
+
+    EXPECT_THROW(lm.importGlobalExanimation("exanimation_test/other.smc", "exanimation_test/missing.txt",
+                                            "exanimation_test/clean.smc"), MissingInputFileException);
+    EXPECT_THROW(lm.exportGlobalExanimation("exanimation_test/rom.smc", "exanimation_test/exanimation.txt",
+                                            "exanimation_test/missing.smc"), MissingInputFileException);
+
+    fs::remove_all("exanimation_test");
+    fs::remove("exanimation_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, BoundsCapturedOutput) {
+    {
+        std::ofstream huge_log("huge_output.log");
+        for (size_t i{}; i < 200000; ++i) {
+            huge_log << "Line " << i << '\n';
+        }
+    }
+
+    std::ofstream("huge_lm.sh") << "#!/bin/sh\ncat \"" << fs::absolute("huge_output.log").string() << "\"\n";
+    fs::permissions("huge_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("huge_lm.sh") };
+
+    const auto unbounded{ lm.exportGFX("rom.smc") };
+    EXPECT_EQ(unbounded.out.size(), 200000u);
+    EXPECT_FALSE(unbounded.truncated);
+
+    lm.setMaxOutputLines(3);
+    const auto bounded{ lm.exportGFX("rom.smc") };
+    EXPECT_TRUE(bounded.truncated);
+    EXPECT_EQ(bounded.out, std::vector<std::string>({
+        "Line 0", "Line 1", "Line 2", "... (199994 lines omitted) ...", "Line 199997", "Line 199998", "Line 199999"
+    }));
+    EXPECT_TRUE(bounded.err.empty());
+
+    fs::remove("huge_output.log");
+    fs::remove("huge_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, EchoesCommandsToStderr) {
+    std::ofstream("echo_lm.sh") << "#!/bin/sh\n";
+    fs::permissions("echo_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("echo_lm.sh") };
+
+    testing::internal::CaptureStderr();
+    lm.exportGFX("rom.smc");
+    EXPECT_EQ(testing::internal::GetCapturedStderr(), "");
+
+    lm.setEchoCommands(true);
+    testing::internal::CaptureStderr();
+    lm.exportGFX("my hack.smc");
+    EXPECT_EQ(testing::internal::GetCapturedStderr(),
+              fmt::format("+ {} -ExportGFX \"my hack.smc\"\n", fs::absolute("echo_lm.sh").string()));
+
+    LunarMagicWrapper spawned{ "missing_lunar_magic.exe" };
+    spawned.setEchoCommands(true).setSpawner([](const std::string&) { return Result{ 0, {}, {} }; });
+    testing::internal::CaptureStderr();
+    spawned.exportLevel("rom.smc", "level 105.mwl", 0x105);
+    EXPECT_EQ(testing::internal::GetCapturedStderr(), "+ -ExportLevel \"rom.smc\" \"level 105.mwl\" 105\n");
+
+    fs::remove("echo_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, DetectsStalledInteractivePrompt) {
+    // prints a line, then hangs like Lunar Magic waiting on a dialog
+    std::ofstream("prompt_lm.sh") << "#!/bin/sh\necho \"Level 105 is locked, overwrite?\"\nexec sleep 10\n";
+    fs::permissions("prompt_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("prompt_lm.sh") };
+    lm.setPromptTimeout(std::chrono::milliseconds(300));
+
+    const auto start{ std::chrono::steady_clock::now() };
+    try {
+        lm.exportGFX("rom.smc");
+        FAIL() << "expected InteractivePromptException";
+    } catch (const InteractivePromptException& e) {
+        EXPECT_EQ(e.getStalledFor(), std::chrono::milliseconds(300));
+        EXPECT_EQ(e.getResult().out, std::vector<std::string>({ "Level 105 is locked, overwrite?" }));
+        EXPECT_FALSE(e.getResult().succeeded);
+    }
+    EXPECT_LT(std::chrono::steady_clock::now() - start, std::chrono::seconds(5));
+
+    // output that keeps coming isn't a prompt
+    std::ofstream("slow_lm.sh") << "#!/bin/sh\nfor i in 1 2 3 4 5; do echo $i; sleep 0.1; done\n";
+    fs::permissions("slow_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper slow{ fs::absolute("slow_lm.sh") };
+    slow.setPromptTimeout(std::chrono::milliseconds(300));
+    EXPECT_EQ(slow.exportGFX("rom.smc").out.size(), 5u);
+
+    fs::remove("prompt_lm.sh");
+    fs::remove("slow_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, TimestampsOutputLines) {
+    std::ofstream("slow_lm.sh") << "#!/bin/sh\necho first\nsleep 0.3\necho warning >&2\necho second\n";
+    fs::permissions("slow_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("slow_lm.sh") };
+    lm.setTimestampOutput(true);
+
+    const auto before{ std::chrono::floor<std::chrono::milliseconds>(std::chrono::system_clock::now()) };
+    const auto result{ lm.exportGFX("rom.smc") };
+    const auto after{ std::chrono::system_clock::now() };
+
+    ASSERT_EQ(result.out.size(), 2u);
+    ASSERT_EQ(result.err.size(), 1u);
+    EXPECT_TRUE(std::regex_match(result.out[0], std::regex(R"(\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d\.\d{3}Z first)")))
+        << result.out[0];
+
+    const auto first{ LunarMagicWrapper::splitTimestamp(result.out[0]) };
+    const auto warning{ LunarMagicWrapper::splitTimestamp(result.err[0]) };
+    const auto second{ LunarMagicWrapper::splitTimestamp(result.out[1]) };
+    ASSERT_TRUE(first && warning && second);
+    EXPECT_EQ(first->second, "first");
+    EXPECT_EQ(warning->second, "warning");
+    EXPECT_EQ(second->second, "second");
+
+    EXPECT_GE(first->first, before);
+    EXPECT_LE(first->first, warning->first);
+    EXPECT_LE(warning->first, second->first);
+    EXPECT_LE(second->first, after);
+    // the sleep shows up between the lines
+    EXPECT_GE(second->first - first->first, std::chrono::milliseconds(200));
+
+    EXPECT_FALSE(LunarMagicWrapper::splitTimestamp("first").has_value());
+
+    fs::remove("slow_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, CollapsesTimestampedOutputLines) {
+    std::ofstream("slow_lm.sh") << "#!/bin/sh\necho Compressing...\nsleep 0.1\necho Compressing...\necho Done.\n";
+    fs::permissions("slow_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("slow_lm.sh") };
+    lm.setTimestampOutput(true).setCollapseRepeatedLines(true);
+
+    const auto result{ lm.exportGFX("rom.smc") };
+
+    ASSERT_EQ(result.out.size(), 2u);
+    const auto compressing{ LunarMagicWrapper::splitTimestamp(result.out[0]) };
+    const auto done{ LunarMagicWrapper::splitTimestamp(result.out[1]) };
+    ASSERT_TRUE(compressing && done);
+    EXPECT_EQ(compressing->second, "Compressing... (x2)");
+    EXPECT_EQ(done->second, "Done.");
+    // the run keeps the time of its first line
+    EXPECT_GE(done->first - compressing->first, std::chrono::milliseconds(50));
+
+    fs::remove("slow_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, CancelsAsyncExport) {
+    const auto original_tmpdir{ std::getenv("TMPDIR") };
+    const std::string original_value{ original_tmpdir ? original_tmpdir : "" };
+    fs::create_directories("cancel_temp");
+    setenv("TMPDIR", fs::absolute("cancel_temp").c_str(), 1);
+
+    std::ofstream("long_lm.sh") << "#!/bin/sh\necho \"Exporting GFX...\"\nexec sleep 10\n";
+    fs::permissions("long_lm.sh", fs::perms::owner_all);
+
+    LunarMagicWrapper lm{ fs::absolute("long_lm.sh") };
+
+    std::stop_source cancel{};
+    const auto start{ std::chrono::steady_clock::now() };
+    auto exported{ lm.exportGFXAsync("rom.smc", cancel.get_token()) };
+    std::this_thread::sleep_for(std::chrono::milliseconds(200));
+    cancel.request_stop();
+
+    try {
+        exported.get();
+        FAIL() << "expected CancelledException";
+    } catch (const CancelledException& e) {
+        EXPECT_EQ(e.getOperation(), Operation::EXPORT_GFX);
+        EXPECT_EQ(e.getResult().out, std::vector<std::string>({ "Exporting GFX..." }));
+    }
+    EXPECT_LT(std::chrono::steady_clock::now() - start, std::chrono::seconds(5));
+    EXPECT_TRUE(fs::is_empty("cancel_temp"));
+
+    // already cancelled, Lunar Magic isn't started at all
+    EXPECT_THROW(lm.exportGFX("rom.smc", cancel.get_token()), CancelledException);
+
+    if (original_tmpdir) {
+        setenv("TMPDIR", original_value.c_str(), 1);
+    } else {
+        unsetenv("TMPDIR");
+    }
+
+    fs::remove_all("cancel_temp");
+    fs::remove("long_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, FallsBackWhenTempDirectoryIsUnavailable) {
+    const auto original_tmpdir{ std::getenv("TMPDIR") };
+    const std::string original_value{ original_tmpdir ? original_tmpdir : "" };
+    setenv("TMPDIR", fs::absolute("missing_temp_directory").c_str(), 1);
+
+    fs::create_directories("temp_fallback");
+    const auto temp_directory{ TestableWrapper::makeTempDirectory({ "missing_fallback", "temp_fallback" }) };
+    EXPECT_EQ(temp_directory.parent_path(), fs::path("temp_fallback"));
+    fs::remove_all(temp_directory);
+
+    try {
+        TestableWrapper::makeTempDirectory({ "missing_fallback" });
+        FAIL() << "expected TempDirectoryException";
+    } catch (const TempDirectoryException& e) {
+        EXPECT_EQ(e.getErrorCode(), std::errc::no_such_file_or_directory);
+    }
+
+    // Lunar Magic's output goes next to the ROM and is cleaned up afterwards
+    std::ofstream("echo_lm.sh") << "#!/bin/sh\necho \"$2\"\n";
+    fs::permissions("echo_lm.sh", fs::perms::owner_all);
+    LunarMagicWrapper lm{ fs::absolute("echo_lm.sh") };
+    const auto result{ lm.exportGFX("temp_fallback/rom.smc") };
+
+    if (original_tmpdir) {
+        setenv("TMPDIR", original_value.c_str(), 1);
+    } else {
+        unsetenv("TMPDIR");
+    }
+
+    EXPECT_EQ(result.out, std::vector<std::string>({ "temp_fallback/rom.smc" }));
+    EXPECT_TRUE(fs::is_empty("temp_fallback"));
+
+    fs::remove_all("temp_fallback");
+    fs::remove("echo_lm.sh");
+}
+
+TEST(LunarMagicWrapperTest, SkipsGFXImportThatChangesNothing) {
+    fs::remove_all("idempotent_test");
+    fs::create_directories("idempotent_test/Graphics");
+
+    // "GFX" of the fake ROM is the ROM itself, so exporting after an import gives back the same file
+    std::ofstream("idempotent_lm.sh") << "#!/bin/sh\n"
+                                         "dir=$(dirname \"$2\")\n"
+                                         "case \"$1\" in\n"
+                                         "-ExportGFX) mkdir -p \"$dir/Graphics\" && cp \"$2\" \"$dir/Graphics/GFX00.bin\" ;;\n"
+                                         "-ImportGFX) cp \"$dir/Graphics/GFX00.bin\" \"$2\" && echo import >> \""
+                                      << fs::absolute("idempotent_imports.log").string() << "\" ;;\n"
+                                         "esac\n";
+    fs::permissions("idempotent_lm.sh", fs::perms::owner_all);
+    std::ofstream("idempotent_test/rom.smc") << "old graphics";
+    std::ofstream("idempotent_test/Graphics/GFX00.bin") << "new graphics";
+
+    LunarMagicWrapper lm{ fs::absolute("idempotent_lm.sh") };
+
+    EXPECT_EQ(lm.importGFXIdempotent("idempotent_test/rom.smc").change, ImportChange::IMPORTED);
+    EXPECT_EQ(lm.importGFXIdempotent("idempotent_test/rom.smc").change, ImportChange::UNCHANGED);
+    EXPECT_EQ(ReadLines("idempotent_imports.log"), std::vector<std::string>({ "import" }));
+
+    fs::remove_all("idempotent_test");
+    fs::remove("idempotent_lm.sh");
+    fs::remove("idempotent_imports.log");
+}
+
+TEST(LunarMagicWrapperTest, ComparesGraphicsOfTwoROMs) {
+    const auto original_tmpdir{ std::getenv("TMPDIR") };
+    const std::string original_value{ original_tmpdir ? original_tmpdir : "" };
+    fs::remove_all("graphics_match");
+    fs::create_directories("graphics_match/temp");
+    fs::create_directories("graphics_match/variant");
+    setenv("TMPDIR", fs::absolute("graphics_match/temp").c_str(), 1);
+
+    // the fake ROM's only GFX file is the ROM itself
+    std::ofstream("graphics_match_lm.sh") << "#!/bin/sh\n"
+                                             "dir=$(dirname \"$2\")\n"
+                                             "mkdir -p \"$dir/Graphics\" && cp \"$2\" \"$dir/Graphics/GFX00.bin\"\n";
+    fs::permissions("graphics_match_lm.sh", fs::perms::owner_all);
+    std::ofstream("graphics_match/rom.smc") << "graphics";
+    std::ofstream("graphics_match/variant/rom.smc") << "graphics";
+    std::ofstream("graphics_match/other.smc") << "other graphics";
+
+    LunarMagicWrapper lm{ fs::absolute("graphics_match_lm.sh") };
+
+    EXPECT_TRUE(lm.graphicsMatch("graphics_match/rom.smc", "graphics_match/variant/rom.smc"));
+    EXPECT_FALSE(lm.graphicsMatch("graphics_match/rom.smc", "graphics_match/other.smc"));
+    EXPECT_FALSE(fs::exists("graphics_match/Graphics"));
+    EXPECT_TRUE(fs::is_empty("graphics_match/temp"));
+
+    if (original_tmpdir) {
+        setenv("TMPDIR", original_value.c_str(), 1);
+    } else {
+        unsetenv("TMPDIR");
+    }
+
+    fs::remove_all("graphics_match");
+    fs::remove("graphics_match_lm.sh");
+}
+#endif
+
+TEST(LunarMagicWrapperTest, CollectsExportedLevelsInLevelOrder) {
+    MakeExportedLevels("collect_test", {
+        "level 105.mwl", "Level 01F.MWL", "level 000.mwl", "other 001.mwl", "level notes.txt", "level final.mwl",
+        "level 00000105.mwl"
+    });
+
+    EXPECT_EQ(LunarMagicWrapper::collectExportedLevels("collect_test", "level "), std::vector<fs::path>({
+        fs::path("collect_test") / "level 000.mwl",
+        fs::path("collect_test") / "Level 01F.MWL",
+        fs::path("collect_test") / "level 105.mwl"
+    }));
+    // longer than any stem, but still the start of "level 105.mwl"
+    EXPECT_TRUE(LunarMagicWrapper::collectExportedLevels("collect_test", "level 105.m").empty());
+
+    fs::remove_all("collect_test");
+}
+
+TEST(LunarMagicWrapperTest, RejectsMissingInputFilesBeforeRunning) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+
+    try {
+        lm.importLevel("rom.smc", "missing.mwl", 0x105);
+        FAIL() << "expected MissingInputFileException";
+    } catch (const MissingInputFileException& e) {
+        EXPECT_EQ(e.getPath(), fs::path("missing.mwl"));
+    }
+
+    EXPECT_THROW(lm.importLevel("rom.smc", FixturePath("palette.pal")), MissingInputFileException);
+    EXPECT_THROW(lm.importMap16("rom.smc", "missing.map16", 0x105), MissingInputFileException);
+    EXPECT_THROW(lm.importCustomPalette("rom.smc", "missing.pal", 0x105), MissingInputFileException);
+}
+
+TEST(LunarMagicWrapperTest, RejectsInvalidMap16Files) {
+    std::vector<std::string> calls{};
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([&](const std::string& call_string) {
+        calls.push_back(call_string);
+        return Result{ 0, {}, {} };
+    });
+
+    try {
+        lm.importAllMap16("rom.smc", FixturePath("truncated.map16"));
+        FAIL() << "expected InvalidMap16Exception";
+    } catch (const InvalidMap16Exception& e) {
+        EXPECT_EQ(e.getPath(), FixturePath("truncated.map16"));
+    }
+    EXPECT_THROW(lm.importMap16("rom.smc", FixturePath("level.mwl"), 0x105), InvalidMap16Exception);
+    EXPECT_THROW(lm.importAllMap16("rom.smc", "missing.map16"), MissingInputFileException);
+    EXPECT_TRUE(calls.empty());
+
+    EXPECT_NO_THROW(lm.importMap16("rom.smc", FixturePath("partial.map16"), 0x105));
+    EXPECT_EQ(calls.size(), 1u);
+}
+
+TEST(LunarMagicWrapperTest, ChecksWhetherLevelImportWouldFit) {
+    // 0x900 free bytes, between the sizes of the two levels
+    std::vector<uint8_t> bytes(ROM::ORIGINAL_ROM_SIZE + 0x1000, 0x11);
+    std::fill(bytes.begin() + ROM::ORIGINAL_ROM_SIZE, bytes.begin() + ROM::ORIGINAL_ROM_SIZE + 0x900, 0);
+    std::ofstream("feasibility.smc", std::ios::binary).write(reinterpret_cast<const char*>(bytes.data()),
+                                                             static_cast<std::streamsize>(bytes.size()));
+
+    const auto small{ LunarMagicWrapper::checkLevelImport("feasibility.smc", FixturePath("levels/level 000.mwl")) };
+    EXPECT_TRUE(small.will_fit);
+    EXPECT_EQ(small.estimated_bytes, 0x826u);
+    EXPECT_EQ(small.free_bytes, 0x900u);
+
+    const auto large{ LunarMagicWrapper::checkLevelImport("feasibility.smc", FixturePath("level.mwl")) };
+    EXPECT_FALSE(large.will_fit);
+    EXPECT_EQ(large.estimated_bytes, 0x980u);
+
+    EXPECT_THROW(LunarMagicWrapper::checkLevelImport("feasibility.smc", "missing.mwl"), MissingInputFileException);
+
+    fs::remove("feasibility.smc");
+}
+
+TEST(LunarMagicWrapperTest, DetectsVersionFromBanner) {
+    const auto lines{ ReadLines(FixturePath("logs/level_imported.log")) };
+
+    EXPECT_EQ(parseBanner(lines), (LunarMagicVersion{ 3, 40 }));
+    EXPECT_EQ(parseBanner(lines)->toString(), "3.40");
+    EXPECT_EQ(parseBanner({ "Importing graphics..." }), std::nullopt);
+    EXPECT_EQ(parseBanner({}), std::nullopt);
+
+    EXPECT_EQ(Result(0, lines, {}).detected_version, (LunarMagicVersion{ 3, 40 }));
+    EXPECT_EQ(Result(0, { "Level 105 imported." }, {}).detected_version, std::nullopt);
+}
+
+TEST(LunarMagicWrapperTest, AttachesBytesWritten) {
+    const auto lines{ ReadLines(FixturePath("logs/bytes_written.log")) };
+
+    EXPECT_EQ(Result(0, lines, {}).bytes_written, 32768u + 2018u);
+    EXPECT_EQ(Result(0, { "Level 105 imported." }, {}).bytes_written, std::nullopt);
+    EXPECT_NO_THROW(Result(0, { "99999999999999999999999 bytes written" }, {}));
+}
+
+TEST(LunarMagicWrapperTest, JoinsAndSearchesResultLines) {
+    const Result result{ 1, { "Lunar Magic 3.40", "Importing GFX..." }, { "Error: GFX01.bin is too big" } };
+
+    EXPECT_EQ(result.joinedOut(), "Lunar Magic 3.40\nImporting GFX...");
+    EXPECT_EQ(result.joinedErr(), "Error: GFX01.bin is too big");
+    EXPECT_EQ(result.findLine("GFX"), "Importing GFX...");
+    EXPECT_EQ(result.findLine("Error"), "Error: GFX01.bin is too big");
+    EXPECT_TRUE(result.containsLine("too big"));
+    EXPECT_FALSE(result.containsLine("ExGFX"));
+
+    const Result empty{ 0, {}, {} };
+    EXPECT_EQ(empty.joinedOut(), "");
+    EXPECT_EQ(empty.joinedErr(), "");
+    EXPECT_EQ(empty.findLine(""), std::nullopt);
+    EXPECT_FALSE(empty.containsLine(""));
+}
+
+TEST(LunarMagicWrapperTest, ExportsLevelWithSummary) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+    lm.setSpawner([](const std::string& call_string) {
+        // -ExportLevel "rom" "mwl" level
+        const auto mwl_start{ call_string.find('"', call_string.find('"', call_string.find('"') + 1) + 1) + 1 };
+        const auto mwl_path{ call_string.substr(mwl_start, call_string.find('"', mwl_start) - mwl_start) };
+        fs::copy_file(FixturePath("level.mwl"), mwl_path, fs::copy_options::overwrite_existing);
+        return Result{ 0, {}, {} };
+    });
+
+    fs::create_directories("summary_export");
+    const auto text{ lm.exportLevelWithSummary("rom.smc", "summary_export/level 105.mwl", 0x105) };
+
+    EXPECT_TRUE(text.result.succeeded);
+    EXPECT_TRUE(fs::exists("summary_export/level 105.mwl"));
+    EXPECT_EQ(text.summary_path, fs::path("summary_export/level 105.txt"));
+    EXPECT_EQ(text.summary.screen_count, 20);
+    EXPECT_EQ(text.summary.screen_exits.size(), 1u);
+    const auto lines{ ReadLines(text.summary_path) };
+    ASSERT_FALSE(lines.empty());
+    EXPECT_EQ(lines[0], "Level: 105");
+
+    const auto json{ lm.exportLevelWithSummary("rom.smc", "summary_export/level 105.mwl", 0x105,
+                                               SummaryFormat::JSON) };
+    EXPECT_EQ(json.summary_path, fs::path("summary_export/level 105.json"));
+    std::ifstream json_file(json.summary_path);
+    const std::string contents{ std::istreambuf_iterator<char>(json_file), std::istreambuf_iterator<char>() };
+    EXPECT_EQ(contents, json.summary.toJSON());
+    EXPECT_NE(contents.find(R"("level_number": 261)"), std::string::npos);
+
+    json_file.close();
+    fs::remove_all("summary_export");
+}
+
+TEST(LunarMagicWrapperTest, ComparesOperationAcrossWrappers) {
+    LunarMagicWrapper lm_333{ "lunar_magic_333.exe" };
+    lm_333.setSpawner([](const std::string&) {
+        return Result{ 0, { "Lunar Magic 3.33", "Importing GFX..." }, {} };
+    });
+    LunarMagicWrapper lm_340{ "lunar_magic_340.exe" };
+    lm_340.setSpawner([](const std::string&) {
+        return Result{ 1, { "Lunar Magic 3.40", "Importing GFX...", "GFX01.bin is too big" }, { "Error" } };
+    });
+
+    const auto comparison{ lm_333.compareWith(lm_340, [](LunarMagicWrapper& lm) {
+        return lm.importGFX("rom.smc");
+    }) };
+
+    EXPECT_TRUE(comparison.result.succeeded);
+    // the failure was thrown, but still compared
+    EXPECT_FALSE(comparison.other_result.succeeded);
+    EXPECT_EQ(comparison.other_result.detected_version, (LunarMagicVersion{ 3, 40 }));
+    EXPECT_FALSE(comparison.sameOutcome());
+    EXPECT_FALSE(comparison.identical());
+    EXPECT_EQ(comparison.out_differences, std::vector<std::string>({
+        "line 1: 'Lunar Magic 3.33' vs 'Lunar Magic 3.40'",
+        "line 3: nothing vs 'GFX01.bin is too big'"
+    }));
+    EXPECT_EQ(comparison.err_differences, std::vector<std::string>({ "line 1: nothing vs 'Error'" }));
+
+    EXPECT_TRUE(lm_333.compareWith(lm_333, [](LunarMagicWrapper& lm) {
+        return lm.exportGFX("rom.smc");
+    }).identical());
+}
+
+TEST(LunarMagicWrapperTest, RejectsMalformedTitleMoves) {
+    LunarMagicWrapper lm{ "missing_lunar_magic.exe" };
+
+    EXPECT_EQ(LunarMagicWrapper::defaultTitleMovesPath(fs::path("project") / "hack.smc"),
+              fs::path("project") / "hack.zst");
+
+    std::ofstream("hack.zst", std::ios::binary) << "not a savestate";
+    try {
+        lm.importTitleMoves("hack.smc");
+        FAIL() << "expected InvalidTitleMovesException";
+    } catch (const InvalidTitleMovesException& e) {
+        EXPECT_EQ(e.getPath(), fs::path("hack.zst"));
+    }
+
+    std::ofstream("hack.zst", std::ios::binary) << LunarMagicWrapper::ZST_SIGNATURE << "143";
+    EXPECT_THROW(lm.importTitleMoves("hack.smc"), InvalidTitleMovesException);
+
+    fs::remove("hack.zst");
+    EXPECT_THROW(lm.importTitleMoves("hack.smc"), MissingInputFileException);
+}